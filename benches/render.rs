@@ -0,0 +1,24 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use lab4_g::framebuffer::Framebuffer;
+use lab4_g::render::{benchmark_scene, render, DrawOptions, RenderCache, RenderConfig};
+use lab4_g::shaders::ShaderKind;
+
+// Un frame completo de la escena fija (icosfera + sol) a 800x600
+fn render_frame(c: &mut Criterion) {
+    let (width, height) = (800, 600);
+    let (uniforms, vertices) = benchmark_scene(width, height);
+    let config = RenderConfig::default();
+    let options = DrawOptions::default();
+    let mut framebuffer = Framebuffer::new(width, height);
+
+    c.bench_function("render_sol_800x600", |b| {
+        b.iter(|| {
+            framebuffer.clear();
+            let mut cache = RenderCache::new();
+            render(&mut framebuffer, &uniforms, &vertices, ShaderKind::Sol, &mut cache, &config, &options).unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, render_frame);
+criterion_main!(benches);
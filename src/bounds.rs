@@ -0,0 +1,187 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat4, dot};
+use crate::vertex::Vertex;
+use crate::camera::Camera;
+use crate::Uniforms;
+
+// Rectángulo en coordenadas de pantalla (píxeles)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl Rect {
+    pub fn width(&self) -> f32 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max_y - self.min_y
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        ((self.min_x + self.max_x) * 0.5, (self.min_y + self.max_y) * 0.5)
+    }
+}
+
+// Vértices con w por debajo de esto están detrás de la cámara o sobre ella
+const MIN_CLIP_W: f32 = 1e-4;
+
+// Rectángulo en pantalla que ocupa la malla con las matrices de uniforms.
+// Si algún vértice queda detrás de la cámara la proyección no está acotada y
+// se devuelve la pantalla completa (conservador, sirve como scissor); si todos
+// quedan detrás, None
+pub fn screen_bounds(vertex_array: &[Vertex], uniforms: &Uniforms) -> Option<Rect> {
+    let transform = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix;
+    let mut bounds: Option<Rect> = None;
+    let mut behind = false;
+
+    for vertex in vertex_array {
+        let clip = transform * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+        if clip.w <= MIN_CLIP_W {
+            behind = true;
+            continue;
+        }
+
+        let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+        let screen = uniforms.viewport_matrix * ndc;
+        let point = Vec3::new(screen.x, screen.y, screen.z);
+
+        bounds = Some(match bounds {
+            Some(rect) => Rect {
+                min_x: rect.min_x.min(point.x),
+                min_y: rect.min_y.min(point.y),
+                max_x: rect.max_x.max(point.x),
+                max_y: rect.max_y.max(point.y),
+            },
+            None => Rect { min_x: point.x, min_y: point.y, max_x: point.x, max_y: point.y },
+        });
+    }
+
+    match bounds {
+        Some(_) if behind => Some(screen_rect(uniforms)),
+        other => other,
+    }
+}
+
+// Plano cercano mínimo para que la proyección no degenere
+const MIN_NEAR: f32 = 0.01;
+
+// near/far ajustados a la esfera envolvente (en mundo) de la malla, medidos
+// sobre el eje de vista de la cámara. Sirven directamente para perspective y
+// dan la mejor precisión posible al z-buffer para esa malla
+pub fn fit_depth_range(vertex_array: &[Vertex], model_matrix: &Mat4, camera: &Camera) -> (f32, f32) {
+    let world: Vec<Vec3> = vertex_array
+        .iter()
+        .map(|vertex| {
+            let position = model_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+            Vec3::new(position.x, position.y, position.z)
+        })
+        .collect();
+
+    if world.is_empty() {
+        return (MIN_NEAR, MIN_NEAR * 2.0);
+    }
+
+    let center = world.iter().fold(Vec3::zeros(), |sum, point| sum + point) / world.len() as f32;
+    let radius = world.iter().map(|point| (point - center).magnitude()).fold(0.0, f32::max);
+
+    let forward = (camera.center - camera.eye).normalize();
+    let distance = dot(&(center - camera.eye), &forward);
+
+    let near = (distance - radius).max(MIN_NEAR);
+    let far = (distance + radius).max(near * 2.0);
+    (near, far)
+}
+
+// Círculo que ocupa en pantalla una esfera del mundo: centro proyectado y
+// radio en píxeles. El radio sale del cono tangente a la esfera desde la
+// cámara, focal * r / sqrt(d² - r²), que es exacto para una esfera en el eje
+// de vista y una buena aproximación fuera de él. Si la esfera queda
+// completamente detrás de la cámara el radio es 0; si la cámara está dentro
+// o la esfera cruza el plano de la cámara, se devuelve un círculo que cubre
+// toda la pantalla (conservador, como screen_bounds)
+pub fn sphere_screen_circle(center: Vec3, radius: f32, uniforms: &Uniforms) -> (Vec2, f32) {
+    let view = uniforms.view_matrix * Vec4::new(center.x, center.y, center.z, 1.0);
+    let screen = screen_rect(uniforms);
+    let (screen_x, screen_y) = screen.center();
+    let whole_screen = (Vec2::new(screen_x, screen_y), (screen.width().powi(2) + screen.height().powi(2)).sqrt());
+
+    // La cámara mira hacia -z en espacio de vista
+    let depth = -view.z;
+    if depth <= -radius {
+        return (Vec2::new(screen_x, screen_y), 0.0);
+    }
+    let distance = Vec3::new(view.x, view.y, view.z).magnitude();
+    if depth <= radius || distance <= radius {
+        return whole_screen;
+    }
+
+    let clip = uniforms.projection_matrix * view;
+    let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let projected = uniforms.viewport_matrix * ndc;
+
+    let focal = uniforms.projection_matrix[(1, 1)] * uniforms.viewport_matrix[(1, 1)].abs();
+    let screen_radius = focal * radius / (distance * distance - radius * radius).sqrt();
+    (Vec2::new(projected.x, projected.y), screen_radius)
+}
+
+// Pantalla completa, deducida de la traslación de la matriz de viewport (ancho/2, alto/2)
+fn screen_rect(uniforms: &Uniforms) -> Rect {
+    Rect {
+        min_x: 0.0,
+        min_y: 0.0,
+        max_x: uniforms.viewport_matrix[(0, 3)] * 2.0,
+        max_y: uniforms.viewport_matrix[(1, 3)] * 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{benchmark_scene, create_model_matrix};
+
+    #[test]
+    fn a_centered_sphere_has_bounds_symmetric_about_the_screen_center() {
+        let (uniforms, vertices) = benchmark_scene(80, 60);
+        let rect = screen_bounds(&vertices, &uniforms).unwrap();
+        let (x, y) = rect.center();
+        assert!((x - 40.0).abs() < 0.5 && (y - 30.0).abs() < 0.5, "{:?}", rect);
+        assert!(rect.width() > 0.0 && rect.max_x < 80.0);
+    }
+
+    #[test]
+    fn a_closer_mesh_gets_a_smaller_near_plane() {
+        let (_, vertices) = benchmark_scene(8, 8);
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+        let near_model = Mat4::identity();
+        let far_model = create_model_matrix(Vec3::new(0.0, 0.0, -5.0), 1.0, Vec3::zeros());
+
+        let (near, far) = fit_depth_range(&vertices, &near_model, &camera);
+        assert!((near - 2.0).abs() < 1e-3 && (far - 4.0).abs() < 1e-3, "{} {}", near, far);
+        let (distant_near, distant_far) = fit_depth_range(&vertices, &far_model, &camera);
+        assert!(near < distant_near && (distant_near - 7.0).abs() < 1e-3 && distant_far > distant_near);
+
+        // Con la cámara dentro de la malla el plano cercano no baja de MIN_NEAR
+        let inside = Camera::new(Vec3::zeros(), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(fit_depth_range(&vertices, &near_model, &inside).0, MIN_NEAR);
+        assert_eq!(fit_depth_range(&[], &near_model, &camera), (MIN_NEAR, MIN_NEAR * 2.0));
+    }
+
+    #[test]
+    fn a_centered_sphere_projects_to_a_centered_circle() {
+        let (uniforms, vertices) = benchmark_scene(80, 60);
+        let (center, radius) = sphere_screen_circle(Vec3::zeros(), 1.0, &uniforms);
+        assert!((center.x - 40.0).abs() < 0.5 && (center.y - 30.0).abs() < 0.5, "{:?}", center);
+
+        // El radio (vertical) coincide con la mitad del alto que ocupa la malla
+        let rect = screen_bounds(&vertices, &uniforms).unwrap();
+        assert!((radius - rect.height() / 2.0).abs() < rect.height() * 0.05, "{} {:?}", radius, rect);
+
+        // Detrás de la cámara no ocupa nada, y con la cámara dentro ocupa toda la pantalla
+        assert_eq!(sphere_screen_circle(Vec3::new(0.0, 0.0, 6.0), 1.0, &uniforms).1, 0.0);
+        assert_eq!(sphere_screen_circle(Vec3::new(0.0, 0.0, 3.0), 1.0, &uniforms).1, 100.0);
+    }
+}
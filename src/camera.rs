@@ -60,6 +60,19 @@ impl Camera {
     self.has_changed = true;
   }
 
+  // Coloca la cámara a `distance` del centro sin cambiar la dirección de vista
+  // Si el ojo está sobre el centro no hay dirección; se usa +Z para no dar NaN
+  pub fn set_distance(&mut self, distance: f32) {
+    let offset = self.eye - self.center;
+    let direction = if offset.magnitude() > f32::EPSILON {
+      offset.normalize()
+    } else {
+      Vec3::new(0.0, 0.0, 1.0)
+    };
+    self.eye = self.center + direction * distance;
+    self.has_changed = true;
+  }
+
   pub fn move_center(&mut self, direction: Vec3) {
     let radius_vector = self.center - self.eye;
     let radius = radius_vector.magnitude();
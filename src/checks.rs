@@ -0,0 +1,46 @@
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::shaders::ShaderKind;
+use crate::vertex::Vertex;
+
+// Comprobaciones de NaN/Inf del pipeline (feature debug-checks). Sin la
+// feature este módulo no se compila y el render no paga nada
+
+// Color con el que se pinta un fragmento inválido para que salte a la vista
+pub const ERROR_COLOR: Color = Color::new(255, 0, 255);
+
+// true si todos los datos que usa el fragment shader son finitos; si no,
+// imprime el fragmento y el shader que lo iba a sombrear
+pub fn check_fragment(fragment: &Fragment, shader: ShaderKind) -> bool {
+    let finite = fragment.position.iter().all(|value| value.is_finite())
+        && fragment.depth.is_finite()
+        && fragment.intensity.is_finite()
+        && fragment.normal.iter().all(|value| value.is_finite())
+        && fragment.vertex_position.iter().all(|value| value.is_finite());
+
+    if !finite {
+        eprintln!(
+            "[debug-checks] fragmento inválido con {:?}: posición ({}, {}), profundidad {}, intensidad {}, normal {:?}, vértice {:?}",
+            shader,
+            fragment.position.x,
+            fragment.position.y,
+            fragment.depth,
+            fragment.intensity,
+            fragment.normal,
+            fragment.vertex_position,
+        );
+    }
+    finite
+}
+
+// Reporta los vértices cuya posición en pantalla no es finita (w = 0, matriz degenerada...)
+pub fn check_vertices(vertices: &[Vertex]) {
+    for (index, vertex) in vertices.iter().enumerate() {
+        if !vertex.transformed_position.iter().all(|value| value.is_finite()) {
+            eprintln!(
+                "[debug-checks] vértice {} inválido: posición {:?} -> pantalla {:?}",
+                index, vertex.position, vertex.transformed_position,
+            );
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt;
+use nalgebra_glm::{Vec3, Mat3};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
     r: u8,
     g: u8,
@@ -19,6 +21,15 @@ impl Color {
         Color { r, g, b }
     }
 
+    // Codifica una normal [-1, 1] en RGB [0, 255]
+    pub fn from_normal(normal: &Vec3) -> Self {
+        Color {
+            r: ((normal.x * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8,
+            g: ((normal.y * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8,
+            b: ((normal.z * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8,
+        }
+    }
+
     pub const fn black() -> Self {
         Color { r: 0, g: 0, b: 0 }
     }
@@ -27,6 +38,40 @@ impl Color {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
 
+    // 16 bits para pantallas de pocos colores: rrrrrggg gggbbbbb (rojo en los 5
+    // bits altos, verde en los 6 del medio, azul en los 5 bajos). Se redondea al
+    // nivel más cercano, con un error de hasta 4 (rojo, azul) o 2 (verde)
+    pub fn to_rgb565(&self) -> u16 {
+        let r = (self.r as u16 * 31 + 127) / 255;
+        let g = (self.g as u16 * 63 + 127) / 255;
+        let b = (self.b as u16 * 31 + 127) / 255;
+        (r << 11) | (g << 5) | b
+    }
+
+    // Inverso de to_rgb565: cada canal se expande de nuevo a [0, 255]
+    pub fn from_rgb565(packed: u16) -> Self {
+        let r = (packed >> 11) & 0x1F;
+        let g = (packed >> 5) & 0x3F;
+        let b = packed & 0x1F;
+        Color {
+            r: ((r * 255 + 15) / 31) as u8,
+            g: ((g * 255 + 31) / 63) as u8,
+            b: ((b * 255 + 15) / 31) as u8,
+        }
+    }
+
+    // rrrrgggg bbbbaaaa: 4 bits por canal, alfa en los bits bajos
+    pub fn to_rgba4444(&self, alpha: u8) -> u16 {
+        let quantize = |channel: u8| (channel as u16 * 15 + 127) / 255;
+        (quantize(self.r) << 12) | (quantize(self.g) << 8) | (quantize(self.b) << 4) | quantize(alpha)
+    }
+
+    // Inverso de to_rgba4444: (color, alfa)
+    pub fn from_rgba4444(packed: u16) -> (Self, u8) {
+        let expand = |shift: u16| (((packed >> shift) & 0xF) * 17) as u8;
+        (Color { r: expand(12), g: expand(8), b: expand(4) }, expand(0))
+    }
+
     // Linear interpolation between two colors
     pub fn lerp(&self, other: &Color, t: f32) -> Self {
         let t = t.clamp(0.0, 1.0);
@@ -37,6 +82,74 @@ impl Color {
         }
     }
 
+    // Interpolación en luz lineal: convierte de sRGB a lineal, mezcla y vuelve a sRGB.
+    // Evita los tonos medios apagados que produce lerp (que mezcla en espacio gamma)
+    pub fn lerp_linear(&self, other: &Color, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| {
+            let a = srgb_to_linear(a);
+            let b = srgb_to_linear(b);
+            linear_to_srgb(a + (b - a) * t)
+        };
+        Color {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+        }
+    }
+
+    // Luminancia relativa [0, 1] con los pesos de Rec. 709. Opera sobre los
+    // valores sRGB tal cual; para luminancia física hay que linealizar antes
+    pub fn luminance(&self) -> f32 {
+        (0.2126 * self.r as f32 + 0.7152 * self.g as f32 + 0.0722 * self.b as f32) / 255.0
+    }
+
+    // Promedio por canal (redondeado); negro si no hay colores
+    pub fn average(colors: impl Iterator<Item = Color>) -> Color {
+        let (mut r, mut g, mut b, mut count) = (0u32, 0u32, 0u32, 0u32);
+        for color in colors {
+            r += color.r as u32;
+            g += color.g as u32;
+            b += color.b as u32;
+            count += 1;
+        }
+        if count == 0 {
+            return Color::black();
+        }
+        let round = count / 2;
+        Color::new(((r + round) / count) as u8, ((g + round) / count) as u8, ((b + round) / count) as u8)
+    }
+
+    // Multiplica (r, g, b) normalizado por una matriz de color; el resultado se recorta a [0, 255]
+    pub fn transform(&self, matrix: &Mat3) -> Color {
+        let mixed = matrix * Vec3::new(self.r as f32, self.g as f32, self.b as f32);
+        Color {
+            r: mixed.x.clamp(0.0, 255.0).round() as u8,
+            g: mixed.y.clamp(0.0, 255.0).round() as u8,
+            b: mixed.z.clamp(0.0, 255.0).round() as u8,
+        }
+    }
+
+    // Como color * f, pero en flotante y sin recortar (RGB en [0, 255] y más):
+    // para encadenar términos de iluminación sin perder precisión en cada paso.
+    // from_hdr hace el único recorte al final
+    pub fn scale_hdr(&self, f: f32) -> Vec3 {
+        Vec3::new(self.r as f32, self.g as f32, self.b as f32) * f
+    }
+
+    pub fn from_hdr(rgb: Vec3) -> Color {
+        Color {
+            r: rgb.x.clamp(0.0, 255.0).round() as u8,
+            g: rgb.y.clamp(0.0, 255.0).round() as u8,
+            b: rgb.z.clamp(0.0, 255.0).round() as u8,
+        }
+    }
+
+    pub fn grayscale(&self) -> Color {
+        let gray = (self.luminance() * 255.0).round() as u8;
+        Color::new(gray, gray, gray)
+    }
+
     pub fn is_black(&self) -> bool {
         self.r == 0 && self.g == 0 && self.b == 0 
     }
@@ -70,6 +183,40 @@ impl Color {
     }
 }
 
+// Color determinista y bien distinto para un índice (triángulos, ids): mezcla
+// el índice al estilo de murmur3 y usa un byte por canal, con un mínimo de 64
+// para que ningún triángulo quede casi negro como el fondo
+pub fn hash_to_color(index: u32) -> Color {
+    let mut h = index.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+
+    let channel = |shift: u32| 64 + ((h >> shift) & 0xFF) as u8 % 192;
+    Color::new(channel(16), channel(8), channel(0))
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let c = value as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let c = value.clamp(0.0, 1.0);
+    let srgb = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round() as u8
+}
+
 use std::ops::Add;
 
 impl Add for Color {
@@ -102,4 +249,80 @@ impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_midpoint_is_brighter_than_the_gamma_one() {
+        let red = Color::new(255, 0, 0);
+        let green = Color::new(0, 255, 0);
+        let gamma = red.lerp(&green, 0.5);
+        let linear = red.lerp_linear(&green, 0.5);
+
+        assert_eq!(gamma, Color::new(128, 128, 0));
+        // 0.5 en luz lineal es ~188 en sRGB
+        assert!((linear.r as i32 - 188).abs() <= 1 && linear.r == linear.g && linear.b == 0, "{:?}", linear);
+        assert!(linear.luminance() > gamma.luminance());
+    }
+
+    #[test]
+    fn green_is_brighter_than_blue_of_the_same_value() {
+        let green = Color::new(0, 200, 0);
+        let blue = Color::new(0, 0, 200);
+        assert!(green.luminance() > blue.luminance());
+        assert_eq!(Color::new(255, 255, 255).luminance(), 1.0);
+        assert_eq!(Color::black().luminance(), 0.0);
+    }
+
+    #[test]
+    fn triangle_indices_map_to_stable_distinct_colors() {
+        for index in 0..1000 {
+            let color = hash_to_color(index);
+            assert_eq!(color, hash_to_color(index));
+            // Triángulos vecinos (índices consecutivos) se distinguen
+            assert_ne!(color, hash_to_color(index + 1), "{index}");
+            // Nunca tan oscuro como el fondo
+            assert!(color.r >= 64 && color.g >= 64 && color.b >= 64);
+        }
+    }
+
+    #[test]
+    fn chaining_scales_in_hdr_is_exact_where_u8_multiplies_lose_it() {
+        let color = Color::new(200, 100, 37);
+        // Factor total 0.75, pero pasando por encima de 1 y por debajo del paso de un u8
+        let scales = [3.0, 0.5, 0.1, 20.0, 0.25];
+
+        let hdr = scales.iter().fold(color.scale_hdr(1.0), |rgb, &scale| rgb * scale);
+        let clamped = scales.iter().fold(color, |color, &scale| color * scale);
+
+        assert_eq!(Color::from_hdr(hdr), Color::new(150, 75, 28));
+        assert_ne!(clamped, Color::from_hdr(hdr));
+        // El u8 se satura en 255 y trunca en cada paso, así que se queda más oscuro
+        assert!(clamped.r < 150 && clamped.g < 75 && clamped.b < 28, "{:?}", clamped);
+    }
+
+    #[test]
+    fn packed_16_bit_colors_round_trip_within_their_quantization_error() {
+        let colors = [
+            Color::new(0, 0, 0), Color::new(255, 255, 255), Color::new(255, 0, 0),
+            Color::new(128, 64, 200), Color::new(17, 233, 90), Color::new(75, 0, 130),
+        ];
+        let error = |a: u8, b: u8| (a as i32 - b as i32).abs();
+        for color in colors {
+            let back = Color::from_rgb565(color.to_rgb565());
+            assert!(error(color.r, back.r) <= 4 && error(color.g, back.g) <= 2 && error(color.b, back.b) <= 4, "{:?} -> {:?}", color, back);
+
+            let (back, alpha) = Color::from_rgba4444(color.to_rgba4444(128));
+            assert!(error(color.r, back.r) <= 8 && error(color.g, back.g) <= 8 && error(color.b, back.b) <= 8, "{:?} -> {:?}", color, back);
+            assert!(error(128, alpha) <= 8);
+        }
+
+        // Los extremos se conservan exactos
+        assert_eq!(Color::new(255, 255, 255).to_rgb565(), 0xFFFF);
+        assert_eq!(Color::new(255, 0, 0).to_rgb565(), 0xF800);
+        assert_eq!(Color::new(0, 0, 255).to_rgba4444(255), 0x00FF);
+    }
+}
@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// Dónde queda la fila 0 de la imagen exportada. El framebuffer guarda la fila
+// 0 arriba (como la muestra la ventana); BottomLeft la deja abajo, que es lo
+// que esperan OpenGL y la mayoría de cargadores de texturas
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FramebufferOrigin {
+    #[default]
+    TopLeft,
+    BottomLeft,
+}
+
+pub fn flip_vertical<T>(buffer: &mut [T], width: usize, height: usize) {
+    assert_eq!(buffer.len(), width * height, "buffer does not match its size");
+    for y in 0..height / 2 {
+        let (top, bottom) = buffer.split_at_mut((height - 1 - y) * width);
+        top[y * width..(y + 1) * width].swap_with_slice(&mut bottom[..width]);
+    }
+}
+
+pub fn flip_horizontal<T>(buffer: &mut [T], width: usize, height: usize) {
+    assert_eq!(buffer.len(), width * height, "buffer does not match its size");
+    for row in buffer.chunks_mut(width.max(1)) {
+        row.reverse();
+    }
+}
+
+// Cómo se escribe una captura. PNG define el alfa como "straight" (sin
+// premultiplicar), que es lo que esperan visores y navegadores; premultiply_alpha
+// es para compositores que interpretan la imagen como premultiplicada
+#[derive(Clone, Copy, Default)]
+pub struct PngOptions {
+    pub origin: FramebufferOrigin,
+    pub premultiply_alpha: bool,
+}
+
+// Guarda un buffer 0xRRGGBB como PNG de 8 bits: RGB si no hay alfa, RGBA si lo
+// hay. Se voltea si el origen pedido no es el del framebuffer
+pub fn save_png(
+    path: impl AsRef<Path>,
+    buffer: &[u32],
+    alpha: Option<&[u8]>,
+    width: usize,
+    height: usize,
+    options: PngOptions,
+) -> Result<(), png::EncodingError> {
+    let data = encode_pixels(buffer, alpha, options.premultiply_alpha);
+    let channels = if alpha.is_some() { 4 } else { 3 };
+
+    let mut rows: Vec<&[u8]> = data.chunks(width * channels).collect();
+    if options.origin == FramebufferOrigin::BottomLeft {
+        flip_vertical(&mut rows, 1, height);
+    }
+
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(if alpha.is_some() { png::ColorType::Rgba } else { png::ColorType::Rgb });
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rows.concat())
+}
+
+// Mapa de alturas como PNG en escala de grises de 16 bits: el rango
+// [mínimo, máximo] de values se estira a [0, 65535], así un degradado suave
+// no se escalona como con 8 bits (256 niveles) al usarlo para desplazar geometría
+pub fn save_heightmap_png16(path: impl AsRef<Path>, width: usize, height: usize, values: &[f32]) -> Result<(), png::EncodingError> {
+    assert_eq!(values.len(), width * height, "heightmap does not match its size");
+    let (min, max) = values.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &value| {
+        (min.min(value), max.max(value))
+    });
+    let range = (max - min).max(f32::EPSILON);
+
+    // PNG guarda las muestras de 16 bits en orden big-endian
+    let data: Vec<u8> = values
+        .iter()
+        .map(|value| (((value - min) / range).clamp(0.0, 1.0) * 65535.0).round() as u16)
+        .flat_map(u16::to_be_bytes)
+        .collect();
+
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)
+}
+
+// Bytes RGB o RGBA por píxel; con premultiply cada canal se escala por el alfa
+fn encode_pixels(buffer: &[u32], alpha: Option<&[u8]>, premultiply: bool) -> Vec<u8> {
+    let mut data = Vec::with_capacity(buffer.len() * 4);
+    for (index, pixel) in buffer.iter().enumerate() {
+        let rgb = [(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8];
+        match alpha {
+            Some(alpha) => {
+                let a = alpha[index];
+                for channel in rgb {
+                    data.push(if premultiply { premultiply_channel(channel, a) } else { channel });
+                }
+                data.push(a);
+            }
+            None => data.extend_from_slice(&rgb),
+        }
+    }
+    data
+}
+
+fn premultiply_channel(channel: u8, alpha: u8) -> u8 {
+    ((channel as u32 * alpha as u32 + 127) / 255) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flipping_twice_returns_the_original_buffer() {
+        let original: Vec<u32> = (0..12).collect();
+        let mut buffer = original.clone();
+
+        flip_horizontal(&mut buffer, 4, 3);
+        assert_eq!(&buffer[..4], &[3, 2, 1, 0]);
+        flip_horizontal(&mut buffer, 4, 3);
+        assert_eq!(buffer, original);
+
+        flip_vertical(&mut buffer, 4, 3);
+        assert_eq!(&buffer[..4], &[8, 9, 10, 11]);
+        flip_vertical(&mut buffer, 4, 3);
+        assert_eq!(buffer, original);
+    }
+
+    #[test]
+    fn half_alpha_red_exports_premultiplied_or_straight() {
+        let red = [0xFF0000];
+        let alpha = [128];
+        assert_eq!(encode_pixels(&red, Some(&alpha), true), vec![128, 0, 0, 128]);
+        assert_eq!(encode_pixels(&red, Some(&alpha), false), vec![255, 0, 0, 128]);
+        assert_eq!(encode_pixels(&red, None, true), vec![255, 0, 0]);
+    }
+
+    #[test]
+    fn a_smooth_ramp_exports_with_more_than_256_levels() {
+        let (width, height) = (1024, 2);
+        let ramp: Vec<f32> = (0..width * height).map(|i| (i % width) as f32 / width as f32).collect();
+        let path = std::env::temp_dir().join(format!("lab4_g_heightmap_{}.png", std::process::id()));
+        save_heightmap_png16(&path, width, height, &ramp).unwrap();
+
+        let mut reader = png::Decoder::new(File::open(&path).unwrap()).read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(frame.bit_depth, png::BitDepth::Sixteen);
+
+        let mut levels: Vec<u16> = data[..frame.buffer_size()]
+            .chunks_exact(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .collect();
+        // El rango se estira a [0, 65535]
+        assert_eq!(levels[0], 0);
+        assert_eq!(levels[width - 1], 65535);
+        levels.sort_unstable();
+        levels.dedup();
+        assert_eq!(levels.len(), width);
+    }
+}
@@ -3,13 +3,26 @@
 use nalgebra_glm::{Vec2, Vec3};
 use crate::color::Color;
 
+#[derive(Clone)]
 pub struct Fragment {
     pub position: Vec2,
     pub color: Color,
     pub depth: f32,
     pub normal: Vec3,
+    // Iluminación difusa siempre en [0, 1]: los shaders multiplican su color por
+    // ella, y un valor negativo o > 1 invertiría o saturaría el color
     pub intensity: f32,
     pub vertex_position: Vec3,
+    // Máscara de muestras MSAA cubiertas (bit i = muestra i); por defecto, todas
+    pub coverage: u32,
+    // Triángulo que lo generó, contando desde 1 dentro de cada dibujo (0 = fondo)
+    pub primitive_id: u32,
+    // Vertex::color interpolado; blanco (no tiñe) si la primitiva no lo trae
+    pub vertex_color: Color,
+    // Vertex::tex_coords interpolado y nivel de mipmap de su triángulo (ver
+    // triangle_lod); el nivel solo se calcula si hay textura en los uniforms
+    pub tex_coords: Vec2,
+    pub texture_lod: f32,
 }
 
 impl Fragment {
@@ -19,8 +32,13 @@ impl Fragment {
             color,
             depth,
             normal,
-            intensity,
-            vertex_position
+            intensity: intensity.clamp(0.0, 1.0),
+            vertex_position,
+            coverage: u32::MAX,
+            primitive_id: 0,
+            vertex_color: Color::new(255, 255, 255),
+            tex_coords: Vec2::zeros(),
+            texture_lod: 0.0,
         }
     }
 }
@@ -1,9 +1,24 @@
+use crate::noise::NoiseSource;
+use crate::color::Color;
+use crate::export::FramebufferOrigin;
 
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
+    pub deferred: bool,
+    pub albedo_buffer: Vec<u32>,
+    pub normal_buffer: Vec<u32>,
+    // MSAA: color y profundidad por muestra (msaa_samples por píxel), 1 = desactivado
+    pub msaa_samples: usize,
+    sample_colors: Vec<u32>,
+    sample_depths: Vec<f32>,
+    // Triángulo visible por píxel (Fragment::primitive_id), 0 = fondo; vacío si está desactivado
+    pub id_buffer: Vec<u32>,
+    current_id: u32,
+    // Origen con el que se exportan las capturas
+    pub origin: FramebufferOrigin,
     background_color: u32,
     current_color: u32,
 }
@@ -15,6 +30,15 @@ impl Framebuffer {
             height,
             buffer: vec![0; width * height],
             zbuffer: vec![f32::INFINITY; width * height],
+            deferred: false,
+            albedo_buffer: Vec::new(),
+            normal_buffer: Vec::new(),
+            msaa_samples: 1,
+            sample_colors: Vec::new(),
+            sample_depths: Vec::new(),
+            id_buffer: Vec::new(),
+            current_id: 0,
+            origin: FramebufferOrigin::TopLeft,
             background_color: 0x000000,
             current_color: 0xFFFFFF
         }
@@ -27,16 +51,269 @@ impl Framebuffer {
         for depth in self.zbuffer.iter_mut() {
             *depth = f32::INFINITY;
         }
+        for pixel in self.albedo_buffer.iter_mut() {
+            *pixel = self.background_color;
+        }
+        for pixel in self.normal_buffer.iter_mut() {
+            *pixel = 0x000000;
+        }
+        for depth in self.sample_depths.iter_mut() {
+            *depth = f32::INFINITY;
+        }
+        for id in self.id_buffer.iter_mut() {
+            *id = 0;
+        }
     }
 
-    pub fn point(&mut self, x: usize, y: usize, depth: f32) {
+    // Índice del píxel (x, y) en buffer y zbuffer; None fuera de la pantalla
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
         if x < self.width && y < self.height {
-            let index = y * self.width + x;
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    // Color de un píxel; None fuera de la pantalla
+    pub fn get(&self, x: usize, y: usize) -> Option<Color> {
+        self.index(x, y).map(|index| Color::from_hex(self.buffer[index]))
+    }
+
+    // Escribe un píxel sin prueba de profundidad; false (y no hace nada) si
+    // está fuera de la pantalla
+    pub fn set(&mut self, x: usize, y: usize, color: Color) -> bool {
+        match self.index(x, y) {
+            Some(index) => {
+                self.buffer[index] = color.to_hex();
+                true
+            }
+            None => false,
+        }
+    }
 
+    // clear con otro color de fondo, que queda como el nuevo background_color
+    pub fn clear_to(&mut self, color: Color) {
+        self.background_color = color.to_hex();
+        self.clear();
+    }
+
+    pub fn point(&mut self, x: usize, y: usize, depth: f32) {
+        if let Some(index) = self.index(x, y) {
             if self.zbuffer[index] > depth {
                 self.buffer[index] = self.current_color;
                 self.zbuffer[index] = depth;
+                self.write_id(index);
+            }
+        }
+    }
+
+    // Igual que point (o point_coverage con MSAA, donde el color va solo a
+    // las muestras cubiertas), pero también escribe albedo y normal en el G-buffer
+    pub fn point_deferred(&mut self, x: usize, y: usize, depth: f32, coverage: u32, albedo: u32, normal: u32) {
+        if self.msaa_samples > 1 {
+            self.point_coverage(x, y, depth, coverage);
+            if let Some(index) = self.index(x, y) {
+                if self.zbuffer[index] == depth {
+                    self.albedo_buffer[index] = albedo;
+                    self.normal_buffer[index] = normal;
+                }
+            }
+            return;
+        }
+        if let Some(index) = self.index(x, y) {
+            if self.zbuffer[index] > depth {
+                self.buffer[index] = self.current_color;
+                self.zbuffer[index] = depth;
+                self.albedo_buffer[index] = albedo;
+                self.normal_buffer[index] = normal;
+                self.write_id(index);
+            }
+        }
+    }
+
+    // Escribe el color actual en las muestras cubiertas por coverage que pasen la
+    // prueba de profundidad. El z-buffer por píxel guarda la más cercana
+    pub fn point_coverage(&mut self, x: usize, y: usize, depth: f32, coverage: u32) {
+        if let Some(index) = self.index(x, y) {
+            for sample in 0..self.msaa_samples {
+                let sample_index = index * self.msaa_samples + sample;
+                if coverage & (1 << sample) != 0 && self.sample_depths[sample_index] > depth {
+                    self.sample_colors[sample_index] = self.current_color;
+                    self.sample_depths[sample_index] = depth;
+                }
+            }
+
+            if self.zbuffer[index] > depth {
+                self.zbuffer[index] = depth;
+                self.write_id(index);
+            }
+        }
+    }
+
+    // Punto redondo y suavizado: suma (mezcla aditiva) color * exp(-d² / 2σ²)
+    // en los píxeles a menos de 3σ del centro (x, y), con σ = size / 2. Solo
+    // respeta el z-buffer, no lo escribe, así que la geometría posterior lo tapa
+    pub fn point_sprite(&mut self, x: f32, y: f32, depth: f32, color: Color, size: f32) {
+        let sigma = (size * 0.5).max(0.25);
+        let reach = sigma * 3.0;
+        let min_x = (x - reach).floor().max(0.0) as usize;
+        let min_y = (y - reach).floor().max(0.0) as usize;
+        let max_x = ((x + reach).ceil().max(0.0) as usize).min(self.width);
+        let max_y = ((y + reach).ceil().max(0.0) as usize).min(self.height);
+
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let Some(index) = self.index(px, py) else { continue };
+                if self.zbuffer[index] <= depth {
+                    continue;
+                }
+                let dx = px as f32 + 0.5 - x;
+                let dy = py as f32 + 0.5 - y;
+                let weight = (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp();
+                self.buffer[index] = (Color::from_hex(self.buffer[index]) + color * weight).to_hex();
+            }
+        }
+    }
+
+    // Promedia las muestras de cada píxel en buffer. Las muestras que nadie
+    // escribió toman el color que ya tenía el píxel (fondo o cielo), así que en
+    // los bordes el resultado es una mezcla con el fondo según la cobertura
+    pub fn resolve_msaa(&mut self) {
+        if self.msaa_samples <= 1 {
+            return;
+        }
+
+        for index in 0..self.width * self.height {
+            let first = index * self.msaa_samples;
+            let samples = first..first + self.msaa_samples;
+            if self.sample_depths[samples.clone()].iter().all(|depth| depth.is_infinite()) {
+                continue;
+            }
+
+            let background = self.buffer[index];
+            let (mut r, mut g, mut b) = (0u32, 0u32, 0u32);
+            for sample in samples {
+                let color = if self.sample_depths[sample].is_finite() {
+                    self.sample_colors[sample]
+                } else {
+                    background
+                };
+                r += (color >> 16) & 0xFF;
+                g += (color >> 8) & 0xFF;
+                b += color & 0xFF;
             }
+
+            let count = self.msaa_samples as u32;
+            let round = count / 2;
+            self.buffer[index] = (((r + round) / count) << 16) | (((g + round) / count) << 8) | ((b + round) / count);
+        }
+    }
+
+    fn write_id(&mut self, index: usize) {
+        if let Some(id) = self.id_buffer.get_mut(index) {
+            *id = self.current_id;
+        }
+    }
+
+    pub fn set_id_buffer(&mut self, enabled: bool) {
+        self.id_buffer = if enabled { vec![0; self.width * self.height] } else { Vec::new() };
+    }
+
+    // Id del triángulo visible en un píxel; None fuera de la pantalla o sin id_buffer
+    pub fn id_at(&self, x: usize, y: usize) -> Option<u32> {
+        self.index(x, y).and_then(|index| self.id_buffer.get(index).copied())
+    }
+
+    pub fn set_msaa(&mut self, samples: usize) {
+        self.msaa_samples = samples.max(1);
+        if self.msaa_samples > 1 {
+            self.sample_colors = vec![self.background_color; self.width * self.height * self.msaa_samples];
+            self.sample_depths = vec![f32::INFINITY; self.width * self.height * self.msaa_samples];
+        } else {
+            self.sample_colors = Vec::new();
+            self.sample_depths = Vec::new();
+        }
+    }
+
+    pub fn set_deferred(&mut self, deferred: bool) {
+        self.deferred = deferred;
+        if deferred {
+            self.albedo_buffer = vec![self.background_color; self.width * self.height];
+            self.normal_buffer = vec![0x000000; self.width * self.height];
+        } else {
+            self.albedo_buffer = Vec::new();
+            self.normal_buffer = Vec::new();
+        }
+    }
+
+    // Color y profundidad de un píxel (cuentagotas / selección); None fuera de la pantalla.
+    // La profundidad es infinita donde no se dibujó nada
+    pub fn sample_pixel(&self, x: usize, y: usize) -> Option<(Color, f32)> {
+        Some((self.get(x, y)?, self.zbuffer[self.index(x, y)?]))
+    }
+
+    // Opacidad por píxel para exportar: 255 donde se dibujó geometría, 0 en el
+    // fondo; con MSAA es la fracción de muestras cubiertas
+    pub fn alpha(&self) -> Vec<u8> {
+        if self.msaa_samples > 1 {
+            self.sample_depths
+                .chunks(self.msaa_samples)
+                .map(|samples| {
+                    let covered = samples.iter().filter(|depth| depth.is_finite()).count();
+                    ((covered * 255 + self.msaa_samples / 2) / self.msaa_samples) as u8
+                })
+                .collect()
+        } else {
+            self.zbuffer.iter().map(|depth| if depth.is_finite() { 255 } else { 0 }).collect()
+        }
+    }
+
+    // El frame empaquetado en 16 bits por píxel (ver Color::to_rgb565), para
+    // pantallas embebidas que esperan RGB565
+    pub fn to_rgb565(&self) -> Vec<u16> {
+        self.buffer.iter().map(|&pixel| Color::from_hex(pixel).to_rgb565()).collect()
+    }
+
+    // Igual en RGBA4444, con el alfa de alpha()
+    pub fn to_rgba4444(&self) -> Vec<u16> {
+        self.buffer.iter().zip(self.alpha()).map(|(&pixel, alpha)| Color::from_hex(pixel).to_rgba4444(alpha)).collect()
+    }
+
+    // Profundidad en escala de grises: cerca = blanco, lejos = negro, fondo = negro
+    pub fn depth_image(&self) -> Vec<u32> {
+        let finite = self.zbuffer.iter().filter(|depth| depth.is_finite());
+        let (min, max) = finite.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &depth| {
+            (min.min(depth), max.max(depth))
+        });
+        let range = (max - min).max(f32::EPSILON);
+
+        self.zbuffer.iter().map(|&depth| {
+            if depth.is_finite() {
+                let gray = (255.0 * (1.0 - (depth - min) / range)).round() as u32;
+                (gray << 16) | (gray << 8) | gray
+            } else {
+                0x000000
+            }
+        }).collect()
+    }
+
+    // Cielo degradado de top (fila superior) a bottom (fila inferior). Se pinta
+    // después de clear y antes de la geometría; no toca el z-buffer, así que
+    // queda a profundidad máxima detrás de todo lo que se dibuje
+    pub fn sky_gradient(&mut self, top: Color, bottom: Color) {
+        self.paint_sky(top, bottom, |_| 0.0);
+    }
+
+    // Igual que sky_gradient, con bandas horizontales suaves de ruido (amount ~0.05)
+    pub fn sky_gradient_banded(&mut self, top: Color, bottom: Color, noise: &dyn NoiseSource, amount: f32) {
+        self.paint_sky(top, bottom, |y| noise.get_noise_2d(0.0, y as f32 * 4.0) * amount);
+    }
+
+    fn paint_sky(&mut self, top: Color, bottom: Color, banding: impl Fn(usize) -> f32) {
+        let last_row = (self.height.max(2) - 1) as f32;
+        for (y, row) in self.buffer.chunks_mut(self.width).enumerate() {
+            let t = (y as f32 / last_row + banding(y)).clamp(0.0, 1.0);
+            row.fill(top.lerp(&bottom, t).to_hex());
         }
     }
 
@@ -47,4 +324,139 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
-}
\ No newline at end of file
+
+    pub fn set_current_id(&mut self, id: u32) {
+        self.current_id = id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_sky_gradient_runs_from_top_to_bottom() {
+        let top = Color::new(0, 0, 64);
+        let bottom = Color::new(200, 120, 255);
+        let mut framebuffer = Framebuffer::new(3, 5);
+        framebuffer.sky_gradient(top, bottom);
+
+        let row = |y: usize| &framebuffer.buffer[y * 3..(y + 1) * 3];
+        assert!(row(0).iter().all(|&pixel| pixel == top.to_hex()));
+        assert!(row(4).iter().all(|&pixel| pixel == bottom.to_hex()));
+        assert!(row(2).iter().all(|&pixel| pixel == top.lerp(&bottom, 0.5).to_hex()));
+    }
+
+    #[test]
+    fn msaa_blends_edge_pixels_by_coverage_and_leaves_interiors_solid() {
+        let mut framebuffer = Framebuffer::new(3, 1);
+        framebuffer.set_msaa(4);
+        framebuffer.clear_to(Color::black());
+        framebuffer.set_current_color(0xFFFFFF);
+
+        framebuffer.point_coverage(0, 0, 0.5, 0b1111);
+        // Borde: la mitad de las muestras cubiertas
+        framebuffer.point_coverage(1, 0, 0.5, 0b0101);
+        framebuffer.resolve_msaa();
+
+        assert_eq!(framebuffer.buffer, vec![0xFFFFFF, 0x808080, 0x000000]);
+        assert_eq!(framebuffer.alpha(), vec![255, 128, 0]);
+    }
+
+    #[test]
+    fn deferred_msaa_writes_only_the_covered_samples_of_an_edge_pixel() {
+        let mut framebuffer = Framebuffer::new(3, 1);
+        framebuffer.set_msaa(4);
+        framebuffer.set_deferred(true);
+        framebuffer.clear_to(Color::black());
+        framebuffer.set_current_color(0xFFFFFF);
+
+        framebuffer.point_deferred(0, 0, 0.5, 0b1111, 0x336699, 0x8080FF);
+        framebuffer.point_deferred(1, 0, 0.5, 0b0101, 0x336699, 0x8080FF);
+        framebuffer.resolve_msaa();
+
+        // Igual que el camino directo: el borde mezcla con el fondo por cobertura
+        assert_eq!(framebuffer.buffer, vec![0xFFFFFF, 0x808080, 0x000000]);
+        assert_eq!(framebuffer.alpha(), vec![255, 128, 0]);
+        assert_eq!(framebuffer.albedo_buffer, vec![0x336699, 0x336699, 0x000000]);
+        assert_eq!(framebuffer.normal_buffer, vec![0x8080FF, 0x8080FF, 0x000000]);
+    }
+
+    #[test]
+    fn picking_a_drawn_pixel_returns_its_color_and_depth() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        framebuffer.clear_to(Color::black());
+        framebuffer.set_current_color(0x3366CC);
+        for x in 1..3 {
+            framebuffer.point(x, 2, 0.25);
+        }
+
+        assert_eq!(framebuffer.sample_pixel(2, 2), Some((Color::new(0x33, 0x66, 0xCC), 0.25)));
+        let (background, depth) = framebuffer.sample_pixel(0, 0).unwrap();
+        assert!(background == Color::black() && depth.is_infinite());
+        assert_eq!(framebuffer.sample_pixel(4, 0), None);
+    }
+
+    #[test]
+    fn a_point_sprite_falls_off_symmetrically_around_its_center() {
+        let mut framebuffer = Framebuffer::new(32, 32);
+        framebuffer.clear();
+        framebuffer.point_sprite(16.0, 16.0, 0.5, Color::new(255, 255, 255), 4.0);
+        let at = |x: usize, y: usize| framebuffer.buffer[framebuffer.index(x, y).unwrap()] & 0xFF;
+
+        for y in 0..32 {
+            for x in 0..32 {
+                assert_eq!(at(x, y), at(31 - x, y));
+                assert_eq!(at(x, y), at(x, 31 - y));
+                assert_eq!(at(x, y), at(y, x));
+            }
+        }
+
+        // Más brillante en el centro, apagándose hacia afuera hasta el fondo
+        let row: Vec<u32> = (16..32).map(|x| at(x, 16)).collect();
+        assert!(row.windows(2).all(|pair| pair[0] >= pair[1]), "{row:?}");
+        assert!(row[0] > 200 && row[1] < row[0]);
+        assert_eq!(at(31, 16), 0);
+    }
+
+    #[test]
+    fn set_then_get_round_trips_a_pixel() {
+        let mut framebuffer = Framebuffer::new(4, 3);
+        framebuffer.clear_to(Color::new(10, 20, 30));
+        assert!(framebuffer.buffer.iter().all(|&pixel| pixel == 0x0A141E));
+
+        assert!(framebuffer.set(3, 2, Color::new(200, 100, 50)));
+        assert_eq!(framebuffer.get(3, 2), Some(Color::new(200, 100, 50)));
+        assert_eq!(framebuffer.get(0, 0), Some(Color::new(10, 20, 30)));
+
+        // El nuevo fondo se mantiene en los siguientes clear
+        framebuffer.clear();
+        assert_eq!(framebuffer.get(3, 2), Some(Color::new(10, 20, 30)));
+    }
+
+    #[test]
+    fn pixels_outside_the_screen_are_ignored() {
+        let mut framebuffer = Framebuffer::new(4, 3);
+        framebuffer.clear();
+        let before = framebuffer.buffer.clone();
+
+        for (x, y) in [(4, 0), (0, 3), (usize::MAX, 1)] {
+            assert_eq!(framebuffer.index(x, y), None);
+            assert_eq!(framebuffer.get(x, y), None);
+            assert!(!framebuffer.set(x, y, Color::new(255, 0, 0)));
+        }
+        assert_eq!(framebuffer.buffer, before);
+    }
+
+    #[test]
+    fn the_packed_frame_keeps_each_pixel_and_its_alpha() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.clear_to(Color::black());
+        framebuffer.set_current_color(0xFF0000);
+        framebuffer.point(0, 0, 0.5);
+
+        assert_eq!(framebuffer.to_rgb565(), vec![0xF800, 0x0000]);
+        // Píxel dibujado opaco, fondo transparente
+        assert_eq!(framebuffer.to_rgba4444(), vec![0xF00F, 0x0000]);
+    }
+}
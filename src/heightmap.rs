@@ -0,0 +1,148 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::noise::NoiseSource;
+
+// Campo de alturas en proyección equirectangular (u = longitud, v = latitud)
+// muestreando el ruido sobre la esfera unitaria, como lo ve el planeta rocoso.
+// Es la base para hornear un mapa de desplazamiento
+pub fn bake_heightfield(noise: &dyn NoiseSource, width: usize, height: usize, zoom: f32) -> Vec<f32> {
+    let mut heightfield = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let latitude = PI * ((y as f32 + 0.5) / height as f32 - 0.5);
+        for x in 0..width {
+            let longitude = 2.0 * PI * (x as f32 + 0.5) / width as f32;
+            let (px, py, pz) = (latitude.cos() * longitude.cos(), latitude.sin(), latitude.cos() * longitude.sin());
+            heightfield.push(noise.get_noise_3d(px * zoom, py * zoom, pz * zoom));
+        }
+    }
+    heightfield
+}
+
+// Erosión térmica aproximada: en cada iteración, cada celda más alta que su
+// vecino más bajo por encima de TALUS le pasa un cuarto del exceso (poco,
+// porque varios vecinos pueden vaciarse en la misma celda a la vez). El
+// material baja de las crestas a los valles, así que los picos se redondean y
+// los valles se rellenan sin cambiar la altura total.
+// Costo: O(iterations * width * height) con 8 vecinos por celda; unas 50
+// iteraciones bastan para 512x256, y el resultado cambia poco pasadas 200
+pub fn erode(heightfield: &mut [f32], width: usize, height: usize, iterations: u32) {
+    assert_eq!(heightfield.len(), width * height, "heightfield does not match its size");
+    const TALUS: f32 = 0.01;
+    const NEIGHBOURS: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+    let mut delta = vec![0.0f32; heightfield.len()];
+    for _ in 0..iterations {
+        delta.iter_mut().for_each(|value| *value = 0.0);
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                // Vecino más bajo; la longitud se envuelve y la latitud se recorta en los polos
+                let lowest = NEIGHBOURS.iter()
+                    .filter_map(|(dx, dy)| {
+                        let ny = y as i64 + dy;
+                        if ny < 0 || ny >= height as i64 {
+                            return None;
+                        }
+                        let nx = (x as i64 + dx).rem_euclid(width as i64);
+                        Some(ny as usize * width + nx as usize)
+                    })
+                    .min_by(|&a, &b| heightfield[a].total_cmp(&heightfield[b]));
+
+                if let Some(lowest) = lowest {
+                    let excess = heightfield[index] - heightfield[lowest] - TALUS;
+                    if excess > 0.0 {
+                        let moved = excess * 0.25;
+                        delta[index] -= moved;
+                        delta[lowest] += moved;
+                    }
+                }
+            }
+        }
+
+        for (value, change) in heightfield.iter_mut().zip(&delta) {
+            *value += change;
+        }
+    }
+}
+
+// Mapa de normales en espacio tangente a partir de las alturas, con
+// diferencias centrales (la longitud se envuelve y la latitud se recorta,
+// como en erode). strength escala la pendiente; la normal se codifica con
+// Color::from_normal, así una zona plana queda (128, 128, 255). +Y apunta
+// hacia arriba de la imagen (convención de OpenGL)
+pub fn heightmap_to_normalmap(heights: &[f32], width: usize, height: usize, strength: f32) -> Vec<Color> {
+    assert_eq!(heights.len(), width * height, "heightmap does not match its size");
+    let at = |x: i64, y: i64| {
+        let x = x.rem_euclid(width as i64) as usize;
+        let y = y.clamp(0, height as i64 - 1) as usize;
+        heights[y * width + x]
+    };
+
+    let mut normals = Vec::with_capacity(width * height);
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let dx = (at(x + 1, y) - at(x - 1, y)) * 0.5;
+            // La fila 0 está arriba: bajar en la imagen es bajar en Y
+            let dy = (at(x, y - 1) - at(x, y + 1)) * 0.5;
+            let normal = Vec3::new(-dx * strength, -dy * strength, 1.0).normalize();
+            normals.push(Color::from_normal(&normal));
+        }
+    }
+    normals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::create_noise;
+
+    // (r, g, b) de un color
+    fn channels(color: Color) -> [i32; 3] {
+        let hex = color.to_hex();
+        [(hex >> 16 & 0xFF) as i32, (hex >> 8 & 0xFF) as i32, (hex & 0xFF) as i32]
+    }
+
+    fn variance(values: &[f32]) -> f32 {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        values.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / values.len() as f32
+    }
+
+    #[test]
+    fn erosion_lowers_the_variance_and_the_peaks() {
+        let (width, height) = (64, 32);
+        let original = bake_heightfield(&create_noise(), width, height, 4.0);
+        let mut eroded = original.clone();
+        erode(&mut eroded, width, height, 50);
+
+        assert!(variance(&eroded) < variance(&original), "{} {}", variance(&eroded), variance(&original));
+        let peak = |values: &[f32]| values.iter().copied().fold(f32::MIN, f32::max);
+        assert!(peak(&eroded) < peak(&original));
+        // El material solo se mueve: la altura total se conserva
+        let total = |values: &[f32]| values.iter().sum::<f32>();
+        assert!((total(&eroded) - total(&original)).abs() < 1e-2);
+    }
+
+    #[test]
+    fn a_flat_heightmap_is_neutral_and_a_slope_tilts_the_normal() {
+        let (width, height) = (8, 8);
+        let flat = heightmap_to_normalmap(&vec![0.3; width * height], width, height, 1.0);
+        assert!(flat.iter().all(|&color| color == Color::new(128, 128, 255)));
+
+        // Sube hacia la derecha: la normal se inclina hacia -x (menos rojo)
+        let east: Vec<f32> = (0..width * height).map(|i| (i % width) as f32 * 0.1).collect();
+        let tilted = heightmap_to_normalmap(&east, width, height, 1.0)[3 * width + 3];
+        let [r, g, b] = channels(tilted);
+        assert!(r < 128 && g == 128 && b < 255, "{:?}", [r, g, b]);
+
+        // Sube hacia abajo de la imagen: con +Y hacia arriba se inclina hacia +y
+        let south: Vec<f32> = (0..width * height).map(|i| (i / width) as f32 * 0.1).collect();
+        let [r, g, _] = channels(heightmap_to_normalmap(&south, width, height, 1.0)[3 * width + 3]);
+        assert!(r == 128 && g > 128, "{:?}", [r, g]);
+
+        // Más strength, más inclinación
+        let [steeper, ..] = channels(heightmap_to_normalmap(&east, width, height, 4.0)[3 * width + 3]);
+        assert!(steeper < channels(tilted)[0]);
+    }
+}
@@ -0,0 +1,32 @@
+pub mod framebuffer;
+pub mod triangle;
+pub mod line;
+pub mod vertex;
+pub mod obj;
+pub mod color;
+pub mod fragment;
+pub mod shaders;
+pub mod camera;
+pub mod bounds;
+pub mod clock;
+pub mod postprocess;
+pub mod mesh;
+pub mod noise;
+pub mod palettes;
+pub mod ramp;
+pub mod swatch;
+pub mod raster;
+pub mod texture;
+pub mod export;
+#[cfg(feature = "debug-checks")]
+pub mod checks;
+pub mod light;
+pub mod shadow;
+pub mod lod;
+pub mod heightmap;
+pub mod ray;
+#[cfg(feature = "serde")]
+pub mod setup;
+pub mod render;
+
+pub use render::Uniforms;
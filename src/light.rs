@@ -0,0 +1,79 @@
+use nalgebra_glm::{Vec3, dot};
+use crate::color::Color;
+use crate::shaders::smoothstep;
+
+// Foco: luz puntual limitada a un cono. Los ángulos son medios ángulos en
+// radianes; dentro de inner ilumina por completo, entre inner y outer se
+// desvanece y fuera de outer no aporta nada
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub strength: f32,
+    // Un foco de relleno no necesita sombras (se ahorra su mapa de sombras);
+    // uno principal las proyecta, teñidas de shadow_color
+    pub casts_shadow: bool,
+    pub shadow_color: Color,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        SpotLight {
+            position: Vec3::new(2.0, 2.0, 3.0),
+            direction: Vec3::new(-2.0, -2.0, -3.0),
+            inner_angle: 6f32.to_radians(),
+            outer_angle: 12f32.to_radians(),
+            strength: 1.0,
+            casts_shadow: false,
+            shadow_color: Color::black(),
+        }
+    }
+}
+
+impl SpotLight {
+    // Atenuación del cono para un punto: 1 dentro de inner, 0 fuera de outer
+    pub fn cone_factor(&self, point: &Vec3) -> f32 {
+        let to_point = (point - self.position).normalize();
+        let cos_angle = dot(&to_point, &self.direction.normalize());
+        smoothstep(self.outer_angle.cos(), self.inner_angle.cos(), cos_angle)
+    }
+
+    // Aporte difuso del foco sobre un punto con la normal dada (mismo espacio)
+    pub fn diffuse(&self, point: &Vec3, normal: &Vec3) -> f32 {
+        let to_light = (self.position - point).normalize();
+        dot(normal, &to_light).max(0.0) * self.cone_factor(point) * self.strength
+    }
+
+    // El mismo foco visto desde otro espacio (por ejemplo, el de vista)
+    pub fn transformed(&self, position: Vec3, direction: Vec3) -> SpotLight {
+        SpotLight { position, direction, ..*self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_cone_lights_fully_inside_partially_between_and_not_outside() {
+        let spot = SpotLight {
+            position: Vec3::zeros(),
+            direction: Vec3::new(0.0, 0.0, -1.0),
+            ..SpotLight::default()
+        };
+        // Punto a un ángulo dado del eje, con la normal mirando al foco
+        let lit_at = |degrees: f32| {
+            let point = Vec3::new(degrees.to_radians().tan(), 0.0, -1.0);
+            spot.diffuse(&point, &(-point).normalize())
+        };
+
+        assert!((lit_at(0.0) - 1.0).abs() < 1e-5);
+        assert!((lit_at(3.0) - 1.0).abs() < 1e-5);
+        let between = lit_at(9.0);
+        assert!(between > 0.05 && between < 0.95, "{between}");
+        assert_eq!(lit_at(20.0), 0.0);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::vertex::Vertex;
+use crate::mesh::tessellate;
+use crate::bounds::screen_bounds;
+use crate::Uniforms;
+
+// Niveles de detalle precalculados de una malla: el nivel i es la malla base
+// teselada i veces. thresholds[i] es el tamaño en pantalla (píxeles, el lado
+// mayor del rectángulo que ocupa) a partir del cual se usa el nivel i + 1
+pub struct MeshLod {
+    levels: Vec<Vec<Vertex>>,
+    thresholds: Vec<f32>,
+}
+
+impl MeshLod {
+    // Un nivel por umbral más el nivel base; los umbrales se ordenan de menor a mayor
+    pub fn new(base: &[Vertex], thresholds: &[f32]) -> Self {
+        let mut thresholds = thresholds.to_vec();
+        thresholds.sort_by(f32::total_cmp);
+        let levels = (0..=thresholds.len() as u32).map(|level| tessellate(base, level)).collect();
+        MeshLod { levels, thresholds }
+    }
+
+    // Niveles ya construidos (por ejemplo teselados y con relieve), del menos
+    // al más detallado; tiene que haber un nivel más que umbrales
+    pub fn from_levels(levels: Vec<Vec<Vertex>>, thresholds: &[f32]) -> Self {
+        assert_eq!(levels.len(), thresholds.len() + 1, "MeshLod needs one level more than thresholds");
+        let mut thresholds = thresholds.to_vec();
+        thresholds.sort_by(f32::total_cmp);
+        MeshLod { levels, thresholds }
+    }
+
+    // Nivel para un tamaño en pantalla dado
+    pub fn level_for_size(&self, screen_size: f32) -> usize {
+        self.thresholds.iter().filter(|&&threshold| screen_size >= threshold).count()
+    }
+
+    // Nivel para la malla vista con estas matrices. Si no queda en pantalla
+    // (toda detrás de la cámara) se usa el nivel más bajo
+    pub fn select(&self, uniforms: &Uniforms) -> usize {
+        match screen_bounds(&self.levels[0], uniforms) {
+            Some(rect) => self.level_for_size(rect.width().max(rect.height())),
+            None => 0,
+        }
+    }
+
+    pub fn level(&self, level: usize) -> &[Vertex] {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec3;
+    use crate::render::{benchmark_scene, create_model_matrix, planet_lod};
+
+    #[test]
+    fn a_smaller_projection_selects_a_coarser_level() {
+        let (mut uniforms, base) = benchmark_scene(800, 800);
+        let lod = planet_lod(&base, false, uniforms.noise.as_ref());
+        let level_at_scale = |uniforms: &mut Uniforms, scale: f32| {
+            uniforms.model_matrix = create_model_matrix(Vec3::zeros(), scale, Vec3::zeros());
+            lod.select(uniforms)
+        };
+
+        let far = level_at_scale(&mut uniforms, 0.2);
+        let near = level_at_scale(&mut uniforms, 1.5);
+        assert_eq!(far, 0);
+        assert!(near > far, "{far} {near}");
+        // Cada nivel tiene más triángulos que el anterior
+        assert!(lod.level(near).len() > lod.level(far).len());
+    }
+}
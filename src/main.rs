@@ -1,150 +1,57 @@
-use nalgebra_glm::{Vec3, Mat4, look_at, perspective};
-use minifb::{Key, Window, WindowOptions};
+use nalgebra_glm::{Vec2, Vec3, Mat4};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
 use std::time::Duration;
+use std::sync::Arc;
 use std::f32::consts::PI;
 
-mod framebuffer;
-mod triangle;
-mod vertex;
-mod obj;
-mod color;
-mod fragment;
-mod shaders;
-mod camera;
-
-use framebuffer::Framebuffer;
-use vertex::Vertex;
-use obj::Obj;
-use camera::Camera;
-use triangle::triangle;
-use shaders::{vertex_shader, fragment_shader};
-use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
-
-pub struct Uniforms {
-    model_matrix: Mat4,
-    view_matrix: Mat4,
-    projection_matrix: Mat4,
-    viewport_matrix: Mat4,
-    time: u32,
-    noise: FastNoiseLite
-}
-
-fn create_noise() -> FastNoiseLite {
-    create_cloud_noise()
-}
-
-fn create_cloud_noise() -> FastNoiseLite {
-    let mut noise = FastNoiseLite::with_seed(1337);
-    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    noise
-}
-
-fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
-    let (sin_x, cos_x) = rotation.x.sin_cos();
-    let (sin_y, cos_y) = rotation.y.sin_cos();
-    let (sin_z, cos_z) = rotation.z.sin_cos();
-
-    let rotation_matrix_x = Mat4::new(
-        1.0,  0.0,    0.0,   0.0,
-        0.0,  cos_x, -sin_x, 0.0,
-        0.0,  sin_x,  cos_x, 0.0,
-        0.0,  0.0,    0.0,   1.0,
-    );
-
-    let rotation_matrix_y = Mat4::new(
-        cos_y,  0.0,  sin_y, 0.0,
-        0.0,    1.0,  0.0,   0.0,
-        -sin_y, 0.0,  cos_y, 0.0,
-        0.0,    0.0,  0.0,   1.0,
-    );
-
-    let rotation_matrix_z = Mat4::new(
-        cos_z, -sin_z, 0.0, 0.0,
-        sin_z,  cos_z, 0.0, 0.0,
-        0.0,    0.0,  1.0, 0.0,
-        0.0,    0.0,  0.0, 1.0,
-    );
-
-    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
-
-    let transform_matrix = Mat4::new(
-        scale, 0.0,   0.0,   translation.x,
-        0.0,   scale, 0.0,   translation.y,
-        0.0,   0.0,   scale, translation.z,
-        0.0,   0.0,   0.0,   1.0,
-    );
-
-    transform_matrix * rotation_matrix
-}
 
-
-fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
-    look_at(&eye, &center, &up)
-}
-
-fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 45.0 * PI / 180.0;
-    let aspect_ratio = window_width / window_height;
-    let near = 0.1;
-    let far = 1000.0;
-
-    perspective(fov, aspect_ratio, near, far)
-}
-
-fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
-    Mat4::new(
-        width / 2.0, 0.0, 0.0, width / 2.0,
-        0.0, -height / 2.0, 0.0, height / 2.0,
-        0.0, 0.0, 1.0, 0.0,
-        0.0, 0.0, 0.0, 1.0
-    )
-}
-
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], current_shader: u8) {
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
-    }
-
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
-        }
-    }
-
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
-    }
-
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = fragment_shader(&fragment, uniforms, current_shader);
-            let color = shaded_color.to_hex();
-            framebuffer.set_current_color(color);
-            framebuffer.point(x, y, fragment.depth);
-        }
-    }
+use lab4_g::framebuffer::Framebuffer;
+use lab4_g::obj::Obj;
+use lab4_g::camera::Camera;
+use lab4_g::clock::Clock;
+use lab4_g::raster::RasterPrecision;
+use lab4_g::shaders::{NormalSpace, ShaderParams, Anisotropic, Bump, CloudShadows, Cutout, IceCaps, Contours, Envelope, LatitudeTint, Outline, Specular, Subsurface, VolumetricClouds, ShaderKind, CELULAR_RAMP};
+use lab4_g::color::{Color, hash_to_color};
+use lab4_g::noise::seed_from_name;
+use lab4_g::swatch::{render_shader_swatch, render_contact_sheet, contact_sheet_size};
+use lab4_g::mesh::torus;
+use lab4_g::postprocess::{ColorLut, FrameAccumulator, FrameFeedback, AutoExposure, PostChain, PostEffect, average_luminance, hue_rotation_matrix, saturation_matrix, luminance_stats};
+use lab4_g::export::{save_png, save_heightmap_png16, PngOptions};
+use lab4_g::heightmap::{bake_heightfield, erode, heightmap_to_normalmap};
+use lab4_g::ray::{ray_mesh, screen_ray};
+use lab4_g::light::SpotLight;
+use lab4_g::shadow::{ShadowMap, ShadowSettings};
+use lab4_g::palettes;
+#[cfg(feature = "serde")]
+use lab4_g::setup;
+use lab4_g::render::*;
+
+const FRAME_DELAY_MS: u64 = 16;
+
+// Qué buffer se muestra en la ventana; los del G-buffer activan el modo diferido
+#[derive(Clone, Copy, PartialEq)]
+enum DebugView {
+    Final,
+    Albedo,
+    Normal,
+    Depth,
+    // Cada triángulo de un color plano según su índice (del id_buffer)
+    TriangleColors,
 }
 
+// Orden en que RenderSetup guarda la vista de depuración
+#[cfg(feature = "serde")]
+const DEBUG_VIEWS: [DebugView; 5] = [DebugView::Final, DebugView::Albedo, DebugView::Normal, DebugView::Depth, DebugView::TriangleColors];
 
 fn main() {
     let window_width = 800;
     let window_height = 600;
     let framebuffer_width = 800;
     let framebuffer_height = 600;
-    let frame_delay = Duration::from_millis(16);
+    let frame_delay = Duration::from_millis(FRAME_DELAY_MS);
 
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    framebuffer.set_id_buffer(true);
     let mut window = Window::new(
         "Animated Fragment Shader",
         window_width,
@@ -169,22 +76,422 @@ fn main() {
     );
 
     let obj = Obj::load("assets/models/sphere.obj").expect("Failed to load obj");
-    let vertex_arrays = obj.get_vertex_array(); 
+    // Mallas que se pueden sombrear: la esfera del modelo y un toro
+    let base_meshes = [obj.get_vertex_array(), torus(0.7, 0.3, 48, 24)];
+    let mut current_mesh = 0;
+    // None: el nivel de teselado se elige según el tamaño del planeta en pantalla
+    let mut tessellation_level = Some(0);
+    let mut displaced = false;
+    let displacement_noise = create_noise();
+    let mut planet_levels = planet_lod(&base_meshes[current_mesh], displaced, &displacement_noise);
     let anillo = Obj::load("assets/models/anillo.obj").expect("Failed to load anillo obj");
-    let vertex_anillo = anillo.get_vertex_array();
-    let mut time = 0;
-
-    let mut current_shader = 1; // Inicia con el shader 1 por defecto
+    let mut vertex_anillo = anillo.get_vertex_array();
+    ring_tex_coords(&mut vertex_anillo, 8.0);
+    let ring_bands = Arc::new(ring_texture(&create_noise()));
+    let mut textured_ring = false;
+    let mut clock = Clock::new();
+
+    let mut current_shader = ShaderKind::Raro; // Inicia con el shader 1 por defecto
+    let mut shown_title = String::new();
+    let mut debug_view = DebugView::Final;
+    // Solo cambia al cargar una configuración (feature serde)
+    #[cfg_attr(not(feature = "serde"), allow(unused_mut))]
+    let mut light_dir = Vec3::new(0.0, 0.0, 1.0);
+    let mut orbit_light = false;
+    let mut params = ShaderParams::default();
+    let color_grades = [ColorLut::identity(), ColorLut::warm(), ColorLut::cool(), ColorLut::high_contrast()];
+    let mut current_grade = 0;
+    // PLANET_NAME: semilla derivada del nombre, reproducible entre ejecuciones
+    let mut seed: u64 = std::env::var("PLANET_NAME").map(|name| seed_from_name(&name)).unwrap_or(0);
+    let mut sepia_strength = 0.0;
+    let mut saturation = 1.0;
+    let mut hue_degrees = 0.0;
+    let mut smoothing = false;
+    let mut render_error: Option<RenderError> = None;
+    let mut show_sky = false;
+    let mut show_swatch = false;
+    let mut show_debug_triangle = false;
+    let mut mouse_was_down = false;
+    let details = [1.0, 2.0, 0.5];
+    let mut current_detail = 0;
+    let intensity_curves = [1.0, 2.0, 0.5];
+    let mut current_intensity_curve = 0;
+    let cellular_ramps = [CELULAR_RAMP, palettes::MARS, palettes::EARTH, palettes::JUPITER, palettes::ICE, palettes::VENUS];
+    let mut current_ramp = 0;
+    let sky_noise = create_noise();
+    let starfield = create_starfield(400, 20.0);
+    // RENDER_THREADS limita los hilos del render (por defecto, todos los núcleos)
+    let mut config = RenderConfig {
+        threads: std::env::var("RENDER_THREADS").ok().and_then(|threads| threads.parse().ok()),
+        ..RenderConfig::default()
+    };
+    // PIXEL_ASPECT: ancho / alto de los píxeles del dispositivo de salida
+    // MAX_TRIANGLES: no dibuja mallas con más triángulos que esto
+    config.max_triangles = std::env::var("MAX_TRIANGLES").ok().and_then(|limit| limit.parse().ok());
+    if let Some(pixel_aspect) = std::env::var("PIXEL_ASPECT").ok().and_then(|aspect| aspect.parse::<f32>().ok()) {
+        config.pixel_aspect = pixel_aspect.max(f32::EPSILON);
+    }
+    let mut frame_feedback = FrameFeedback::new();
+    let mut accumulator = FrameAccumulator::new();
+    let white_balances = [Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.1, 1.0, 0.88), Vec3::new(0.88, 1.0, 1.1)];
+    let mut current_white_balance = 0;
+    let mut auto_exposure: Option<AutoExposure> = None;
+    let mut planet_cache = RenderCache::new();
+    let mut anillo_cache = RenderCache::new();
+    let asteroid_belt = create_asteroid_belt(60, 1.8);
+    let mut asteroid_caches = Vec::new();
+    let mut show_asteroids = false;
+    let mut show_gizmo = false;
 
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
             break;
         }
 
-        time += 1;
+        let time = clock.time();
         handle_input(&window, &mut camera, &mut current_shader);
+        handle_debug_view(&window, &mut debug_view);
+        handle_clock(&window, &mut clock);
+        if window.is_key_pressed(Key::L, KeyRepeat::No) {
+            orbit_light = !orbit_light;
+        }
+        if window.is_key_pressed(Key::I, KeyRepeat::No) {
+            params.ice_caps = match params.ice_caps {
+                Some(_) => None,
+                None => Some(IceCaps::default()),
+            };
+        }
+        if window.is_key_pressed(Key::Y, KeyRepeat::No) {
+            params.latitude_tint = match params.latitude_tint {
+                Some(_) => None,
+                None => Some(LatitudeTint::default()),
+            };
+        }
+        if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            params.subsurface = match params.subsurface {
+                Some(_) => None,
+                None => Some(Subsurface::default()),
+            };
+        }
+        if window.is_key_pressed(Key::F6, KeyRepeat::No) {
+            current_white_balance = (current_white_balance + 1) % white_balances.len();
+        }
+        let white_balance = white_balances[current_white_balance];
+        if window.is_key_pressed(Key::Backspace, KeyRepeat::No) {
+            auto_exposure = match auto_exposure {
+                Some(_) => None,
+                None => Some(AutoExposure::new()),
+            };
+        }
+        if window.is_key_pressed(Key::F8, KeyRepeat::No) {
+            current_detail = (current_detail + 1) % details.len();
+            params.detail = details[current_detail];
+        }
+        if window.is_key_pressed(Key::F9, KeyRepeat::No) {
+            if config.motion_blur_samples > 1 {
+                config.motion_blur_samples = 1;
+            } else {
+                config.motion_blur_samples = 4;
+                config.shutter = 0.25;
+            }
+        }
+        if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            params.contours = match params.contours {
+                Some(_) => None,
+                None => Some(Contours::default()),
+            };
+        }
+        // Enter: reproduce la aparición/desaparición desde el principio
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            params.envelope = match params.envelope {
+                Some(_) => None,
+                None => {
+                    clock.set_time(0.0);
+                    Some(Envelope::default())
+                }
+            };
+        }
+        if window.is_key_pressed(Key::Minus, KeyRepeat::No) {
+            current_intensity_curve = (current_intensity_curve + 1) % intensity_curves.len();
+            params.intensity_curve = intensity_curves[current_intensity_curve];
+        }
+        // [ / ]: menos / más nubes en el planeta gaseoso
+        for (key, delta) in [(Key::LeftBracket, -0.1), (Key::RightBracket, 0.1)] {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                let mut clouds = params.cloud_coverage.unwrap_or_default();
+                clouds.coverage = (clouds.coverage + delta).clamp(0.0, 1.0);
+                params.cloud_coverage = Some(clouds);
+            }
+        }
+        // Insert: guarda la configuración en setup.json, Home: la carga
+        #[cfg(feature = "serde")]
+        {
+            if window.is_key_pressed(Key::Insert, KeyRepeat::No) {
+                let setup = setup::RenderSetup {
+                    shader: current_shader,
+                    params,
+                    cellular_ramp: current_ramp,
+                    camera_eye: camera.eye.into(),
+                    camera_center: camera.center.into(),
+                    camera_up: camera.up.into(),
+                    light_dir: light_dir.into(),
+                    seed,
+                    time: clock.time(),
+                    mesh: current_mesh,
+                    tessellation_level,
+                    displaced,
+                    color_grade: current_grade,
+                    white_balance: current_white_balance,
+                    sepia: sepia_strength,
+                    saturation,
+                    hue_degrees,
+                    smoothing,
+                    auto_exposure: auto_exposure.is_some(),
+                    msaa: framebuffer.msaa_samples > 1,
+                    debug_view: DEBUG_VIEWS.iter().position(|view| *view == debug_view).unwrap_or(0),
+                    depth_prepass: config.depth_prepass,
+                    feedback: config.feedback,
+                    motion_blur_samples: config.motion_blur_samples,
+                    shutter: config.shutter,
+                    alpha_to_coverage: config.alpha_to_coverage,
+                    raster_precision: config.raster_precision,
+                    accumulate: config.accumulate,
+                };
+                if let Err(error) = setup.save("setup.json") {
+                    eprintln!("no se pudo guardar la configuración: {}", error);
+                }
+            }
+            if window.is_key_pressed(Key::Home, KeyRepeat::No) {
+                match setup::RenderSetup::load("setup.json") {
+                    Ok(setup) => {
+                        current_shader = setup.shader;
+                        current_ramp = setup.cellular_ramp.min(cellular_ramps.len() - 1);
+                        params = setup.params;
+                        params.cellular_ramp = cellular_ramps[current_ramp];
+                        camera.eye = setup.camera_eye.into();
+                        camera.center = setup.camera_center.into();
+                        camera.up = setup.camera_up.into();
+                        camera.has_changed = true;
+                        light_dir = setup.light_dir.into();
+                        seed = setup.seed;
+                        clock.set_time(setup.time);
+                        current_mesh = setup.mesh.min(base_meshes.len() - 1);
+                        tessellation_level = setup.tessellation_level.map(|level| level.min(2));
+                        displaced = setup.displaced;
+                        planet_levels = planet_lod(&base_meshes[current_mesh], displaced, &displacement_noise);
+                        planet_cache.invalidate();
+                        current_grade = setup.color_grade.min(color_grades.len() - 1);
+                        current_white_balance = setup.white_balance.min(white_balances.len() - 1);
+                        sepia_strength = setup.sepia;
+                        saturation = setup.saturation;
+                        hue_degrees = setup.hue_degrees;
+                        smoothing = setup.smoothing;
+                        if setup.auto_exposure != auto_exposure.is_some() {
+                            auto_exposure = setup.auto_exposure.then(AutoExposure::new);
+                        }
+                        framebuffer.set_msaa(if setup.msaa { 4 } else { 1 });
+                        debug_view = DEBUG_VIEWS[setup.debug_view.min(DEBUG_VIEWS.len() - 1)];
+                        config.depth_prepass = setup.depth_prepass;
+                        config.feedback = setup.feedback;
+                        config.motion_blur_samples = setup.motion_blur_samples;
+                        config.shutter = setup.shutter;
+                        config.alpha_to_coverage = setup.alpha_to_coverage;
+                        config.raster_precision = setup.raster_precision;
+                        config.accumulate = setup.accumulate;
+                    }
+                    Err(error) => eprintln!("no se pudo cargar la configuración: {}", error),
+                }
+            }
+        }
+        if window.is_key_pressed(Key::PageUp, KeyRepeat::No) {
+            params.spot_light = match params.spot_light {
+                Some(_) => None,
+                None => Some(SpotLight::default()),
+            };
+        }
+        // Espacio: relieve (normal completa -> mezclada a la mitad -> apagado)
+        if window.is_key_pressed(Key::Space, KeyRepeat::No) {
+            (params.bump, params.normal_blend) = match (params.bump, params.normal_blend) {
+                (None, _) => (Some(Bump::default()), 1.0),
+                (Some(bump), blend) if blend >= 1.0 => (Some(bump), 0.5),
+                _ => (None, 1.0),
+            };
+        }
+        if window.is_key_pressed(Key::NumPad1, KeyRepeat::No) {
+            params.cutout = match params.cutout {
+                Some(_) => None,
+                None => Some(Cutout::default()),
+            };
+        }
+        if window.is_key_pressed(Key::NumPad2, KeyRepeat::No) {
+            config.alpha_to_coverage = !config.alpha_to_coverage;
+        }
+        // 6 del teclado numérico: reflejo anisótropo (metal cepillado)
+        if window.is_key_pressed(Key::NumPad6, KeyRepeat::No) {
+            let mut specular = params.specular.unwrap_or_default();
+            specular.anisotropic = match specular.anisotropic {
+                Some(_) => None,
+                None => Some(Anisotropic::default()),
+            };
+            params.specular = Some(specular);
+        }
+        // 8 del teclado numérico: el foco proyecta o no sombras (lo enciende si
+        // estaba apagado); 9: sombras de la luz principal teñidas de azul
+        if window.is_key_pressed(Key::NumPad8, KeyRepeat::No) {
+            let mut spot = params.spot_light.unwrap_or_default();
+            spot.casts_shadow = !spot.casts_shadow;
+            spot.shadow_color = if spot.casts_shadow { Color::new(60, 20, 20) } else { Color::black() };
+            params.spot_light = Some(spot);
+        }
+        if window.is_key_pressed(Key::NumPad9, KeyRepeat::No) {
+            if let Some(shadows) = params.shadows.as_mut() {
+                shadows.shadow_color = if shadows.shadow_color.is_black() { Color::new(40, 50, 110) } else { Color::black() };
+            }
+        }
+        if window.is_key_pressed(Key::NumPadPlus, KeyRepeat::No) {
+            params.cloud_shadows = match params.cloud_shadows {
+                Some(_) => None,
+                None => Some(CloudShadows::default()),
+            };
+        }
+        if window.is_key_pressed(Key::NumPadMinus, KeyRepeat::No) {
+            params.limb_darkening = if params.limb_darkening > 0.0 { 0.0 } else { 0.6 };
+        }
+        if window.is_key_pressed(Key::NumPadEnter, KeyRepeat::No) {
+            textured_ring = !textured_ring;
+        }
+        if window.is_key_pressed(Key::NumPadDot, KeyRepeat::No) {
+            params.tint_by_vertex_color = !params.tint_by_vertex_color;
+        }
+        if window.is_key_pressed(Key::NumPad7, KeyRepeat::No) {
+            show_gizmo = !show_gizmo;
+        }
+        if window.is_key_pressed(Key::NumPad5, KeyRepeat::No) {
+            params.spot_invert = !params.spot_invert;
+        }
+        if window.is_key_pressed(Key::NumPad3, KeyRepeat::No) {
+            config.accumulate = !config.accumulate;
+            accumulator.reset();
+        }
+        if window.is_key_pressed(Key::PageDown, KeyRepeat::No) {
+            params.shadows = match params.shadows {
+                Some(_) => None,
+                None => Some(ShadowSettings::default()),
+            };
+        }
+        if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+            show_debug_triangle = !show_debug_triangle;
+        }
+        if window.is_key_pressed(Key::B, KeyRepeat::No) {
+            show_asteroids = !show_asteroids;
+        }
+        if window.is_key_pressed(Key::O, KeyRepeat::No) {
+            params.outline = match params.outline {
+                Some(_) => None,
+                None => Some(Outline::default()),
+            };
+        }
+        // H: sin brillo -> brillo del color del albedo -> brillo blanco
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            params.specular = match params.specular {
+                None => Some(Specular::default()),
+                Some(Specular { color: None, .. }) => Some(Specular { color: Some(Color::new(255, 255, 255)), ..Specular::default() }),
+                Some(_) => None,
+            };
+        }
+        if window.is_key_pressed(Key::J, KeyRepeat::No) {
+            params.normal_space = match params.normal_space {
+                NormalSpace::World => NormalSpace::View,
+                NormalSpace::View => NormalSpace::World,
+            };
+        }
+        if window.is_key_pressed(Key::U, KeyRepeat::No) {
+            params.volumetric_clouds = match params.volumetric_clouds {
+                Some(_) => None,
+                None => Some(VolumetricClouds::default()),
+            };
+        }
+        if window.is_key_pressed(Key::N, KeyRepeat::No) {
+            seed += 1;
+        }
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            tessellation_level = match tessellation_level {
+                Some(level) if level < 2 => Some(level + 1),
+                Some(_) => None,
+                None => Some(0),
+            };
+        }
+        if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            current_mesh = (current_mesh + 1) % base_meshes.len();
+            planet_levels = planet_lod(&base_meshes[current_mesh], displaced, &displacement_noise);
+            planet_cache.invalidate();
+        }
+        if window.is_key_pressed(Key::NumPad4, KeyRepeat::No) {
+            displaced = !displaced;
+            planet_levels = planet_lod(&base_meshes[current_mesh], displaced, &displacement_noise);
+            planet_cache.invalidate();
+        }
+        if window.is_key_pressed(Key::X, KeyRepeat::No) {
+            current_ramp = (current_ramp + 1) % cellular_ramps.len();
+            params.cellular_ramp = cellular_ramps[current_ramp];
+        }
+        if window.is_key_pressed(Key::F, KeyRepeat::No) {
+            config.feedback = if config.feedback > 0.0 { 0.0 } else { 0.8 };
+        }
+        if window.is_key_pressed(Key::Backslash, KeyRepeat::No) {
+            camera.set_distance(current_shader.recommended_camera_distance());
+        }
+        if window.is_key_pressed(Key::Apostrophe, KeyRepeat::No) {
+            params.reflection_color = match params.reflection_color {
+                Some(_) => None,
+                None => Some(Color::new(60, 90, 150)),
+            };
+        }
+        if window.is_key_pressed(Key::Semicolon, KeyRepeat::No) {
+            config.raster_precision = match config.raster_precision {
+                RasterPrecision::Float => RasterPrecision::FixedPoint,
+                RasterPrecision::FixedPoint => RasterPrecision::Float,
+            };
+        }
+        if window.is_key_pressed(Key::Z, KeyRepeat::No) {
+            config.depth_prepass = !config.depth_prepass;
+        }
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            framebuffer.set_msaa(if framebuffer.msaa_samples > 1 { 1 } else { 4 });
+        }
+        if window.is_key_pressed(Key::G, KeyRepeat::No) {
+            show_swatch = !show_swatch;
+        }
+        if window.is_key_pressed(Key::K, KeyRepeat::No) {
+            show_sky = !show_sky;
+        }
+        if window.is_key_pressed(Key::Delete, KeyRepeat::No) {
+            smoothing = !smoothing;
+        }
+        if window.is_key_pressed(Key::NumPadAsterisk, KeyRepeat::No) {
+            saturation = if saturation == 1.0 { 0.0 } else if saturation == 0.0 { 1.5 } else { 1.0 };
+        }
+        if window.is_key_pressed(Key::NumPadSlash, KeyRepeat::No) {
+            hue_degrees = (hue_degrees + 30.0) % 360.0;
+        }
+        if window.is_key_pressed(Key::V, KeyRepeat::No) {
+            sepia_strength = if sepia_strength >= 1.0 { 0.0 } else { sepia_strength + 0.5 };
+        }
+        if window.is_key_pressed(Key::C, KeyRepeat::No) {
+            current_grade = (current_grade + 1) % color_grades.len();
+        }
+        if framebuffer.deferred != (debug_view != DebugView::Final) {
+            framebuffer.set_deferred(debug_view != DebugView::Final);
+        }
+
+        // La vista cambió: el promedio acumulado ya no corresponde a la imagen
+        if camera.check_if_changed() || orbit_light {
+            accumulator.reset();
+        }
+        let jitter = if config.accumulate { accumulator.jitter() } else { Vec2::zeros() };
 
         framebuffer.clear();
+        let mut stats = RenderStats::default();
 
         let noise1 = create_noise();
         let noise2 = create_noise();
@@ -193,79 +500,328 @@ fn main() {
         let anillo_matrix = create_model_matrix(translation, scale, rotation);
         let view_matrix = create_view_matrix(camera.eye, camera.center, camera.up);
         let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-        let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
-        let uniforms = Uniforms { 
+        let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32, config.pixel_aspect, jitter);
+        let mut uniforms = Uniforms { 
             model_matrix, 
             view_matrix, 
             projection_matrix, 
             viewport_matrix,
             time,
-            noise: noise1
+            noise: Arc::new(noise1),
+            light_dir,
+            camera_pos: camera.eye,
+            params,
+            seed,
+            white_balance,
+            exposure: auto_exposure.as_ref().map_or(1.0, |auto| auto.exposure),
+            shadow_map: None,
+            spot_shadow_map: None,
+            texture: None,
         };
-        let uniforms_anillo = Uniforms { 
+        let mut uniforms_anillo = Uniforms { 
             model_matrix:anillo_matrix, 
             view_matrix, 
             projection_matrix, 
             viewport_matrix,
             time,
-            noise: noise2
+            noise: Arc::new(noise2),
+            light_dir,
+            camera_pos: camera.eye,
+            params,
+            seed,
+            white_balance,
+            exposure: auto_exposure.as_ref().map_or(1.0, |auto| auto.exposure),
+            shadow_map: None,
+            spot_shadow_map: None,
+            texture: textured_ring.then(|| ring_bands.clone()),
+        };
+        let level = tessellation_level.map_or_else(|| planet_levels.select(&uniforms), |level| level as usize);
+        let vertex_arrays = planet_levels.level(level);
+        if orbit_light {
+            uniforms.orbit_light(Vec3::new(0.0, 1.0, 0.0), PI / 4.0);
+            uniforms_anillo.orbit_light(Vec3::new(0.0, 1.0, 0.0), PI / 4.0);
+        }
+        if let Some(shadows) = params.shadows {
+            let build_shadow_map = |light_dir: Vec3| {
+                let mut shadow_map = ShadowMap::new(light_dir, 2.5, shadows.resolution);
+                shadow_map.rasterize(vertex_arrays, &uniforms.model_matrix);
+                if window.is_key_down(Key::Key8) {
+                    shadow_map.rasterize(&vertex_anillo, &uniforms_anillo.model_matrix);
+                }
+                if show_asteroids {
+                    for transform in &asteroid_belt {
+                        shadow_map.rasterize(vertex_arrays, transform);
+                    }
+                }
+                Arc::new(shadow_map)
+            };
+            let shadow_map = build_shadow_map(uniforms.light_dir);
+            // El foco se trata como luz direccional a lo largo de su eje
+            let spot_shadow_map = params.spot_light
+                .filter(|spot| spot.casts_shadow)
+                .map(|spot| build_shadow_map(-spot.direction));
+            uniforms.shadow_map = Some(shadow_map.clone());
+            uniforms_anillo.shadow_map = Some(shadow_map);
+            uniforms.spot_shadow_map = spot_shadow_map.clone();
+            uniforms_anillo.spot_shadow_map = spot_shadow_map;
+        }
+
+        if show_sky {
+            framebuffer.sky_gradient_banded(Color::new(5, 5, 30), Color::new(60, 40, 90), &sky_noise, 0.05);
+            draw_points(&mut framebuffer, &Uniforms { model_matrix: Mat4::identity(), ..uniforms.clone() }, &starfield, 1.5, None);
+        }
+
+        // Los errores de dibujo se avisan una vez, no en cada frame
+        let mut frame_error = None;
+        let mut record = |result: Result<RenderStats, RenderError>| match result {
+            Ok(drawn) => drawn,
+            Err(error) => {
+                frame_error = Some(error);
+                RenderStats::default()
+            }
+        };
+        if show_debug_triangle {
+            stats += record(draw_debug_triangle(&mut framebuffer, &mut uniforms, current_shader, &config));
+        } else if show_swatch {
+            let swatch = render_shader_swatch(framebuffer_width, framebuffer_height, current_shader, &uniforms);
+            for (pixel, color) in framebuffer.buffer.iter_mut().zip(swatch) {
+                *pixel = color.to_hex();
+            }
+        } else {
+            framebuffer.set_current_color(0xFFDDDD);
+            let mut tecla = 0;
+            if window.is_key_down(Key::Key8) {
+                tecla = 8;
+            }
+            match tecla{
+                8 => {
+                    stats += record(render(&mut framebuffer, &uniforms, vertex_arrays, current_shader, &mut planet_cache, &config, &DrawOptions::default())); 
+                    stats += record(render(&mut framebuffer, &uniforms_anillo, &vertex_anillo, current_shader, &mut anillo_cache, &config, &DrawOptions { two_sided: true, ..DrawOptions::default() }));},
+                _ => {stats += record(render(&mut framebuffer, &uniforms, vertex_arrays, current_shader, &mut planet_cache, &config, &DrawOptions::default()));}
+            }
+            if show_asteroids {
+                stats += record(draw_instanced(&mut framebuffer, vertex_arrays, &asteroid_belt, &mut uniforms, ShaderKind::Rocoso, &mut asteroid_caches, &config));
+            }
+        }
+        if frame_error != render_error {
+            if let Some(error) = &frame_error {
+                eprintln!("no se pudo dibujar: {}", error);
+            }
+            render_error = frame_error;
+        }
+
+        framebuffer.resolve_msaa();
+        if config.accumulate {
+            accumulator.accumulate(&mut framebuffer.buffer);
+        }
+        frame_feedback.apply(&mut framebuffer.buffer, config.feedback);
+        // La exposición se mide solo sobre la geometría: el fondo negro la
+        // dispararía al máximo. El frame ya lleva la exposición actual, así que
+        // se deshace para medir la luminancia de la escena
+        if let Some(auto) = auto_exposure.as_mut() {
+            let visible: Vec<Color> = framebuffer.buffer.iter()
+                .zip(&framebuffer.zbuffer)
+                .filter(|(_, depth)| depth.is_finite())
+                .map(|(pixel, _)| Color::from_hex(*pixel))
+                .collect();
+            if !visible.is_empty() {
+                uniforms.exposure = auto.update(average_luminance(&visible) / uniforms.exposure);
+            }
+        }
+        let animated = if current_shader.is_animated() { " (animado)" } else { "" };
+        let mut title = format!("{}{}", current_shader.name(), animated);
+        if config.accumulate {
+            title += &format!(" - {} frames acumulados", accumulator.samples());
+        }
+        if config.depth_prepass {
+            title += &format!(" - sombreados {} de {} fragmentos", stats.shaded, stats.fragments);
+        }
+        if title != shown_title {
+            window.set_title(&title);
+            shown_title = title;
+        }
+
+        // Suavizado, matriz de color, gradación y sepia, en el orden de PostChain
+        let mut post_chain = PostChain::new();
+        if smoothing {
+            post_chain.push(PostEffect::Smoothing(1.5, 0.1));
+        }
+        post_chain
+            .push(PostEffect::ColorMatrix(hue_rotation_matrix(hue_degrees) * saturation_matrix(saturation)))
+            .push(PostEffect::ColorGrade(Box::new(color_grades[current_grade].clone())))
+            .push(PostEffect::Sepia(sepia_strength));
+        post_chain.apply(&mut framebuffer.buffer, framebuffer_width, framebuffer_height);
+        if show_gizmo {
+            draw_axis_gizmo(&mut framebuffer, &uniforms, 40.0);
+        }
+
+        let depth_image;
+        let triangle_image: Vec<u32>;
+        let displayed = match debug_view {
+            DebugView::Final => &framebuffer.buffer,
+            DebugView::Albedo => &framebuffer.albedo_buffer,
+            DebugView::Normal => &framebuffer.normal_buffer,
+            DebugView::Depth => {
+                depth_image = framebuffer.depth_image();
+                &depth_image
+            }
+            DebugView::TriangleColors => {
+                triangle_image = framebuffer.id_buffer.iter()
+                    .map(|&id| if id == 0 { 0x000000 } else { hash_to_color(id).to_hex() })
+                    .collect();
+                &triangle_image
+            }
         };
 
-        framebuffer.set_current_color(0xFFDDDD);
-        let mut tecla = 0;
-        if window.is_key_down(Key::Key8) {
-            tecla = 8;
+        // Clic izquierdo: imprime el color y la profundidad del píxel bajo el cursor
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if mouse_down && !mouse_was_down {
+            if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                if let Some((color, depth)) = framebuffer.sample_pixel(x as usize, y as usize) {
+                    let id = framebuffer.id_at(x as usize, y as usize).unwrap_or(0);
+                    println!("({}, {}): {} profundidad {} triángulo {}", x as usize, y as usize, color, depth, id);
+                }
+                // El mismo píxel como rayo contra la malla del planeta, en espacio de mundo
+                let (origin, direction) = screen_ray(x, y, &uniforms);
+                if let Some(distance) = ray_mesh(origin, direction, vertex_arrays, &uniforms.model_matrix) {
+                    let hit = origin + direction * distance;
+                    println!("  punto en el planeta: ({:.3}, {:.3}, {:.3})", hit.x, hit.y, hit.z);
+                }
+            }
+        }
+        mouse_was_down = mouse_down;
+
+        // End: imprime el histograma de luminancia del frame mostrado
+        if window.is_key_pressed(Key::End, KeyRepeat::No) {
+            let colors: Vec<Color> = displayed.iter().map(|&pixel| Color::from_hex(pixel)).collect();
+            let luminance = luminance_stats(&colors, 16);
+            println!("luminancia: mín {:.3}, máx {:.3}, media {:.3}", luminance.min, luminance.max, luminance.mean);
+            println!("histograma (16 intervalos): {:?}", luminance.histogram);
+        }
+
+        // Igual (=): guarda todos los shaders lado a lado en hoja_de_contacto.png
+        if window.is_key_pressed(Key::Equal, KeyRepeat::No) {
+            let (cell_width, cell_height) = (200, 150);
+            let sheet: Vec<u32> = render_contact_sheet(cell_width, cell_height, &uniforms).iter().map(Color::to_hex).collect();
+            let (width, height) = contact_sheet_size(cell_width, cell_height);
+            let options = PngOptions { origin: framebuffer.origin, premultiply_alpha: false };
+            if let Err(error) = save_png("hoja_de_contacto.png", &sheet, None, width, height, options) {
+                eprintln!("no se pudo guardar la hoja de contacto: {}", error);
+            }
         }
-        match tecla{
-            8 => {
-                render(&mut framebuffer, &uniforms, &vertex_arrays, current_shader); 
-                render(&mut framebuffer, &uniforms_anillo, &vertex_anillo, current_shader);},
-            _ => {render(&mut framebuffer, &uniforms, &vertex_arrays, current_shader);}
+
+        // /: hornea el relieve del planeta rocoso (erosionado) en altura.png y sus
+        // normales en normales.png
+        if window.is_key_pressed(Key::Slash, KeyRepeat::No) {
+            let (width, height) = (512, 256);
+            let mut heightfield = bake_heightfield(uniforms.noise.as_ref(), width, height, 4.0);
+            erode(&mut heightfield, width, height, 50);
+            if let Err(error) = save_heightmap_png16("altura.png", width, height, &heightfield) {
+                eprintln!("no se pudo guardar el mapa de alturas: {}", error);
+            }
+            let normals: Vec<u32> = heightmap_to_normalmap(&heightfield, width, height, 8.0).iter().map(Color::to_hex).collect();
+            let options = PngOptions { origin: framebuffer.origin, premultiply_alpha: false };
+            if let Err(error) = save_png("normales.png", &normals, None, width, height, options) {
+                eprintln!("no se pudo guardar el mapa de normales: {}", error);
+            }
+        }
+
+        // F12: captura con alfa normal, F11: con alfa premultiplicado
+        for (key, path, premultiply_alpha) in [(Key::F12, "captura.png", false), (Key::F11, "captura_premultiplicada.png", true)] {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                let alpha = framebuffer.alpha();
+                let options = PngOptions { origin: framebuffer.origin, premultiply_alpha };
+                if let Err(error) = save_png(path, displayed, Some(&alpha), framebuffer_width, framebuffer_height, options) {
+                    eprintln!("no se pudo guardar la captura: {}", error);
+                }
+            }
         }
 
         window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
+            .update_with_buffer(displayed, framebuffer_width, framebuffer_height)
             .unwrap();
 
         std::thread::sleep(frame_delay);
     }
 }
 
-fn handle_input(window: &Window, camera: &mut Camera, current_shader: &mut u8) {
+// P: pausa, R: vuelve a 0, coma/punto: mitad/doble de velocidad
+fn handle_clock(window: &Window, clock: &mut Clock) {
+    if window.is_key_pressed(Key::P, KeyRepeat::No) {
+        if clock.is_paused() {
+            clock.resume();
+        } else {
+            clock.pause();
+        }
+    }
+    if window.is_key_pressed(Key::R, KeyRepeat::No) {
+        clock.set_time(0.0);
+    }
+    if window.is_key_pressed(Key::Comma, KeyRepeat::No) {
+        clock.set_scale(clock.scale() * 0.5);
+    }
+    if window.is_key_pressed(Key::Period, KeyRepeat::No) {
+        clock.set_scale(clock.scale() * 2.0);
+    }
+}
+
+// F1: imagen final, F2: albedo, F3: normales, F4: profundidad
+fn handle_debug_view(window: &Window, debug_view: &mut DebugView) {
+    if window.is_key_down(Key::F1) {
+        *debug_view = DebugView::Final;
+    }
+    if window.is_key_down(Key::F2) {
+        *debug_view = DebugView::Albedo;
+    }
+    if window.is_key_down(Key::F3) {
+        *debug_view = DebugView::Normal;
+    }
+    if window.is_key_down(Key::F4) {
+        *debug_view = DebugView::Depth;
+    }
+    if window.is_key_down(Key::Backquote) {
+        *debug_view = DebugView::TriangleColors;
+    }
+}
+
+fn handle_input(window: &Window, camera: &mut Camera, current_shader: &mut ShaderKind) {
     let movement_speed = 1.0;
     let rotation_speed = PI / 50.0;
     let zoom_speed = 0.1;
 
     // Cambiar shader con teclas numéricas
     if window.is_key_down(Key::Key0) {
-        *current_shader = 0;
+        *current_shader = ShaderKind::Neon;
     }
     if window.is_key_down(Key::Key1) {
-        *current_shader = 1;
+        *current_shader = ShaderKind::Raro;
     }
     if window.is_key_down(Key::Key2) {
-        *current_shader = 2;
+        *current_shader = ShaderKind::Saturno;
     }
     if window.is_key_down(Key::Key3) {
-        *current_shader = 3;
+        *current_shader = ShaderKind::Azul;
     }
     if window.is_key_down(Key::Key4) {
-        *current_shader = 4;
+        *current_shader = ShaderKind::Celular;
     }
     if window.is_key_down(Key::Key5) {
-        *current_shader = 5;
+        *current_shader = ShaderKind::Mancha;
     }
     if window.is_key_down(Key::Key6) {
-        *current_shader = 6;
+        *current_shader = ShaderKind::Sol;
     }
     if window.is_key_down(Key::Key7) {
-        *current_shader = 7;
+        *current_shader = ShaderKind::Rocoso;
     }
     if window.is_key_down(Key::Key8) {
-        *current_shader = 8;
+        *current_shader = ShaderKind::Gaseoso;
     }
     if window.is_key_down(Key::Key9) {
-        *current_shader = 9;
+        *current_shader = ShaderKind::Arcilla;
+    }
+    if window.is_key_down(Key::NumPad0) {
+        *current_shader = ShaderKind::Terrestre;
     }
 
    
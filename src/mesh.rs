@@ -0,0 +1,318 @@
+use nalgebra_glm::{Vec2, Vec3, dot};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use crate::noise::NoiseSource;
+use crate::vertex::Vertex;
+
+// Cómo se agrupan los vértices de una malla en triángulos
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum PrimitiveTopology {
+    // Cada 3 vértices, un triángulo
+    #[default]
+    Triangles,
+    // Cada vértice nuevo forma un triángulo con los dos anteriores. Los
+    // triángulos impares se invierten ((1, 0, 2) en vez de (0, 1, 2)) para que
+    // todos conserven el sentido de giro del primero
+    TriangleStrip,
+    // Todos los triángulos comparten el vértice 0: (0, i, i + 1)
+    TriangleFan,
+}
+
+// Índices de los triángulos que forman `count` vértices con la topología dada
+pub fn assemble(count: usize, topology: PrimitiveTopology) -> Vec<[usize; 3]> {
+    match topology {
+        PrimitiveTopology::Triangles => (0..count / 3).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect(),
+        PrimitiveTopology::TriangleStrip => (0..count.saturating_sub(2))
+            .map(|i| if i % 2 == 0 { [i, i + 1, i + 2] } else { [i + 1, i, i + 2] })
+            .collect(),
+        PrimitiveTopology::TriangleFan => (1..count.saturating_sub(1)).map(|i| [0, i, i + 1]).collect(),
+    }
+}
+
+// Lista de triángulos con la malla original seguida de su reflejo en el plano
+// que pasa por el origen con normal plane_normal. El reflejo invierte el
+// sentido de giro, así que sus triángulos se reordenan (a, c, b) y las normales
+// también se reflejan para que la iluminación siga siendo correcta
+pub fn with_mirror(vertices: &[Vertex], topology: PrimitiveTopology, plane_normal: Vec3) -> Vec<Vertex> {
+    let plane_normal = plane_normal.normalize();
+    let reflect = |v: Vec3| v - plane_normal * (2.0 * dot(&v, &plane_normal));
+    let triangles = assemble(vertices.len(), topology);
+
+    let mut mirrored = Vec::with_capacity(triangles.len() * 6);
+    for [a, b, c] in &triangles {
+        mirrored.extend([vertices[*a].clone(), vertices[*b].clone(), vertices[*c].clone()]);
+    }
+    for [a, b, c] in &triangles {
+        for index in [*a, *c, *b] {
+            let mut vertex = vertices[index].clone();
+            vertex.position = reflect(vertex.position);
+            vertex.normal = reflect(vertex.normal);
+            mirrored.push(vertex);
+        }
+    }
+    mirrored
+}
+
+// Subdivide cada triángulo en 4 usando los puntos medios de sus aristas,
+// `level` veces, interpolando normal, coordenadas de textura y color.
+// Ojo: el número de triángulos se multiplica por 4 en cada nivel (4^level);
+// la esfera de 960 triángulos pasa a 15 360 con level = 2 y a 245 760 con 4
+pub fn tessellate(vertices: &[Vertex], level: u32) -> Vec<Vertex> {
+    let mut current = vertices.to_vec();
+
+    for _ in 0..level {
+        let mut next = Vec::with_capacity(current.len() * 4);
+        for tri in current.chunks_exact(3) {
+            let (a, b, c) = (&tri[0], &tri[1], &tri[2]);
+            let ab = midpoint(a, b);
+            let bc = midpoint(b, c);
+            let ca = midpoint(c, a);
+
+            next.extend([a.clone(), ab.clone(), ca.clone()]);
+            next.extend([ab.clone(), b.clone(), bc.clone()]);
+            next.extend([ca.clone(), bc.clone(), c.clone()]);
+            next.extend([ab, bc, ca]);
+        }
+        current = next;
+    }
+
+    current
+}
+
+// Unifica los vértices que están a menos de epsilon entre sí: todos toman la
+// posición del primero y la normal promedio del grupo. Las tex_coords no se
+// tocan, así que la costura UV de la esfera sigue teniendo dos juegos de
+// coordenadas, pero sus vértices ya coinciden exactamente
+pub fn weld_vertices(vertices: &mut [Vertex], epsilon: f32) {
+    let epsilon = epsilon.max(f32::EPSILON);
+    let cell = |p: &Vec3| ((p.x / epsilon).floor() as i64, (p.y / epsilon).floor() as i64, (p.z / epsilon).floor() as i64);
+
+    // Celdas de lado epsilon -> grupos (posición representante, suma de normales)
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    let mut groups: Vec<(Vec3, Vec3)> = Vec::new();
+    let mut group_of = Vec::with_capacity(vertices.len());
+
+    for vertex in vertices.iter() {
+        let (cx, cy, cz) = cell(&vertex.position);
+        // Un vecino a menos de epsilon puede caer en cualquiera de las 27 celdas alrededor
+        let found = (-1..=1).flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (cx + dx, cy + dy, cz + dz))))
+            .filter_map(|key| grid.get(&key))
+            .flatten()
+            .copied()
+            .find(|&group| (groups[group].0 - vertex.position).magnitude() <= epsilon);
+
+        let group = match found {
+            Some(group) => group,
+            None => {
+                groups.push((vertex.position, Vec3::zeros()));
+                grid.entry((cx, cy, cz)).or_default().push(groups.len() - 1);
+                groups.len() - 1
+            }
+        };
+        groups[group].1 += vertex.normal;
+        group_of.push(group);
+    }
+
+    for (vertex, group) in vertices.iter_mut().zip(group_of) {
+        let (position, normal_sum) = groups[group];
+        vertex.position = position;
+        if normal_sum.magnitude() > 0.0 {
+            vertex.normal = normal_sum.normalize();
+        }
+    }
+}
+
+// Desplaza cada vértice a lo largo de su normal según el ruido en su posición
+// de objeto (no en UV): dos vértices soldados reciben siempre el mismo
+// desplazamiento y la costura no se abre
+pub fn displace(vertices: &mut [Vertex], noise: &dyn NoiseSource, amount: f32, zoom: f32) {
+    for vertex in vertices.iter_mut() {
+        let p = vertex.position * zoom;
+        vertex.position += vertex.normal * noise.get_noise_3d(p.x, p.y, p.z) * amount;
+    }
+}
+
+fn midpoint(a: &Vertex, b: &Vertex) -> Vertex {
+    let normal = (a.normal + b.normal) * 0.5;
+    let normal = if normal.magnitude() > 0.0 { normal.normalize() } else { a.normal };
+
+    let mut vertex = Vertex::new(
+        (a.position + b.position) * 0.5,
+        normal,
+        (a.tex_coords + b.tex_coords) * 0.5,
+    );
+    vertex.color = a.color.lerp(&b.color, 0.5);
+    vertex
+}
+
+// Esfera de radio 1 a partir de un icosaedro teselado `level` veces, con los
+// vértices proyectados a la esfera. Normales hacia afuera y tex_coords
+// esféricas; 20 * 4^level triángulos
+pub fn icosphere(level: u32) -> Vec<Vertex> {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let corners = [
+        Vec3::new(-1.0, t, 0.0), Vec3::new(1.0, t, 0.0), Vec3::new(-1.0, -t, 0.0), Vec3::new(1.0, -t, 0.0),
+        Vec3::new(0.0, -1.0, t), Vec3::new(0.0, 1.0, t), Vec3::new(0.0, -1.0, -t), Vec3::new(0.0, 1.0, -t),
+        Vec3::new(t, 0.0, -1.0), Vec3::new(t, 0.0, 1.0), Vec3::new(-t, 0.0, -1.0), Vec3::new(-t, 0.0, 1.0),
+    ];
+    let faces = [
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    let on_sphere = |position: Vec3| {
+        let normal = position.normalize();
+        let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+        let v = 0.5 - normal.y.asin() / PI;
+        Vertex::new(normal, normal, Vec2::new(u, v))
+    };
+
+    let base: Vec<Vertex> = faces.iter().flatten().map(|&index| on_sphere(corners[index])).collect();
+    tessellate(&base, level).into_iter().map(|vertex| on_sphere(vertex.position)).collect()
+}
+
+// Toro centrado en el origen, con el eje en Y, como lista de triángulos. major
+// recorre el anillo y minor el tubo; la costura se cierra repitiendo la primera
+// columna y la primera fila con tex_coords 1 para que la textura no salte
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> Vec<Vertex> {
+    let major_segments = major_segments.max(3);
+    let minor_segments = minor_segments.max(3);
+
+    let vertex_at = |i: u32, j: u32| {
+        let u = i as f32 / major_segments as f32;
+        let v = j as f32 / minor_segments as f32;
+        let (sin_u, cos_u) = (u * 2.0 * PI).sin_cos();
+        let (sin_v, cos_v) = (v * 2.0 * PI).sin_cos();
+
+        // Centro del tubo en este ángulo del anillo y normal hacia afuera del tubo
+        let center = Vec3::new(cos_u * major_radius, 0.0, sin_u * major_radius);
+        let normal = Vec3::new(cos_u * cos_v, sin_v, sin_u * cos_v);
+        Vertex::new(center + normal * minor_radius, normal, Vec2::new(u, v))
+    };
+
+    let mut vertices = Vec::with_capacity((major_segments * minor_segments * 6) as usize);
+    for i in 0..major_segments {
+        for j in 0..minor_segments {
+            let a = vertex_at(i, j);
+            let b = vertex_at(i + 1, j);
+            let c = vertex_at(i + 1, j + 1);
+            let d = vertex_at(i, j + 1);
+            vertices.extend([a.clone(), b, c.clone()]);
+            vertices.extend([a, c, d]);
+        }
+    }
+
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::raster::triangle_area_2d;
+    use crate::noise::LinearNoise;
+
+    #[test]
+    fn one_level_splits_each_triangle_into_four_with_midpoint_attributes() {
+        let mut a = Vertex::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0));
+        let mut b = Vertex::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(1.0, 0.0));
+        let c = Vertex::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 1.0));
+        a.normal = Vec3::new(1.0, 0.0, 0.0);
+        a.color = Color::new(255, 0, 0);
+        b.color = Color::new(0, 0, 255);
+
+        let tessellated = tessellate(&[a, b, c], 1);
+        assert_eq!(tessellated.len(), 12);
+
+        // El primer triángulo es (a, ab, ca): ab es el punto medio entre a y b
+        let ab = &tessellated[1];
+        assert_eq!(ab.position, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(ab.tex_coords, Vec2::new(0.5, 0.0));
+        assert_eq!(ab.color, Color::new(128, 0, 128));
+        assert!((ab.normal - Vec3::new(1.0, 0.0, 1.0).normalize()).magnitude() < 1e-6);
+        // El triángulo central es (ab, bc, ca)
+        assert_eq!(tessellated[9].position, ab.position);
+        assert_eq!(tessellated[10].position, Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(tessellated[11].position, Vec3::new(0.0, 1.0, 0.0));
+
+        assert_eq!(tessellate(&tessellated[..3], 0).len(), 3);
+    }
+
+    #[test]
+    fn a_four_vertex_strip_makes_two_triangles_with_the_same_winding() {
+        // Zigzag de un quad: 0 y 2 abajo, 1 y 3 arriba
+        let points = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)];
+        let triangles = assemble(4, PrimitiveTopology::TriangleStrip);
+        assert_eq!(triangles, vec![[0, 1, 2], [2, 1, 3]]);
+
+        let winding = |[a, b, c]: [usize; 3]| triangle_area_2d(&points[a], &points[b], &points[c]).signum();
+        assert_eq!(winding(triangles[0]), winding(triangles[1]));
+
+        assert_eq!(assemble(5, PrimitiveTopology::TriangleFan), vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        assert_eq!(assemble(7, PrimitiveTopology::Triangles), vec![[0, 1, 2], [3, 4, 5]]);
+    }
+
+    #[test]
+    fn torus_vertices_lie_on_the_surface_with_normals_out_of_the_tube() {
+        let (major, minor) = (2.0, 0.5);
+        let vertices = torus(major, minor, 24, 12);
+        assert_eq!(vertices.len(), 24 * 12 * 6);
+
+        for vertex in &vertices {
+            // Centro del tubo más cercano: el punto del anillo en la misma dirección
+            let ring = Vec3::new(vertex.position.x, 0.0, vertex.position.z).normalize() * major;
+            let offset = vertex.position - ring;
+            assert!((offset.magnitude() - minor).abs() < 1e-4, "{:?}", vertex.position);
+            assert!((vertex.normal.magnitude() - 1.0).abs() < 1e-4);
+            assert!((vertex.normal - offset / minor).magnitude() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn the_mirror_copy_flips_x_and_the_normal_and_keeps_the_winding() {
+        let normal = Vec3::new(0.6, 0.8, 0.0);
+        let triangle = [
+            Vertex::new(Vec3::new(1.0, 0.2, 0.3), normal, Vec2::zeros()),
+            Vertex::new(Vec3::new(2.0, 0.0, 0.0), normal, Vec2::zeros()),
+            Vertex::new(Vec3::new(1.5, 1.0, 0.0), normal, Vec2::zeros()),
+        ];
+        let mirrored = with_mirror(&triangle, PrimitiveTopology::Triangles, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(mirrored.len(), 6);
+        assert_eq!(mirrored[0].position, triangle[0].position);
+
+        // El primer vértice reflejado es el de +x, ahora en -x con la normal reflejada
+        assert_eq!(mirrored[3].position, Vec3::new(-1.0, 0.2, 0.3));
+        assert_eq!(mirrored[3].normal, Vec3::new(-0.6, 0.8, 0.0));
+
+        // El reflejo se reordena para que siga mirando hacia el mismo lado
+        let area = |tri: &[Vertex]| triangle_area_2d(&tri[0].position, &tri[1].position, &tri[2].position);
+        assert!(area(&mirrored[..3]) * area(&mirrored[3..]) > 0.0);
+    }
+
+    #[test]
+    fn welding_closes_the_seam_so_displacement_leaves_no_gap() {
+        // Dos copias del mismo vértice de la costura, con distintas UV y normales algo distintas
+        let seam = || vec![
+            Vertex::new(Vec3::new(0.6, 0.0, 0.8), Vec3::new(0.6, 0.1, 0.8).normalize(), Vec2::new(0.0, 0.5)),
+            Vertex::new(Vec3::new(0.6, 0.0, 0.800_001), Vec3::new(0.6, -0.1, 0.8).normalize(), Vec2::new(1.0, 0.5)),
+            Vertex::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec2::new(0.5, 0.0)),
+        ];
+        let noise = LinearNoise { scale: 0.3, offset: 0.2 };
+
+        let mut open = seam();
+        displace(&mut open, &noise, 0.5, 1.0);
+        assert!((open[0].position - open[1].position).magnitude() > 0.01);
+
+        let mut welded = seam();
+        weld_vertices(&mut welded, 1e-4);
+        assert_eq!(welded[0].position, welded[1].position);
+        assert_eq!(welded[0].normal, welded[1].normal);
+        assert_ne!(welded[0].tex_coords, welded[1].tex_coords);
+        assert_eq!(welded[2].position, Vec3::new(0.0, 1.0, 0.0));
+
+        displace(&mut welded, &noise, 0.5, 1.0);
+        assert_eq!(welded[0].position, welded[1].position);
+    }
+}
@@ -0,0 +1,107 @@
+use fastnoise_lite::FastNoiseLite;
+
+// Fuente de ruido que consumen los shaders. Permite cambiar el algoritmo (o
+// usar ruido precalculado) sin tocar los shaders. Send + Sync porque el
+// render sombrea fragmentos en paralelo
+pub trait NoiseSource: Send + Sync {
+    fn get_noise_2d(&self, x: f32, y: f32) -> f32;
+    fn get_noise_3d(&self, x: f32, y: f32, z: f32) -> f32;
+}
+
+impl NoiseSource for FastNoiseLite {
+    fn get_noise_2d(&self, x: f32, y: f32) -> f32 {
+        FastNoiseLite::get_noise_2d(self, x, y)
+    }
+
+    fn get_noise_3d(&self, x: f32, y: f32, z: f32) -> f32 {
+        FastNoiseLite::get_noise_3d(self, x, y, z)
+    }
+}
+
+// Valor pseudoaleatorio estable en [0, 1] para la celda entera que contiene
+// (x, y): mismo resultado para la misma celda y semilla, sin crear un Rng por
+// fragmento. Escalar las coordenadas antes de llamar define el tamaño de celda
+pub fn hash_noise(x: f32, y: f32, seed: u32) -> f32 {
+    let xi = x.floor() as i32 as u32;
+    let yi = y.floor() as i32 as u32;
+
+    // Mezcla entera al estilo de murmur3 (fmix32) sobre las coordenadas y la semilla
+    let mut h = seed ^ xi.wrapping_mul(0x27d4_eb2d) ^ yi.wrapping_mul(0x1656_67b1).rotate_left(13);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+
+    h as f32 / u32::MAX as f32
+}
+
+// Semilla estable para un nombre de planeta ("Kepler-22b" se ve igual en
+// cualquier ejecución y máquina): FNV-1a de 64 bits sobre los bytes UTF-8.
+// DefaultHasher no sirve porque su algoritmo puede cambiar entre versiones
+pub fn seed_from_name(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+// Ruido determinista para probar shaders sin la aleatoriedad del ruido real;
+// siempre está en las pruebas del crate, y con test-utils también afuera
+#[cfg(any(test, feature = "test-utils"))]
+pub struct ConstantNoise(pub f32);
+
+#[cfg(any(test, feature = "test-utils"))]
+impl NoiseSource for ConstantNoise {
+    fn get_noise_2d(&self, _x: f32, _y: f32) -> f32 {
+        self.0
+    }
+
+    fn get_noise_3d(&self, _x: f32, _y: f32, _z: f32) -> f32 {
+        self.0
+    }
+}
+
+// offset + scale * (x + y + z), limitado a [-1, 1] como el ruido real
+#[cfg(any(test, feature = "test-utils"))]
+pub struct LinearNoise {
+    pub scale: f32,
+    pub offset: f32,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl NoiseSource for LinearNoise {
+    fn get_noise_2d(&self, x: f32, y: f32) -> f32 {
+        (self.offset + self.scale * (x + y)).clamp(-1.0, 1.0)
+    }
+
+    fn get_noise_3d(&self, x: f32, y: f32, z: f32) -> f32 {
+        (self.offset + self.scale * (x + y + z)).clamp(-1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_noise_is_in_range_varies_between_cells_and_repeats() {
+        let values: Vec<f32> = (0..64).map(|i| hash_noise(i as f32, 3.0, 11)).collect();
+        assert!(values.iter().all(|value| (0.0..=1.0).contains(value)));
+        // Celdas vecinas no dan el mismo valor
+        assert!(values.windows(2).all(|pair| pair[0] != pair[1]));
+        // Misma celda (cualquier punto dentro de ella) y semilla: mismo valor
+        assert_eq!(hash_noise(5.2, 3.9, 11), values[5]);
+        assert_ne!(hash_noise(5.0, 3.0, 12), values[5]);
+    }
+
+    #[test]
+    fn the_same_name_gives_the_same_seed_and_different_names_differ() {
+        assert_eq!(seed_from_name("Kepler-22b"), seed_from_name("Kepler-22b"));
+        assert_ne!(seed_from_name("Kepler-22b"), seed_from_name("Kepler-22c"));
+        assert_ne!(seed_from_name("ab"), seed_from_name("ba"));
+
+        // Valores de referencia de FNV-1a: la semilla no depende de la versión de Rust
+        assert_eq!(seed_from_name(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(seed_from_name("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
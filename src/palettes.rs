@@ -0,0 +1,60 @@
+use crate::color::Color;
+use crate::ramp::ColorRamp;
+
+// Paletas con nombre listas para cualquier shader basado en ColorRamp
+// (hoy, el planeta celular a través de ShaderParams::cellular_ramp)
+
+// Óxidos rojizos y polvo: para el planeta celular como superficie marciana
+pub const MARS: ColorRamp = ColorRamp::new(&[
+    (0.0, Color::new(96, 38, 20)),
+    (0.15, Color::new(156, 62, 32)),
+    (0.3, Color::new(193, 98, 52)),
+    (0.5, Color::new(222, 150, 100)),
+]);
+
+// Océano, costa, vegetación y montaña: para el planeta celular como planeta habitable
+pub const EARTH: ColorRamp = ColorRamp::new(&[
+    (0.0, Color::new(20, 60, 140)),
+    (0.1, Color::new(40, 110, 190)),
+    (0.2, Color::new(214, 200, 140)),
+    (0.3, Color::new(60, 130, 50)),
+    (0.5, Color::new(110, 100, 80)),
+]);
+
+// Cremas y ocres de gigante gaseoso: para el planeta celular con celdas grandes
+pub const JUPITER: ColorRamp = ColorRamp::new(&[
+    (0.0, Color::new(120, 80, 50)),
+    (0.1, Color::new(200, 150, 100)),
+    (0.25, Color::new(235, 215, 180)),
+    (0.4, Color::new(180, 110, 70)),
+]);
+
+// Blancos y azules glaciares: para el planeta celular como luna helada
+pub const ICE: ColorRamp = ColorRamp::new(&[
+    (0.0, Color::new(150, 190, 220)),
+    (0.15, Color::new(195, 225, 245)),
+    (0.3, Color::new(235, 245, 255)),
+    (0.5, Color::new(255, 255, 255)),
+]);
+
+// Amarillos sulfurosos de nubes densas: para el planeta celular como Venus
+pub const VENUS: ColorRamp = ColorRamp::new(&[
+    (0.0, Color::new(170, 120, 50)),
+    (0.15, Color::new(210, 170, 90)),
+    (0.3, Color::new(235, 205, 140)),
+    (0.5, Color::new(250, 230, 180)),
+]);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_palette_has_increasing_stops_inside_the_unit_range() {
+        for ramp in [MARS, EARTH, JUPITER, ICE, VENUS] {
+            assert_eq!(ramp.stops[0].0, 0.0);
+            assert!(ramp.stops.iter().all(|&(position, _)| (0.0..=1.0).contains(&position)));
+            assert!(ramp.stops.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        }
+    }
+}
@@ -0,0 +1,594 @@
+use nalgebra_glm::{Vec2, Vec3, Mat3};
+use crate::color::Color;
+
+// Tabla de consulta (LUT) por canal para la gradación de color del frame final
+#[derive(Clone)]
+pub struct ColorLut {
+    pub r: [u8; 256],
+    pub g: [u8; 256],
+    pub b: [u8; 256],
+}
+
+impl ColorLut {
+    pub fn identity() -> Self {
+        let table = build_table(|value| value);
+        ColorLut { r: table, g: table, b: table }
+    }
+
+    pub fn warm() -> Self {
+        ColorLut {
+            r: build_table(|value| value * 1.1 + 0.02),
+            g: build_table(|value| value * 1.02),
+            b: build_table(|value| value * 0.85),
+        }
+    }
+
+    pub fn cool() -> Self {
+        ColorLut {
+            r: build_table(|value| value * 0.85),
+            g: build_table(|value| value * 1.0),
+            b: build_table(|value| value * 1.1 + 0.02),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        // Curva en S centrada en el gris medio
+        let table = build_table(|value| {
+            let centered = value * 2.0 - 1.0;
+            let curved = centered.signum() * centered.abs().powf(0.6);
+            curved * 0.5 + 0.5
+        });
+        ColorLut { r: table, g: table, b: table }
+    }
+
+    pub fn apply(&self, buffer: &mut [u32]) {
+        for pixel in buffer.iter_mut() {
+            let r = self.r[((*pixel >> 16) & 0xFF) as usize] as u32;
+            let g = self.g[((*pixel >> 8) & 0xFF) as usize] as u32;
+            let b = self.b[(*pixel & 0xFF) as usize] as u32;
+            *pixel = (r << 16) | (g << 8) | b;
+        }
+    }
+}
+
+// Construye una tabla a partir de una curva sobre valores normalizados [0, 1]
+fn build_table(curve: impl Fn(f32) -> f32) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (index, entry) in table.iter_mut().enumerate() {
+        let value = curve(index as f32 / 255.0).clamp(0.0, 1.0);
+        *entry = (value * 255.0).round() as u8;
+    }
+    table
+}
+
+// Pesos Rec. 709 de Color::luminance, como fila de una matriz
+const LUMINANCE_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+// Transformación lineal de color (saturación, giro de tono, mezcla de canales)
+// aplicada a cada píxel; la identidad deja la imagen igual
+pub fn apply_color_matrix(buffer: &mut [Color], m: Mat3) {
+    for color in buffer.iter_mut() {
+        *color = color.transform(&m);
+    }
+}
+
+// Mezcla cada canal con la luminancia: 0 = escala de grises, 1 = identidad, > 1 satura
+pub fn saturation_matrix(s: f32) -> Mat3 {
+    let [r, g, b] = LUMINANCE_WEIGHTS.map(|weight| weight * (1.0 - s));
+    Mat3::new(
+        r + s, g,     b,
+        r,     g + s, b,
+        r,     g,     b + s,
+    )
+}
+
+// Giro del tono en grados conservando la luminancia (la matriz de feColorMatrix
+// hueRotate de SVG)
+pub fn hue_rotation_matrix(deg: f32) -> Mat3 {
+    let (sin, cos) = deg.to_radians().sin_cos();
+    Mat3::new(
+        0.213 + cos * 0.787 - sin * 0.213, 0.715 - cos * 0.715 - sin * 0.715, 0.072 - cos * 0.072 + sin * 0.928,
+        0.213 - cos * 0.213 + sin * 0.143, 0.715 + cos * 0.285 + sin * 0.140, 0.072 - cos * 0.072 - sin * 0.283,
+        0.213 - cos * 0.213 - sin * 0.787, 0.715 - cos * 0.715 + sin * 0.715, 0.072 + cos * 0.928 + sin * 0.072,
+    )
+}
+
+// Sepia como matriz: el tono sepia es la luminancia escalada por canal, y
+// strength interpola entre la identidad y ese tono
+pub fn sepia_matrix(strength: f32) -> Mat3 {
+    let tint = Vec3::new(1.07, 0.86, 0.62);
+    let luminance = Vec3::from(LUMINANCE_WEIGHTS).transpose();
+    Mat3::identity() * (1.0 - strength) + tint * luminance * strength
+}
+
+// Virado sepia: mezcla cada píxel con su tono sepia según strength (0 = sin cambio)
+pub fn apply_sepia(buffer: &mut [u32], strength: f32) {
+    let strength = strength.clamp(0.0, 1.0);
+    if strength == 0.0 {
+        return;
+    }
+
+    let matrix = sepia_matrix(strength);
+    for pixel in buffer.iter_mut() {
+        *pixel = Color::from_hex(*pixel).transform(&matrix).to_hex();
+    }
+}
+
+// Suavizado bilateral: cada píxel se promedia con sus vecinos (radio 2σ)
+// pesando por distancia (spatial_sigma, en píxeles) y por parecido de color
+// (range_sigma, distancia RGB en [0, 1]). Dentro de una zona el ruido se
+// difumina, pero a través de un borde de alto contraste el peso es casi 0 y
+// el borde queda nítido. Costo: O(width * height * (4 * spatial_sigma)²)
+pub fn bilateral_filter(buffer: &mut [Color], width: usize, height: usize, spatial_sigma: f32, range_sigma: f32) {
+    assert_eq!(buffer.len(), width * height, "buffer does not match its size");
+    let channels = |color: &Color| {
+        let hex = color.to_hex();
+        Vec3::new(((hex >> 16) & 0xFF) as f32, ((hex >> 8) & 0xFF) as f32, (hex & 0xFF) as f32) / 255.0
+    };
+    let source: Vec<Vec3> = buffer.iter().map(channels).collect();
+    let radius = (spatial_sigma * 2.0).ceil().max(1.0) as i64;
+    let spatial = 2.0 * spatial_sigma.max(f32::EPSILON).powi(2);
+    let range = 2.0 * range_sigma.max(f32::EPSILON).powi(2);
+
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let center = source[y as usize * width + x as usize];
+            let mut sum = Vec3::zeros();
+            let mut total = 0.0;
+            for ny in (y - radius).max(0)..=(y + radius).min(height as i64 - 1) {
+                for nx in (x - radius).max(0)..=(x + radius).min(width as i64 - 1) {
+                    let neighbour = source[ny as usize * width + nx as usize];
+                    let distance = ((nx - x).pow(2) + (ny - y).pow(2)) as f32;
+                    let difference = (neighbour - center).magnitude_squared();
+                    let weight = (-distance / spatial - difference / range).exp();
+                    sum += neighbour * weight;
+                    total += weight;
+                }
+            }
+            let filtered = sum / total * 255.0;
+            buffer[y as usize * width + x as usize] = Color::new(
+                filtered.x.round().clamp(0.0, 255.0) as u8,
+                filtered.y.round().clamp(0.0, 255.0) as u8,
+                filtered.z.round().clamp(0.0, 255.0) as u8,
+            );
+        }
+    }
+}
+
+// Un paso de posproceso con sus parámetros
+#[derive(Clone)]
+pub enum PostEffect {
+    // Suavizado bilateral (spatial_sigma, range_sigma)
+    Smoothing(f32, f32),
+    ColorMatrix(Mat3),
+    ColorGrade(Box<ColorLut>),
+    Sepia(f32),
+}
+
+// Efectos de posproceso que se aplican al frame en el orden de passes. El
+// orden recomendado es el de la ventana: suavizado, matriz de color, gradación
+// y por último sepia, que es un look sobre la imagen ya graduada. La exposición
+// y el balance de blancos no están aquí: los aplica el render sobre el color
+// HDR. Una cadena vacía deja el frame igual
+#[derive(Clone, Default)]
+pub struct PostChain {
+    pub passes: Vec<PostEffect>,
+}
+
+impl PostChain {
+    pub fn new() -> Self {
+        PostChain { passes: Vec::new() }
+    }
+
+    pub fn push(&mut self, effect: PostEffect) -> &mut Self {
+        self.passes.push(effect);
+        self
+    }
+
+    pub fn apply(&self, buffer: &mut [u32], width: usize, height: usize) {
+        for pass in &self.passes {
+            match pass {
+                PostEffect::Smoothing(spatial_sigma, range_sigma) => {
+                    let mut colors: Vec<Color> = buffer.iter().map(|&pixel| Color::from_hex(pixel)).collect();
+                    bilateral_filter(&mut colors, width, height, *spatial_sigma, *range_sigma);
+                    for (pixel, color) in buffer.iter_mut().zip(colors) {
+                        *pixel = color.to_hex();
+                    }
+                }
+                PostEffect::ColorMatrix(matrix) => {
+                    for pixel in buffer.iter_mut() {
+                        *pixel = Color::from_hex(*pixel).transform(matrix).to_hex();
+                    }
+                }
+                PostEffect::ColorGrade(lut) => lut.apply(buffer),
+                PostEffect::Sepia(strength) => apply_sepia(buffer, *strength),
+            }
+        }
+    }
+}
+
+// Realimentación entre frames para estelas: guarda el último frame mostrado y
+// lo mezcla con el nuevo como new * (1 - amount) + previous * amount
+pub struct FrameFeedback {
+    previous: Vec<u32>,
+}
+
+impl FrameFeedback {
+    pub fn new() -> Self {
+        FrameFeedback { previous: Vec::new() }
+    }
+
+    pub fn apply(&mut self, buffer: &mut [u32], amount: f32) {
+        let amount = amount.clamp(0.0, 1.0);
+        if amount > 0.0 && self.previous.len() == buffer.len() {
+            for (pixel, previous) in buffer.iter_mut().zip(&self.previous) {
+                *pixel = Color::from_hex(*pixel).lerp(&Color::from_hex(*previous), amount).to_hex();
+            }
+        }
+        self.previous.clear();
+        self.previous.extend_from_slice(buffer);
+    }
+}
+
+impl Default for FrameFeedback {
+    fn default() -> Self {
+        FrameFeedback::new()
+    }
+}
+
+// Supermuestreo progresivo: cada frame se dibuja con el centro de los píxeles
+// desplazado (jitter) y se suma a un promedio acumulado, que converge a la
+// imagen antialiasada mientras la vista no cambie. reset empieza de nuevo
+pub struct FrameAccumulator {
+    sum: Vec<Vec3>,
+    samples: u32,
+}
+
+impl FrameAccumulator {
+    pub fn new() -> Self {
+        FrameAccumulator { sum: Vec::new(), samples: 0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.samples = 0;
+    }
+
+    // Frames acumulados desde el último reset
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    // Desplazamiento en píxeles, en [-0.5, 0.5), para el próximo frame: la
+    // secuencia de Halton en bases 2 y 3 reparte las muestras sin agruparlas
+    pub fn jitter(&self) -> Vec2 {
+        let index = self.samples + 1;
+        Vec2::new(halton(index, 2) - 0.5, halton(index, 3) - 0.5)
+    }
+
+    // Suma el frame nuevo y deja en buffer el promedio de todos los acumulados
+    pub fn accumulate(&mut self, buffer: &mut [u32]) {
+        if self.samples == 0 || self.sum.len() != buffer.len() {
+            self.sum.clear();
+            self.sum.resize(buffer.len(), Vec3::zeros());
+            self.samples = 0;
+        }
+        self.samples += 1;
+
+        let count = self.samples as f32;
+        for (pixel, sum) in buffer.iter_mut().zip(self.sum.iter_mut()) {
+            *sum += Vec3::new(((*pixel >> 16) & 0xFF) as f32, ((*pixel >> 8) & 0xFF) as f32, (*pixel & 0xFF) as f32);
+            let average = *sum / count;
+            *pixel = ((average.x.round() as u32) << 16) | ((average.y.round() as u32) << 8) | average.z.round() as u32;
+        }
+    }
+}
+
+impl Default for FrameAccumulator {
+    fn default() -> Self {
+        FrameAccumulator::new()
+    }
+}
+
+// Elemento index (desde 1) de la secuencia de Halton en la base dada, en [0, 1)
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+// Histograma de luminancia (Rec. 709, en [0, 1]) con `bins` intervalos iguales;
+// la luminancia 1 cae en el último
+pub fn luminance_histogram(buffer: &[Color], bins: usize) -> Vec<u32> {
+    let bins = bins.max(1);
+    let mut histogram = vec![0u32; bins];
+    for color in buffer {
+        let bin = ((color.luminance() * bins as f32) as usize).min(bins - 1);
+        histogram[bin] += 1;
+    }
+    histogram
+}
+
+pub struct LuminanceStats {
+    pub histogram: Vec<u32>,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+// Histograma más mínimo, máximo y media; todo 0 para un buffer vacío
+pub fn luminance_stats(buffer: &[Color], bins: usize) -> LuminanceStats {
+    let (mut min, mut max, mut sum) = (f32::INFINITY, f32::NEG_INFINITY, 0.0);
+    for color in buffer {
+        let luminance = color.luminance();
+        min = min.min(luminance);
+        max = max.max(luminance);
+        sum += luminance;
+    }
+
+    if buffer.is_empty() {
+        (min, max) = (0.0, 0.0);
+    }
+
+    LuminanceStats {
+        histogram: luminance_histogram(buffer, bins),
+        min,
+        max,
+        mean: if buffer.is_empty() { 0.0 } else { sum / buffer.len() as f32 },
+    }
+}
+
+// Luminancia media geométrica (exp de la media de ln L): un par de píxeles
+// muy brillantes no la disparan como a la media aritmética. delta evita ln 0
+// en los píxeles negros; 0 para un buffer vacío
+pub fn average_luminance(buffer: &[Color]) -> f32 {
+    if buffer.is_empty() {
+        return 0.0;
+    }
+    let delta = 1e-4;
+    let log_sum: f32 = buffer.iter().map(|color| (delta + color.luminance()).ln()).sum();
+    (log_sum / buffer.len() as f32).exp()
+}
+
+// Exposición automática: busca la ganancia que lleva la luminancia media al
+// valor key (gris medio) y se acerca a ella un poco en cada frame para que el
+// cambio no sea brusco cuando la luz orbita
+pub struct AutoExposure {
+    pub exposure: f32,
+    pub key: f32,
+    // Fracción del camino hacia el objetivo que se recorre por frame (1 = inmediato)
+    pub speed: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl AutoExposure {
+    pub fn new() -> Self {
+        AutoExposure {
+            exposure: 1.0,
+            key: 0.18,
+            speed: 0.1,
+            min: 0.25,
+            max: 4.0,
+        }
+    }
+
+    pub fn update(&mut self, average_luminance: f32) -> f32 {
+        let target = (self.key / average_luminance.max(f32::EPSILON)).clamp(self.min, self.max);
+        self.exposure += (target - self.exposure) * self.speed.clamp(0.0, 1.0);
+        self.exposure
+    }
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        AutoExposure::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_inverting_lut_produces_the_negative_image() {
+        let table = build_table(|value| 1.0 - value);
+        let invert = ColorLut { r: table, g: table, b: table };
+        let image = vec![0x000000, 0xFFFFFF, 0x123456, 0xFF8000];
+
+        let mut inverted = image.clone();
+        invert.apply(&mut inverted);
+        assert_eq!(inverted, image.iter().map(|pixel| !pixel & 0xFFFFFF).collect::<Vec<u32>>());
+
+        let mut unchanged = image.clone();
+        ColorLut::identity().apply(&mut unchanged);
+        assert_eq!(unchanged, image);
+    }
+
+    #[test]
+    fn full_sepia_tints_every_pixel_and_zero_leaves_them_alone() {
+        let image = vec![0x808080, 0x2040C0, 0x00FF00, 0xC02020];
+
+        let mut untouched = image.clone();
+        apply_sepia(&mut untouched, 0.0);
+        assert_eq!(untouched, image);
+
+        // Con strength 1 cualquier color queda en el tono sepia: r > g > b
+        let mut toned = image.clone();
+        apply_sepia(&mut toned, 1.0);
+        for pixel in toned {
+            let (r, g, b) = (pixel >> 16 & 0xFF, pixel >> 8 & 0xFF, pixel & 0xFF);
+            assert!(r > g && g > b, "{:06X}", pixel);
+        }
+    }
+
+    // Pasa por la estela una fila de 4 píxeles negros con un punto blanco que
+    // avanza un píxel por frame
+    fn moving_dot_frames(amount: f32) -> Vec<u32> {
+        let mut feedback = FrameFeedback::new();
+        let mut frame = Vec::new();
+        for x in 0..4 {
+            frame = vec![0x000000; 4];
+            frame[x] = 0xFFFFFF;
+            feedback.apply(&mut frame, amount);
+        }
+        frame
+    }
+
+    #[test]
+    fn feedback_leaves_a_decaying_trail_behind_a_moving_dot() {
+        let trail: Vec<u32> = moving_dot_frames(0.5).iter().map(|pixel| pixel & 0xFF).collect();
+        // Cada frame conserva la mitad del anterior; el primero no tiene con qué mezclarse
+        assert_eq!(trail, vec![32, 32, 64, 128]);
+        assert_eq!(moving_dot_frames(0.0), vec![0x000000, 0x000000, 0x000000, 0xFFFFFF]);
+    }
+
+    #[test]
+    fn a_gray_ramp_fills_the_histogram_evenly() {
+        let ramp: Vec<Color> = (0..=255).map(|value| Color::new(value, value, value)).collect();
+        assert_eq!(luminance_histogram(&ramp, 4), vec![64, 64, 64, 64]);
+
+        let stats = luminance_stats(&ramp, 4);
+        assert_eq!(stats.histogram, vec![64, 64, 64, 64]);
+        assert!(stats.min == 0.0 && (stats.max - 1.0).abs() < 1e-6 && (stats.mean - 0.5).abs() < 1e-3);
+
+        // Un buffer de un solo color va entero a su intervalo
+        assert_eq!(luminance_histogram(&[Color::new(255, 255, 255); 10], 4), vec![0, 0, 0, 10]);
+    }
+
+    // (r, g, b) de un color
+    fn channels(color: Color) -> [i32; 3] {
+        let hex = color.to_hex();
+        [(hex >> 16 & 0xFF) as i32, (hex >> 8 & 0xFF) as i32, (hex & 0xFF) as i32]
+    }
+
+    #[test]
+    fn zero_saturation_turns_colors_into_their_luminance_gray() {
+        let colors = [Color::new(255, 0, 0), Color::new(0, 255, 0), Color::new(30, 120, 220), Color::new(200, 180, 10)];
+        let mut gray = colors.to_vec();
+        apply_color_matrix(&mut gray, saturation_matrix(0.0));
+        for (original, gray) in colors.iter().zip(&gray) {
+            let expected = (original.luminance() * 255.0).round() as i32;
+            for channel in channels(*gray) {
+                assert!((channel - expected).abs() <= 1, "{:?} -> {:?}", original, gray);
+            }
+        }
+
+        // Saturación 1 y giros de tono de 0° o 360° dejan la imagen igual
+        for matrix in [saturation_matrix(1.0), hue_rotation_matrix(0.0), hue_rotation_matrix(360.0)] {
+            let mut same = colors.to_vec();
+            apply_color_matrix(&mut same, matrix);
+            for (original, same) in colors.iter().zip(&same) {
+                let difference = channels(*original).iter().zip(channels(*same)).map(|(a, b)| (a - b).abs()).max();
+                assert!(difference <= Some(1), "{:?} -> {:?}", original, same);
+            }
+        }
+    }
+
+    #[test]
+    fn a_bright_frame_settles_on_a_lower_exposure_than_a_dark_one() {
+        let bright = vec![Color::new(150, 150, 150); 16];
+        let dark = vec![Color::new(20, 20, 20); 16];
+        let settle = |frame: &[Color]| {
+            let mut auto = AutoExposure::default();
+            for _ in 0..100 {
+                auto.update(average_luminance(frame));
+            }
+            auto.exposure
+        };
+
+        let (bright_exposure, dark_exposure) = (settle(&bright), settle(&dark));
+        assert!(bright_exposure < 1.0 && dark_exposure > 1.0, "{bright_exposure} {dark_exposure}");
+        // Ambas quedan en la exposición que lleva su luminancia a key
+        assert!((bright_exposure * average_luminance(&bright) - 0.18).abs() < 0.01);
+        assert!((dark_exposure * average_luminance(&dark) - 0.18).abs() < 0.01);
+    }
+
+    #[test]
+    fn the_bilateral_filter_smooths_noise_but_keeps_a_sharp_edge() {
+        let (width, height) = (16, 8);
+        // Mitad izquierda oscura y derecha clara, con ruido fijo de ±10
+        let image: Vec<Color> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let value = if x < width / 2 { 60 } else { 200 } + (x * 7 + y * 13) % 21 - 10;
+                Color::new(value as u8, value as u8, value as u8)
+            })
+            .collect();
+        let mut filtered = image.clone();
+        bilateral_filter(&mut filtered, width, height, 2.0, 0.1);
+
+        let red = |buffer: &[Color], x: usize, y: usize| channels(buffer[y * width + x])[0] as f32;
+        let spread = |buffer: &[Color]| {
+            let values: Vec<f32> = (0..height).flat_map(|y| (1..width / 2 - 1).map(move |x| (x, y))).map(|(x, y)| red(buffer, x, y)).collect();
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            values.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / values.len() as f32
+        };
+        assert!(spread(&filtered) < spread(&image) * 0.5, "{} {}", spread(&filtered), spread(&image));
+
+        // A ambos lados del borde cada píxel sigue cerca de su zona
+        for y in 0..height {
+            assert!((red(&filtered, width / 2 - 1, y) - 60.0).abs() < 15.0);
+            assert!((red(&filtered, width / 2, y) - 200.0).abs() < 15.0);
+        }
+    }
+
+    // Fila de 16 píxeles con un borde vertical en x = edge: blanco a la
+    // izquierda, negro a la derecha, muestreando el centro de cada píxel corrido por jitter
+    fn hard_edge_row(edge: f32, jitter: Vec2) -> Vec<u32> {
+        (0..16).map(|x| if x as f32 + 0.5 + jitter.x < edge { 0xFFFFFF } else { 0 }).collect()
+    }
+
+    #[test]
+    fn accumulating_jittered_frames_smooths_an_edge_pixel() {
+        let edge = 8.3;
+        let single = hard_edge_row(edge, Vec2::zeros());
+        // Un solo frame: el píxel del borde es todo o nada
+        assert!(single[8] == 0 || single[8] == 0xFFFFFF);
+
+        let mut accumulator = FrameAccumulator::new();
+        let mut frame = Vec::new();
+        for _ in 0..32 {
+            frame = hard_edge_row(edge, accumulator.jitter());
+            accumulator.accumulate(&mut frame);
+        }
+        assert_eq!(accumulator.samples(), 32);
+
+        // Acumulado, el píxel 8 queda gris según cuánto lo cubre el borde (~30 %)
+        let coverage = (frame[8] & 0xFF) as f32 / 255.0;
+        assert!((coverage - 0.3).abs() < 0.1, "{coverage}");
+        assert_eq!(frame[0], 0xFFFFFF);
+        assert_eq!(frame[15], 0);
+
+        accumulator.reset();
+        assert_eq!(accumulator.samples(), 0);
+    }
+
+    #[test]
+    fn reordering_passes_changes_the_output_and_an_empty_chain_does_nothing() {
+        let image = vec![0xC8320A, 0x3366CC, 0x000000, 0xFFFFFF];
+
+        let mut untouched = image.clone();
+        PostChain::new().apply(&mut untouched, 2, 2);
+        assert_eq!(untouched, image);
+
+        // El contraste se aplica por canal antes o después de pasar a grises
+        let contrast = PostEffect::ColorGrade(Box::new(ColorLut::high_contrast()));
+        let desaturate = PostEffect::ColorMatrix(saturation_matrix(0.0));
+        let mut contrast_first = image.clone();
+        PostChain::new().push(contrast.clone()).push(desaturate.clone()).apply(&mut contrast_first, 2, 2);
+        let mut desaturate_first = image.clone();
+        PostChain::new().push(desaturate).push(contrast).apply(&mut desaturate_first, 2, 2);
+
+        assert_ne!(contrast_first[0], desaturate_first[0]);
+        // Ambos órdenes terminan en gris
+        for pixel in contrast_first.iter().chain(&desaturate_first) {
+            let [r, g, b] = channels(Color::from_hex(*pixel));
+            assert!((r - g).abs() <= 1 && (g - b).abs() <= 1, "{:06X}", pixel);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::color::Color;
+
+// Rampa de colores: lista de paradas (posición, color) ordenadas por posición
+// creciente dentro de [0, 1]
+#[derive(Clone, Copy, PartialEq)]
+pub struct ColorRamp {
+    pub stops: &'static [(f32, Color)],
+}
+
+impl ColorRamp {
+    pub const fn new(stops: &'static [(f32, Color)]) -> Self {
+        ColorRamp { stops }
+    }
+
+    // Color de la última parada cuya posición es <= value, sin interpolar.
+    // Por debajo de la primera parada se usa el primer color
+    pub fn step(&self, value: f32) -> Color {
+        let mut color = self.stops[0].1;
+        for &(position, stop_color) in self.stops {
+            if value >= position {
+                color = stop_color;
+            } else {
+                break;
+            }
+        }
+        color
+    }
+}
@@ -0,0 +1,91 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4, cross, dot};
+use crate::vertex::Vertex;
+use crate::Uniforms;
+
+// Intersección rayo-triángulo de Möller–Trumbore: distancia t (en unidades de
+// dir) hasta el punto origin + dir * t, o None si el rayo no lo toca, es
+// paralelo al plano o el triángulo queda detrás. Acepta ambas caras
+pub fn ray_triangle(origin: Vec3, dir: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = cross(&dir, &edge2);
+    let det = dot(&edge1, &p);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+
+    let inverse_det = 1.0 / det;
+    let to_origin = origin - a;
+    let u = dot(&to_origin, &p) * inverse_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = cross(&to_origin, &edge1);
+    let v = dot(&dir, &q) * inverse_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = dot(&edge2, &q) * inverse_det;
+    if t > 0.0 { Some(t) } else { None }
+}
+
+// Rayo en espacio de mundo que pasa por el píxel (x, y): sale del plano
+// cercano y apunta hacia el lejano, con dirección normalizada
+pub fn screen_ray(x: f32, y: f32, uniforms: &Uniforms) -> (Vec3, Vec3) {
+    let inverse = (uniforms.viewport_matrix * uniforms.projection_matrix * uniforms.view_matrix)
+        .try_inverse()
+        .unwrap_or(Mat4::identity());
+    let unproject = |z: f32| {
+        let point = inverse * Vec4::new(x, y, z, 1.0);
+        Vec3::new(point.x, point.y, point.z) / point.w
+    };
+
+    let near = unproject(-1.0);
+    let far = unproject(1.0);
+    (near, (far - near).normalize())
+}
+
+// Impacto más cercano del rayo con una malla (lista de triángulos) colocada con model_matrix
+pub fn ray_mesh(origin: Vec3, dir: Vec3, vertex_array: &[Vertex], model_matrix: &Mat4) -> Option<f32> {
+    let world = |vertex: &Vertex| {
+        let point = model_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+        Vec3::new(point.x, point.y, point.z)
+    };
+    vertex_array
+        .chunks_exact(3)
+        .filter_map(|tri| ray_triangle(origin, dir, world(&tri[0]), world(&tri[1]), world(&tri[2])))
+        .min_by(f32::total_cmp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::benchmark_scene;
+
+    #[test]
+    fn a_ray_through_the_centroid_hits_and_a_parallel_one_misses() {
+        let (a, b, c) = (Vec3::new(0.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 3.0, 0.0));
+        let centroid = (a + b + c) / 3.0;
+        let origin = centroid + Vec3::new(0.0, 0.0, 2.0);
+
+        let t = ray_triangle(origin, Vec3::new(0.0, 0.0, -1.0), a, b, c).unwrap();
+        assert!((t - 2.0).abs() < 1e-6);
+        // Por el otro lado también se ve (ambas caras), pero no hacia atrás
+        assert!(ray_triangle(centroid - Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, 1.0), a, b, c).is_some());
+        assert_eq!(ray_triangle(origin, Vec3::new(0.0, 0.0, 1.0), a, b, c), None);
+        // Paralelo al plano del triángulo
+        assert_eq!(ray_triangle(origin, Vec3::new(1.0, 0.0, 0.0), a, b, c), None);
+        assert_eq!(ray_triangle(centroid, Vec3::new(1.0, 1.0, 0.0).normalize(), a, b, c), None);
+    }
+
+    #[test]
+    fn a_ray_from_the_camera_hits_the_near_side_of_the_sphere() {
+        let (uniforms, sphere) = benchmark_scene(64, 64);
+        let t = ray_mesh(uniforms.camera_pos, Vec3::new(0.0, 0.0, -1.0), &sphere, &uniforms.model_matrix).unwrap();
+        // Cámara a 3 del centro y esfera de radio 1 (un poco menos entre vértices)
+        assert!(t > 1.95 && t < 2.1, "{t}");
+        assert_eq!(ray_mesh(uniforms.camera_pos, Vec3::new(0.0, 1.0, 0.0), &sphere, &uniforms.model_matrix), None);
+    }
+}
@@ -0,0 +1,1280 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4, Mat4, dot, look_at, perspective, rotate_vec3};
+use std::sync::Arc;
+use std::f32::consts::PI;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rayon::prelude::*;
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+
+use crate::framebuffer::Framebuffer;
+use crate::vertex::Vertex;
+use crate::fragment::Fragment;
+use crate::triangle::triangle;
+use crate::line::line;
+use crate::raster::{alpha_to_coverage, depth_slope, RasterPrecision};
+use crate::shaders::{vertex_shader, shading_light_dir, facing_normal, fragment_shader, fragment_albedo, apply_lighting, cutout_alpha, ShaderParams, ShaderKind, NormalSpace};
+use crate::color::Color;
+use crate::noise::NoiseSource;
+use crate::mesh::{tessellate, icosphere, assemble, displace, weld_vertices, with_mirror, PrimitiveTopology};
+use crate::shadow::ShadowMap;
+use crate::lod::MeshLod;
+use crate::texture::{triangle_lod, Texture, WrapMode};
+#[cfg(feature = "debug-checks")]
+use crate::checks;
+
+#[derive(Clone)]
+pub struct Uniforms {
+    pub model_matrix: Mat4,
+    pub view_matrix: Mat4,
+    pub projection_matrix: Mat4,
+    pub viewport_matrix: Mat4,
+    // Segundos de animación (ver Clock)
+    pub time: f32,
+    pub noise: Arc<dyn NoiseSource>,
+    pub light_dir: Vec3,
+    pub camera_pos: Vec3,
+    pub params: ShaderParams,
+    pub seed: u64,
+    // Ganancia por canal (R, G, B) sobre el color HDR antes de cuantizar; (1, 1, 1) es neutro
+    pub white_balance: Vec3,
+    // Ganancia global (exposición) sobre el color HDR antes de cuantizar; 1 es neutra
+    pub exposure: f32,
+    // Profundidad vista desde la luz; solo existe con params.shadows
+    pub shadow_map: Option<Arc<ShadowMap>>,
+    // Profundidad vista desde el foco; solo si params.spot_light proyecta sombras
+    pub spot_shadow_map: Option<Arc<ShadowMap>>,
+    // Textura que multiplica el albedo, muestreada en tex_coords con mipmaps
+    pub texture: Option<Arc<Texture>>,
+}
+
+impl Uniforms {
+    // Rota light_dir alrededor de axis según el tiempo transcurrido; light_dir
+    // actúa como dirección base, así que se llama una vez por frame
+    pub fn orbit_light(&mut self, axis: Vec3, radians_per_sec: f32) {
+        self.light_dir = rotate_vec3(&self.light_dir, radians_per_sec * self.time, &axis.normalize());
+    }
+
+    // Copia con otro instante de animación (para el motion blur)
+    pub fn at_time(&self, time: f32) -> Uniforms {
+        Uniforms { time, ..self.clone() }
+    }
+}
+
+pub fn create_noise() -> FastNoiseLite {
+    create_cloud_noise()
+}
+
+pub fn create_cloud_noise() -> FastNoiseLite {
+    let mut noise = FastNoiseLite::with_seed(1337);
+    noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+    noise
+}
+
+// Malla del planeta teselada; con displaced, soldada y con relieve de ruido
+pub fn planet_mesh(base: &[Vertex], level: u32, displaced: bool, noise: &dyn NoiseSource) -> Vec<Vertex> {
+    let mut vertices = tessellate(base, level);
+    if displaced {
+        weld_vertices(&mut vertices, 1e-4);
+        displace(&mut vertices, noise, 0.05, 3.0);
+    }
+    paint_poles(&mut vertices);
+    vertices
+}
+
+// Tamaños en pantalla (píxeles) a partir de los cuales el planeta usa 1 y 2
+// niveles de teselado cuando el nivel es automático
+pub const PLANET_LOD_THRESHOLDS: [f32; 2] = [300.0, 500.0];
+
+// Los tres niveles de planet_mesh (0, 1 y 2 teselados) para elegir por tamaño en pantalla
+pub fn planet_lod(base: &[Vertex], displaced: bool, noise: &dyn NoiseSource) -> MeshLod {
+    let levels = (0..=PLANET_LOD_THRESHOLDS.len() as u32).map(|level| planet_mesh(base, level, displaced, noise)).collect();
+    MeshLod::from_levels(levels, &PLANET_LOD_THRESHOLDS)
+}
+
+// Coordenadas de textura del anillo: u da vueltas alrededor del eje Y (repeats
+// veces por vuelta) y v va de 0 en el borde interior a 1 en el exterior
+pub fn ring_tex_coords(vertices: &mut [Vertex], repeats: f32) {
+    let radius = |vertex: &Vertex| Vec2::new(vertex.position.x, vertex.position.z).magnitude();
+    let inner = vertices.iter().map(radius).fold(f32::INFINITY, f32::min);
+    let outer = vertices.iter().map(radius).fold(0.0, f32::max);
+    let width = (outer - inner).max(f32::EPSILON);
+    for vertex in vertices.iter_mut() {
+        let angle = vertex.position.z.atan2(vertex.position.x) / (2.0 * PI) + 0.5;
+        vertex.tex_coords = Vec2::new(angle * repeats, (radius(vertex) - inner) / width);
+    }
+}
+
+// Bandas concéntricas para el anillo: brillo y tono cambian a lo ancho (v) con
+// ruido, y un poco a lo largo (u). Se repite alrededor del anillo y se fija
+// en los bordes, así las bandas no se cuelan de un borde al otro
+pub fn ring_texture(noise: &dyn NoiseSource) -> Texture {
+    let (width, height) = (64, 256);
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let band = noise.get_noise_2d(0.0, y as f32 * 2.0) * 0.5 + 0.5;
+        for x in 0..width {
+            let grain = noise.get_noise_2d(x as f32 * 8.0, y as f32 * 8.0) * 0.03;
+            let color = Color::new(120, 100, 80).lerp(&Color::new(235, 220, 190), (band + grain).clamp(0.0, 1.0));
+            data.push(color);
+        }
+    }
+    let mut texture = Texture::new(width, height, data);
+    texture.set_wrap(WrapMode::Repeat, WrapMode::Clamp);
+    texture.build_mips();
+    texture
+}
+
+// Pintura por vértice de ejemplo (se ve con params.tint_by_vertex_color):
+// blanco en el ecuador, oscureciéndose hacia los polos
+pub fn paint_poles(vertices: &mut [Vertex]) {
+    for vertex in vertices.iter_mut() {
+        let pole = vertex.position.normalize().y.abs();
+        vertex.color = Color::new(255, 255, 255).lerp(&Color::new(60, 60, 80), (pole - 0.6) / 0.4);
+    }
+}
+
+pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
+    let (sin_x, cos_x) = rotation.x.sin_cos();
+    let (sin_y, cos_y) = rotation.y.sin_cos();
+    let (sin_z, cos_z) = rotation.z.sin_cos();
+
+    let rotation_matrix_x = Mat4::new(
+        1.0,  0.0,    0.0,   0.0,
+        0.0,  cos_x, -sin_x, 0.0,
+        0.0,  sin_x,  cos_x, 0.0,
+        0.0,  0.0,    0.0,   1.0,
+    );
+
+    let rotation_matrix_y = Mat4::new(
+        cos_y,  0.0,  sin_y, 0.0,
+        0.0,    1.0,  0.0,   0.0,
+        -sin_y, 0.0,  cos_y, 0.0,
+        0.0,    0.0,  0.0,   1.0,
+    );
+
+    let rotation_matrix_z = Mat4::new(
+        cos_z, -sin_z, 0.0, 0.0,
+        sin_z,  cos_z, 0.0, 0.0,
+        0.0,    0.0,  1.0, 0.0,
+        0.0,    0.0,  0.0, 1.0,
+    );
+
+    let rotation_matrix = rotation_matrix_z * rotation_matrix_y * rotation_matrix_x;
+
+    let transform_matrix = Mat4::new(
+        scale, 0.0,   0.0,   translation.x,
+        0.0,   scale, 0.0,   translation.y,
+        0.0,   0.0,   scale, translation.z,
+        0.0,   0.0,   0.0,   1.0,
+    );
+
+    transform_matrix * rotation_matrix
+}
+
+
+// Matriz de modelo que orienta un quad (que mira hacia +z en su espacio local)
+// hacia la cámara, para sprites como estrellas lejanas o etiquetas
+pub fn billboard_matrix(position: Vec3, camera_pos: Vec3, up: Vec3) -> Mat4 {
+    let forward = (camera_pos - position).normalize();
+    let mut right = up.cross(&forward);
+    if right.magnitude() < 1e-6 {
+        // up es paralelo a la dirección de vista: se elige otro eje cualquiera
+        right = Vec3::new(1.0, 0.0, 0.0).cross(&forward);
+    }
+    let right = right.normalize();
+    let up = forward.cross(&right);
+
+    Mat4::new(
+        right.x, up.x, forward.x, position.x,
+        right.y, up.y, forward.y, position.y,
+        right.z, up.z, forward.z, position.z,
+        0.0,     0.0,  0.0,       1.0,
+    )
+}
+
+// Estrellas repartidas sobre una esfera de radio `radius` con brillo aleatorio
+pub fn create_starfield(count: usize, radius: f32) -> Vec<Vertex> {
+    let mut rng = StdRng::seed_from_u64(7);
+    (0..count).map(|_| {
+        let direction = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        );
+        let direction = if direction.magnitude() > 0.0 { direction.normalize() } else { Vec3::new(0.0, 0.0, -1.0) };
+        let brightness = rng.gen_range(120..=255);
+        Vertex::new_with_color(direction * radius, Color::new(brightness, brightness, brightness.saturating_add(20)))
+    }).collect()
+}
+
+// Transformaciones de un cinturón de asteroides alrededor del origen
+pub fn create_asteroid_belt(count: usize, radius: f32) -> Vec<Mat4> {
+    let mut rng = StdRng::seed_from_u64(42);
+    (0..count).map(|i| {
+        let angle = i as f32 / count as f32 * 2.0 * PI + rng.gen_range(-0.05..0.05);
+        let distance = radius + rng.gen_range(-0.15..0.15);
+        let translation = Vec3::new(
+            distance * angle.cos(),
+            rng.gen_range(-0.05..0.05),
+            distance * angle.sin(),
+        );
+        let rotation = Vec3::new(
+            rng.gen_range(0.0..2.0 * PI),
+            rng.gen_range(0.0..2.0 * PI),
+            rng.gen_range(0.0..2.0 * PI),
+        );
+        create_model_matrix(translation, rng.gen_range(0.03..0.07), rotation)
+    }).collect()
+}
+
+pub fn create_view_matrix(eye: Vec3, center: Vec3, up: Vec3) -> Mat4 {
+    look_at(&eye, &center, &up)
+}
+
+pub fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
+    let fov = 45.0 * PI / 180.0;
+    let aspect_ratio = window_width / window_height;
+    let near = 0.1;
+    let far = 1000.0;
+
+    perspective(fov, aspect_ratio, near, far)
+}
+
+// Con píxeles no cuadrados (pixel_aspect = ancho / alto del píxel) la escala
+// horizontal se divide por pixel_aspect para que la imagen no salga estirada
+// jitter desplaza la imagen una fracción de píxel (supermuestreo progresivo)
+pub fn create_viewport_matrix(width: f32, height: f32, pixel_aspect: f32, jitter: Vec2) -> Mat4 {
+    Mat4::new(
+        width / 2.0 / pixel_aspect, 0.0, 0.0, width / 2.0 + jitter.x,
+        0.0, -height / 2.0, 0.0, height / 2.0 + jitter.y,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0
+    )
+}
+
+// Entradas de rasterize de las que dependen los fragmentos guardados
+#[derive(Clone, Copy, PartialEq)]
+struct CacheKey {
+    model_matrix: Mat4,
+    view_matrix: Mat4,
+    projection_matrix: Mat4,
+    viewport_matrix: Mat4,
+    light_dir: Vec3,
+    camera_pos: Vec3,
+    normal_space: NormalSpace,
+    samples: usize,
+    precision: RasterPrecision,
+    options: DrawOptions,
+    vertex_count: usize,
+    // Huella del contenido de la malla: una malla editada con el mismo número
+    // de vértices no reutiliza los fragmentos viejos
+    vertex_hash: u64,
+    // Tamaño de la textura, del que depende texture_lod
+    texture_size: Option<(usize, usize)>,
+}
+
+impl CacheKey {
+    fn new(uniforms: &Uniforms, vertex_array: &[Vertex], samples: usize, precision: RasterPrecision, options: &DrawOptions) -> Self {
+        CacheKey {
+            model_matrix: uniforms.model_matrix,
+            view_matrix: uniforms.view_matrix,
+            projection_matrix: uniforms.projection_matrix,
+            viewport_matrix: uniforms.viewport_matrix,
+            light_dir: uniforms.light_dir,
+            camera_pos: uniforms.camera_pos,
+            normal_space: uniforms.params.normal_space,
+            samples,
+            precision,
+            options: *options,
+            vertex_count: vertex_array.len(),
+            vertex_hash: mesh_hash(vertex_array),
+            texture_size: uniforms.texture.as_ref().map(|texture| (texture.width(), texture.height())),
+        }
+    }
+}
+
+// FNV-1a de 64 bits sobre los atributos de entrada de cada vértice (los
+// transformados los vuelve a calcular rasterize)
+fn mesh_hash(vertex_array: &[Vertex]) -> u64 {
+    vertex_array.iter()
+        .flat_map(|vertex| {
+            let [x, y, z] = [vertex.position.x, vertex.position.y, vertex.position.z].map(f32::to_bits);
+            let [nx, ny, nz] = [vertex.normal.x, vertex.normal.y, vertex.normal.z].map(f32::to_bits);
+            let [u, v] = [vertex.tex_coords.x, vertex.tex_coords.y].map(f32::to_bits);
+            [x, y, z, nx, ny, nz, u, v, vertex.color.to_hex()]
+        })
+        .fold(0xcbf2_9ce4_8422_2325, |hash, word| {
+            (hash ^ word as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+// Fragmentos rasterizados del frame anterior. Mientras la malla, las matrices,
+// la luz y las opciones de dibujo no cambien, solo hace falta volver a correr
+// el fragment shader. invalidate los descarta a la fuerza
+pub struct RenderCache {
+    fragments: Option<Vec<Fragment>>,
+    key: Option<CacheKey>,
+}
+
+impl RenderCache {
+    pub fn new() -> Self {
+        RenderCache { fragments: None, key: None }
+    }
+
+    pub fn invalidate(&mut self) {
+        self.fragments = None;
+        self.key = None;
+    }
+
+    // Los fragmentos guardados si las entradas son las mismas que la última
+    // vez; si no, los que devuelve rasterize
+    fn fragments(&mut self, key: CacheKey, rasterize: impl FnOnce() -> Vec<Fragment>) -> &Vec<Fragment> {
+        if self.key != Some(key) {
+            self.fragments = None;
+            self.key = Some(key);
+        }
+        self.fragments.get_or_insert_with(rasterize)
+    }
+}
+
+impl Default for RenderCache {
+    fn default() -> Self {
+        RenderCache::new()
+    }
+}
+
+pub fn rasterize(uniforms: &Uniforms, vertex_array: &[Vertex], samples: usize, precision: RasterPrecision, options: &DrawOptions) -> Vec<Fragment> {
+    let mirrored;
+    let (vertex_array, topology) = match options.mirror {
+        Some(plane_normal) => {
+            mirrored = with_mirror(vertex_array, options.topology, plane_normal);
+            (&mirrored[..], PrimitiveTopology::Triangles)
+        }
+        None => (vertex_array, options.topology),
+    };
+
+    let transformed_vertices: Vec<Vertex> = vertex_array
+        .par_iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
+    #[cfg(feature = "debug-checks")]
+    checks::check_vertices(&transformed_vertices);
+
+    let triangles: Vec<[Vertex; 3]> = assemble(transformed_vertices.len(), topology)
+        .into_iter()
+        .map(|[a, b, c]| [
+            transformed_vertices[a].clone(),
+            transformed_vertices[b].clone(),
+            transformed_vertices[c].clone(),
+        ])
+        .collect();
+
+    // collect conserva el orden de los triángulos, así que la salida no
+    // depende de cuántos hilos haya
+    let light_dir = shading_light_dir(uniforms);
+    let per_triangle: Vec<Vec<Fragment>> = triangles
+        .par_iter()
+        .enumerate()
+        .map(|(i, tri)| {
+            let mut fragments = triangle(&tri[0], &tri[1], &tri[2], &light_dir, samples, precision);
+            let texture_lod = uniforms.texture.as_ref().map_or(0.0, |texture| triangle_lod(
+                [tri[0].transformed_position, tri[1].transformed_position, tri[2].transformed_position],
+                [tri[0].tex_coords, tri[1].tex_coords, tri[2].tex_coords],
+                texture,
+            ));
+            let bias = options.depth_bias + options.slope_scaled_bias * depth_slope(
+                &tri[0].transformed_position,
+                &tri[1].transformed_position,
+                &tri[2].transformed_position,
+            );
+            for fragment in fragments.iter_mut() {
+                fragment.primitive_id = i as u32 + 1;
+                fragment.texture_lod = texture_lod;
+                fragment.depth -= bias;
+                if options.two_sided {
+                    fragment.normal = facing_normal(fragment, uniforms);
+                    fragment.intensity = dot(&fragment.normal, &light_dir.normalize()).clamp(0.0, 1.0);
+                }
+            }
+            fragments
+        })
+        .collect();
+
+    per_triangle.into_iter().flatten().collect()
+}
+
+// Opciones de cada llamada de dibujo
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct DrawOptions {
+    pub topology: PrimitiveTopology,
+    // Ilumina también las caras traseras (geometría delgada como el anillo)
+    pub two_sided: bool,
+    // Desplazamiento de la profundidad hacia la cámara antes de la prueba del
+    // z-buffer (polygon offset), para que calcomanías coplanares ganen siempre:
+    // depth_bias + slope_scaled_bias * pendiente de profundidad del triángulo
+    pub depth_bias: f32,
+    pub slope_scaled_bias: f32,
+    // Dibuja además el reflejo de la malla en el plano por el origen con esta
+    // normal (vista previa de simetría)
+    pub mirror: Option<Vec3>,
+}
+
+// Opciones del pipeline que no dependen del shader
+pub struct RenderConfig {
+    // Llena el z-buffer antes de sombrear para correr el shader solo en el
+    // fragmento visible de cada píxel (no se usa con MSAA)
+    pub depth_prepass: bool,
+    // Cuánto del frame anterior se conserva en el nuevo (0 = sin estela)
+    pub feedback: f32,
+    // Motion blur: cada fragmento se sombrea en motion_blur_samples instantes
+    // repartidos en [time, time + shutter) y se promedia; 0 o 1 lo desactiva
+    pub motion_blur_samples: u32,
+    pub shutter: f32,
+    // Hilos para rasterizar y sombrear; None usa todos los núcleos
+    pub threads: Option<usize>,
+    // Ancho / alto de un píxel del dispositivo de salida (1 = píxeles cuadrados)
+    pub pixel_aspect: f32,
+    // Con MSAA, el alfa del recorte (params.cutout) elige cuántas muestras
+    // cubre cada fragmento en lugar de la prueba de alfa de todo o nada
+    pub alpha_to_coverage: bool,
+    // Límite de triángulos por malla: render devuelve TooManyTriangles en vez
+    // de quedarse rasterizando una malla desmesurada. None = sin límite
+    pub max_triangles: Option<usize>,
+    // Aritmética de las aristas al rasterizar (ver RasterPrecision)
+    pub raster_precision: RasterPrecision,
+    // Supermuestreo progresivo: promedia frames con jitter mientras la cámara
+    // y la luz no se muevan (ver FrameAccumulator)
+    pub accumulate: bool,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            depth_prepass: false,
+            feedback: 0.0,
+            motion_blur_samples: 1,
+            shutter: 0.0,
+            threads: None,
+            pixel_aspect: 1.0,
+            alpha_to_coverage: false,
+            max_triangles: None,
+            raster_precision: RasterPrecision::Float,
+            accumulate: false,
+        }
+    }
+}
+
+impl RenderConfig {
+    // Corre work en un pool de threads hilos, o en el global si no hay límite.
+    // El pool sale de threads en cada llamada, así que nunca contradice al campo
+    pub fn run<R: Send>(&self, work: impl FnOnce() -> R + Send) -> R {
+        match self.threads {
+            Some(threads) => rayon::ThreadPoolBuilder::new()
+                .num_threads(threads.max(1))
+                .build()
+                .expect("could not build the render thread pool")
+                .install(work),
+            None => work(),
+        }
+    }
+}
+
+// Fragmentos generados y veces que corrió el fragment shader en un frame
+#[derive(Clone, Copy, Default)]
+pub struct RenderStats {
+    pub fragments: usize,
+    pub shaded: usize,
+}
+
+impl std::ops::AddAssign for RenderStats {
+    fn add_assign(&mut self, other: RenderStats) {
+        self.fragments += other.fragments;
+        self.shaded += other.shaded;
+    }
+}
+
+// Por qué no se pudo dibujar una malla
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderError {
+    // La malla tiene más triángulos que RenderConfig::max_triangles
+    TooManyTriangles { count: usize, limit: usize },
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RenderError::TooManyTriangles { count, limit } => {
+                write!(f, "la malla tiene {} triángulos y el límite es {}", count, limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+// Resultado del fragment shader para un fragmento (albedo y normal solo en diferido)
+struct Shaded {
+    color: u32,
+    albedo: u32,
+    normal: u32,
+    // Muestras MSAA que escribe (la del fragmento, reducida por el recorte)
+    coverage: u32,
+}
+
+pub fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], current_shader: ShaderKind, cache: &mut RenderCache, config: &RenderConfig, options: &DrawOptions) -> Result<RenderStats, RenderError> {
+    if let Some(limit) = config.max_triangles {
+        let copies = if options.mirror.is_some() { 2 } else { 1 };
+        let count = assemble(vertex_array.len(), options.topology).len() * copies;
+        if count > limit {
+            return Err(RenderError::TooManyTriangles { count, limit });
+        }
+    }
+
+    let samples = framebuffer.msaa_samples;
+    let key = CacheKey::new(uniforms, vertex_array, samples, config.raster_precision, options);
+    let fragments = cache.fragments(key, || config.run(|| rasterize(uniforms, vertex_array, samples, config.raster_precision, options)));
+    let (width, height, deferred) = (framebuffer.width, framebuffer.height, framebuffer.deferred);
+
+    // Pre-pase de profundidad: por cada píxel se queda con el primer fragmento
+    // más cercano, el mismo que ganaría la prueba estricta de point, así que el
+    // resultado es idéntico al de un solo pase
+    // Con recorte el fragmento más cercano puede descartarse, así que no hay pre-pase
+    let visible = if config.depth_prepass && samples == 1 && uniforms.params.cutout.is_none() {
+        let mut nearest = framebuffer.zbuffer.clone();
+        let mut winner = vec![usize::MAX; width * height];
+        for (i, fragment) in fragments.iter().enumerate() {
+            if let Some(index) = framebuffer.index(fragment.position.x as usize, fragment.position.y as usize) {
+                if nearest[index] > fragment.depth {
+                    nearest[index] = fragment.depth;
+                    winner[index] = i;
+                }
+            }
+        }
+        Some(winner)
+    } else {
+        None
+    };
+
+    // Instantes del motion blur; con una sola muestra se usa uniforms tal cual
+    let blur_samples = config.motion_blur_samples.max(1);
+    let sub_frames: Vec<Uniforms> = if blur_samples > 1 {
+        (0..blur_samples)
+            .map(|i| uniforms.at_time(uniforms.time + config.shutter * i as f32 / blur_samples as f32))
+            .collect()
+    } else {
+        vec![uniforms.clone()]
+    };
+
+    // El shader corre en paralelo; las escrituras al framebuffer se hacen
+    // después, en orden, para que la prueba de profundidad no dependa de los hilos
+    let shaded: Vec<Option<Shaded>> = config.run(|| {
+        fragments.par_iter().enumerate().map(|(i, fragment)| {
+            let x = fragment.position.x as usize;
+            let y = fragment.position.y as usize;
+            let index = framebuffer.index(x, y)?;
+            if let Some(winner) = &visible {
+                if winner[index] != i {
+                    return None;
+                }
+            }
+
+            #[cfg(feature = "debug-checks")]
+            if !checks::check_fragment(fragment, current_shader) {
+                let error = checks::ERROR_COLOR.to_hex();
+                return Some(Shaded { color: error, albedo: error, normal: 0, coverage: fragment.coverage });
+            }
+
+            let alpha = cutout_alpha(fragment, uniforms);
+            let coverage = if samples > 1 && config.alpha_to_coverage {
+                fragment.coverage & alpha_to_coverage(alpha, samples, x, y)
+            } else if alpha < 0.5 {
+                0
+            } else {
+                fragment.coverage
+            };
+            if coverage == 0 {
+                return None;
+            }
+
+            if deferred {
+                let albedo = Color::average(sub_frames.iter().map(|u| fragment_albedo(fragment, u, current_shader)));
+                let shaded_color = Color::average(sub_frames.iter().map(|u| {
+                    apply_lighting(fragment_albedo(fragment, u, current_shader), fragment, u, current_shader)
+                }));
+                let normal = Color::from_normal(&fragment.normal);
+                Some(Shaded { color: shaded_color.to_hex(), albedo: albedo.to_hex(), normal: normal.to_hex(), coverage })
+            } else {
+                let shaded_color = Color::average(sub_frames.iter().map(|u| fragment_shader(fragment, u, current_shader)));
+                Some(Shaded { color: shaded_color.to_hex(), albedo: 0, normal: 0, coverage })
+            }
+        }).collect()
+    });
+
+    let mut stats = RenderStats { fragments: fragments.len(), shaded: 0 };
+    for (fragment, shaded) in fragments.iter().zip(&shaded) {
+        let Some(shaded) = shaded else { continue };
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        stats.shaded += 1;
+
+        framebuffer.set_current_color(shaded.color);
+        framebuffer.set_current_id(fragment.primitive_id);
+        if deferred {
+            framebuffer.point_deferred(x, y, fragment.depth, shaded.coverage, shaded.albedo, shaded.normal);
+        } else if samples > 1 {
+            framebuffer.point_coverage(x, y, fragment.depth, shaded.coverage);
+        } else {
+            framebuffer.point(x, y, fragment.depth);
+        }
+    }
+
+    Ok(stats)
+}
+
+// Dibuja la misma malla una vez por transformación; la geometría se comparte
+// y solo cambia la matriz de modelo que ve el vertex shader. caches guarda una
+// RenderCache por instancia (se ajusta al número de transformaciones), así que
+// las instancias quietas no se rasterizan de nuevo en cada frame
+pub fn draw_instanced(framebuffer: &mut Framebuffer, vertex_array: &[Vertex], instance_transforms: &[Mat4], uniforms: &mut Uniforms, current_shader: ShaderKind, caches: &mut Vec<RenderCache>, config: &RenderConfig) -> Result<RenderStats, RenderError> {
+    let model_matrix = uniforms.model_matrix;
+    let mut stats = RenderStats::default();
+    let mut result = Ok(());
+    caches.resize_with(instance_transforms.len(), RenderCache::new);
+    for (transform, cache) in instance_transforms.iter().zip(caches.iter_mut()) {
+        uniforms.model_matrix = *transform;
+        match render(framebuffer, uniforms, vertex_array, current_shader, cache, config, &DrawOptions::default()) {
+            Ok(instance) => stats += instance,
+            Err(error) => {
+                result = Err(error);
+                break;
+            }
+        }
+    }
+    uniforms.model_matrix = model_matrix;
+    result.map(|_| stats)
+}
+
+// Dibuja cada vértice como un punto suavizado de point_size píxeles con su
+// color (ver Framebuffer::point_sprite). Con attenuation = Some(d) el tamaño se
+// escala por d / distancia, así que a distancia d mide exactamente point_size
+pub fn draw_points(framebuffer: &mut Framebuffer, uniforms: &Uniforms, points: &[Vertex], point_size: f32, attenuation: Option<f32>) {
+    let transform = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix;
+    for point in points {
+        let clip = transform * Vec4::new(point.position.x, point.position.y, point.position.z, 1.0);
+        if clip.w <= 0.0 {
+            continue;
+        }
+        let screen = uniforms.viewport_matrix * (clip / clip.w);
+        let size = match attenuation {
+            Some(distance) => point_size * distance / clip.w,
+            None => point_size,
+        };
+        framebuffer.point_sprite(screen.x, screen.y, screen.z, point.color, size);
+    }
+}
+
+// Ejes X (rojo), Y (verde) y Z (azul) del mundo en la esquina inferior
+// izquierda, de `size` píxeles. Solo se usa la rotación de la vista, así los
+// ejes muestran hacia dónde mira la cámara sin importar dónde esté. Se dibujan
+// del más lejano al más cercano y sin prueba de profundidad
+pub fn draw_axis_gizmo(framebuffer: &mut Framebuffer, uniforms: &Uniforms, size: f32) {
+    let origin = Vec3::new(size + 10.0, framebuffer.height as f32 - size - 10.0, 0.0);
+    let mut axes = [
+        (Vec3::new(1.0, 0.0, 0.0), Color::new(230, 60, 60)),
+        (Vec3::new(0.0, 1.0, 0.0), Color::new(60, 200, 60)),
+        (Vec3::new(0.0, 0.0, 1.0), Color::new(70, 110, 240)),
+    ].map(|(axis, color)| {
+        let view = uniforms.view_matrix * Vec4::new(axis.x, axis.y, axis.z, 0.0);
+        (Vec3::new(view.x, view.y, view.z), color)
+    });
+    axes.sort_by(|a, b| a.0.z.total_cmp(&b.0.z));
+
+    for (direction, color) in axes {
+        let mut start = Vertex::new_with_color(Vec3::zeros(), color);
+        let mut end = Vertex::new_with_color(Vec3::zeros(), color);
+        start.transformed_position = origin;
+        end.transformed_position = origin + Vec3::new(direction.x, -direction.y, 0.0) * size;
+        for fragment in line(&start, &end) {
+            if fragment.position.x >= 0.0 && fragment.position.y >= 0.0 {
+                framebuffer.set(fragment.position.x as usize, fragment.position.y as usize, fragment.color);
+            }
+        }
+    }
+}
+
+// "Hola triángulo": un triángulo grande directamente en coordenadas de
+// dispositivo (matrices de modelo, vista y proyección identidad) que recorre
+// vertex shader, rasterizado y fragment shader sin cargar ninguna malla
+pub fn draw_debug_triangle(framebuffer: &mut Framebuffer, uniforms: &mut Uniforms, current_shader: ShaderKind, config: &RenderConfig) -> Result<RenderStats, RenderError> {
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+    let vertices = [
+        Vertex::new(Vec3::new(-0.9, -0.9, 0.0), normal, Vec2::new(0.0, 1.0)),
+        Vertex::new(Vec3::new(0.9, -0.9, 0.0), normal, Vec2::new(1.0, 1.0)),
+        Vertex::new(Vec3::new(0.0, 0.9, 0.0), normal, Vec2::new(0.5, 0.0)),
+    ];
+
+    let matrices = (uniforms.model_matrix, uniforms.view_matrix, uniforms.projection_matrix);
+    uniforms.model_matrix = Mat4::identity();
+    uniforms.view_matrix = Mat4::identity();
+    uniforms.projection_matrix = Mat4::identity();
+    let stats = render(framebuffer, uniforms, &vertices, current_shader, &mut RenderCache::new(), config, &DrawOptions::default());
+    (uniforms.model_matrix, uniforms.view_matrix, uniforms.projection_matrix) = matrices;
+    stats
+}
+
+// Escena fija para medir el render: icosfera (nivel 3) con el shader del sol
+// a t = 1 y semilla 0, siempre con la misma cámara y luz, así los tiempos de
+// dos versiones del código son comparables (ver benches/render.rs)
+pub fn benchmark_scene(width: usize, height: usize) -> (Uniforms, Vec<Vertex>) {
+    let eye = Vec3::new(0.0, 0.0, 3.0);
+    let uniforms = Uniforms {
+        model_matrix: Mat4::identity(),
+        view_matrix: create_view_matrix(eye, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        projection_matrix: create_perspective_matrix(width as f32, height as f32),
+        viewport_matrix: create_viewport_matrix(width as f32, height as f32, 1.0, Vec2::zeros()),
+        time: 1.0,
+        noise: Arc::new(create_noise()),
+        light_dir: Vec3::new(0.0, 0.0, 1.0),
+        camera_pos: eye,
+        params: ShaderParams::default(),
+        seed: 0,
+        white_balance: Vec3::new(1.0, 1.0, 1.0),
+        exposure: 1.0,
+        shadow_map: None,
+        spot_shadow_map: None,
+        texture: None,
+    };
+    (uniforms, icosphere(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::ConstantNoise;
+    use crate::shaders::Cutout;
+
+    // Framebuffer limpio y la escena fija de benchmark_scene
+    fn scene(width: usize, height: usize) -> (Framebuffer, Uniforms, Vec<Vertex>) {
+        let (uniforms, vertices) = benchmark_scene(width, height);
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.clear();
+        (framebuffer, uniforms, vertices)
+    }
+
+    // (r, g, b) de un píxel 0xRRGGBB
+    fn channels(pixel: u32) -> [i32; 3] {
+        [(pixel >> 16 & 0xFF) as i32, (pixel >> 8 & 0xFF) as i32, (pixel & 0xFF) as i32]
+    }
+
+    fn draw(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertices: &[Vertex], shader: ShaderKind) -> RenderStats {
+        render(framebuffer, uniforms, vertices, shader, &mut RenderCache::new(), &RenderConfig::default(), &DrawOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn deferred_buffers_hold_unlit_albedo_and_encoded_normals() {
+        let (mut front_lit, mut uniforms, vertices) = scene(64, 64);
+        front_lit.set_deferred(true);
+        draw(&mut front_lit, &uniforms, &vertices, ShaderKind::Rocoso);
+
+        uniforms.light_dir = Vec3::new(0.0, 0.0, -1.0);
+        let (mut back_lit, ..) = scene(64, 64);
+        back_lit.set_deferred(true);
+        draw(&mut back_lit, &uniforms, &vertices, ShaderKind::Rocoso);
+
+        // La luz cambia la imagen final pero no el albedo
+        assert_ne!(front_lit.buffer, back_lit.buffer);
+        assert_eq!(front_lit.albedo_buffer, back_lit.albedo_buffer);
+        let center = front_lit.index(32, 32).unwrap();
+        assert_ne!(front_lit.albedo_buffer[center], 0);
+
+        // En el centro del disco la normal mira a la cámara: (0, 0, 1) -> (128, 128, 255)
+        let [r, g, b] = channels(front_lit.normal_buffer[center]);
+        assert!((r - 128).abs() <= 8 && (g - 128).abs() <= 8 && b >= 250, "{:?}", [r, g, b]);
+    }
+
+    #[test]
+    fn orbit_light_returns_to_its_start_after_a_full_period() {
+        let (_, mut uniforms, _) = scene(8, 8);
+        let start = Vec3::new(0.0, 0.0, 1.0);
+        let speed = PI / 4.0;
+
+        uniforms.light_dir = start;
+        uniforms.time = 2.0 * PI / speed;
+        uniforms.orbit_light(Vec3::new(0.0, 1.0, 0.0), speed);
+        assert!((uniforms.light_dir - start).magnitude() < 1e-4, "{:?}", uniforms.light_dir);
+
+        // A mitad de período la luz está del otro lado
+        uniforms.light_dir = start;
+        uniforms.time = PI / speed;
+        uniforms.orbit_light(Vec3::new(0.0, 1.0, 0.0), speed);
+        assert!((uniforms.light_dir + start).magnitude() < 1e-4, "{:?}", uniforms.light_dir);
+    }
+
+    #[test]
+    fn the_cached_path_matches_a_full_render() {
+        let (_, mut uniforms, vertices) = scene(64, 48);
+        let mut cache = RenderCache::new();
+        let draw_cached = |uniforms: &Uniforms, cache: &mut RenderCache| {
+            let (mut framebuffer, ..) = scene(64, 48);
+            render(&mut framebuffer, uniforms, &vertices, ShaderKind::Sol, cache, &RenderConfig::default(), &DrawOptions::default()).unwrap();
+            framebuffer.buffer
+        };
+        let draw_full = |uniforms: &Uniforms| {
+            let (mut framebuffer, ..) = scene(64, 48);
+            draw(&mut framebuffer, uniforms, &vertices, ShaderKind::Sol);
+            framebuffer.buffer
+        };
+        draw_cached(&uniforms, &mut cache);
+
+        // Solo avanza el tiempo: se reutilizan los fragmentos
+        uniforms.time = 2.0;
+        assert_eq!(draw_cached(&uniforms, &mut cache), draw_full(&uniforms));
+
+        // Cambia el modelo: la caché se descarta sola
+        uniforms.model_matrix = create_model_matrix(Vec3::new(0.3, 0.0, 0.0), 0.8, Vec3::new(0.0, 0.5, 0.0));
+        assert_eq!(draw_cached(&uniforms, &mut cache), draw_full(&uniforms));
+    }
+
+    #[test]
+    fn editing_a_mesh_in_place_misses_the_cache() {
+        let (_, uniforms, mut vertices) = scene(64, 48);
+        let mut cache = RenderCache::new();
+        let draw_cached = |vertices: &[Vertex], cache: &mut RenderCache| {
+            let (mut framebuffer, ..) = scene(64, 48);
+            render(&mut framebuffer, &uniforms, vertices, ShaderKind::Sol, cache, &RenderConfig::default(), &DrawOptions::default()).unwrap();
+            framebuffer.zbuffer
+        };
+        let before = draw_cached(&vertices, &mut cache);
+
+        // Misma cantidad de vértices, pero la malla se encoge a la mitad
+        for vertex in vertices.iter_mut() {
+            vertex.position *= 0.5;
+        }
+        let after = draw_cached(&vertices, &mut cache);
+        let (mut full, ..) = scene(64, 48);
+        draw(&mut full, &uniforms, &vertices, ShaderKind::Sol);
+        assert_ne!(after, before);
+        assert_eq!(after, full.zbuffer);
+    }
+
+    // Columna media de los píxeles escritos (con profundidad finita)
+    fn mean_written_column(framebuffer: &Framebuffer) -> Option<f32> {
+        let columns: Vec<usize> = framebuffer.zbuffer.iter().enumerate()
+            .filter(|(_, depth)| depth.is_finite())
+            .map(|(index, _)| index % framebuffer.width)
+            .collect();
+        if columns.is_empty() {
+            return None;
+        }
+        Some(columns.iter().sum::<usize>() as f32 / columns.len() as f32)
+    }
+
+    #[test]
+    fn instances_of_one_mesh_land_where_their_transforms_put_them() {
+        let (_, mut uniforms, vertices) = scene(64, 48);
+        let left = create_model_matrix(Vec3::new(-1.0, 0.0, 0.0), 0.3, Vec3::zeros());
+        let right = create_model_matrix(Vec3::new(1.0, 0.0, 0.0), 0.3, Vec3::zeros());
+        let draw_at = |uniforms: &mut Uniforms, transforms: &[Mat4]| {
+            let (mut framebuffer, ..) = scene(64, 48);
+            draw_instanced(&mut framebuffer, &vertices, transforms, uniforms, ShaderKind::Rocoso, &mut Vec::new(), &RenderConfig::default()).unwrap();
+            framebuffer
+        };
+
+        let left_column = mean_written_column(&draw_at(&mut uniforms, &[left])).unwrap();
+        let right_column = mean_written_column(&draw_at(&mut uniforms, &[right])).unwrap();
+        assert!(left_column < 28.0 && right_column > 36.0, "{} {}", left_column, right_column);
+
+        // Las dos instancias juntas: cada una en su sitio y nada en el centro
+        let both = draw_at(&mut uniforms, &[left, right]);
+        assert!(both.zbuffer[both.index(left_column as usize, 24).unwrap()].is_finite());
+        assert!(both.zbuffer[both.index(right_column as usize, 24).unwrap()].is_finite());
+        assert!(both.zbuffer[both.index(32, 24).unwrap()].is_infinite());
+        assert_eq!(uniforms.model_matrix, benchmark_scene(64, 48).0.model_matrix);
+    }
+
+    #[test]
+    fn each_instance_keeps_its_own_cache_between_frames() {
+        let (_, mut uniforms, vertices) = scene(64, 48);
+        let transforms = [
+            create_model_matrix(Vec3::new(-1.0, 0.0, 0.0), 0.3, Vec3::zeros()),
+            create_model_matrix(Vec3::new(1.0, 0.0, 0.0), 0.3, Vec3::zeros()),
+        ];
+        let mut caches = Vec::new();
+        let mut draw_frame = |uniforms: &mut Uniforms| {
+            let (mut framebuffer, ..) = scene(64, 48);
+            draw_instanced(&mut framebuffer, &vertices, &transforms, uniforms, ShaderKind::Sol, &mut caches, &RenderConfig::default()).unwrap();
+            framebuffer.buffer
+        };
+        let first = draw_frame(&mut uniforms);
+        uniforms.time = 2.0;
+        let second = draw_frame(&mut uniforms);
+
+        // Una caché por instancia, cada una con los fragmentos de su transformación
+        assert_eq!(caches.len(), 2);
+        assert!(caches.iter().all(|cache| cache.fragments.as_ref().is_some_and(|fragments| !fragments.is_empty())));
+        assert!(caches[0].key != caches[1].key);
+        let mut uncached = Vec::new();
+        let (mut framebuffer, ..) = scene(64, 48);
+        draw_instanced(&mut framebuffer, &vertices, &transforms, &mut uniforms, ShaderKind::Sol, &mut uncached, &RenderConfig::default()).unwrap();
+        // El sol sigue animándose con los fragmentos guardados
+        assert_eq!(second, framebuffer.buffer);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn a_billboard_faces_the_camera_from_its_position() {
+        let position = Vec3::new(2.0, 1.0, -3.0);
+        let camera_pos = Vec3::new(-1.0, 4.0, 5.0);
+        let matrix = billboard_matrix(position, camera_pos, Vec3::new(0.0, 1.0, 0.0));
+
+        // El +z local del quad apunta del billboard a la cámara y su origen queda en position
+        let forward = matrix * Vec4::new(0.0, 0.0, 1.0, 0.0);
+        let origin = matrix * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        assert!((forward.xyz() - (camera_pos - position).normalize()).magnitude() < 1e-5, "{:?}", forward);
+        assert!((origin.xyz() - position).magnitude() < 1e-5);
+
+        // Con up paralelo a la vista la base sigue siendo ortonormal
+        let above = billboard_matrix(Vec3::zeros(), Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let right = (above * Vec4::new(1.0, 0.0, 0.0, 0.0)).xyz();
+        let forward = (above * Vec4::new(0.0, 0.0, 1.0, 0.0)).xyz();
+        assert!((right.magnitude() - 1.0).abs() < 1e-5 && right.dot(&forward).abs() < 1e-5);
+        assert!((forward - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn the_depth_prepass_shades_fewer_fragments_for_the_same_image() {
+        let (mut single_pass, uniforms, vertices) = scene(64, 48);
+        let single_stats = draw(&mut single_pass, &uniforms, &vertices, ShaderKind::Rocoso);
+
+        let (mut prepass, ..) = scene(64, 48);
+        let config = RenderConfig { depth_prepass: true, ..RenderConfig::default() };
+        let prepass_stats = render(&mut prepass, &uniforms, &vertices, ShaderKind::Rocoso, &mut RenderCache::new(), &config, &DrawOptions::default()).unwrap();
+
+        assert_eq!(prepass.buffer, single_pass.buffer);
+        assert_eq!(prepass_stats.fragments, single_stats.fragments);
+        // La cara trasera de la esfera ya no se sombrea
+        assert_eq!(single_stats.shaded, single_stats.fragments);
+        assert!(prepass_stats.shaded < single_stats.shaded * 3 / 4, "{} vs {}", prepass_stats.shaded, single_stats.shaded);
+    }
+
+    #[test]
+    fn one_thread_and_several_render_the_same_image() {
+        let (_, uniforms, vertices) = scene(64, 48);
+        let draw_with = |threads: usize| {
+            let (mut framebuffer, ..) = scene(64, 48);
+            let config = RenderConfig { threads: Some(threads), ..RenderConfig::default() };
+            render(&mut framebuffer, &uniforms, &vertices, ShaderKind::Gaseoso, &mut RenderCache::new(), &config, &DrawOptions::default()).unwrap();
+            (framebuffer.buffer, framebuffer.zbuffer)
+        };
+
+        let single = draw_with(1);
+        assert_eq!(draw_with(4), single);
+        assert_eq!(draw_with(7), single);
+    }
+
+    #[test]
+    fn a_struct_literal_thread_count_limits_the_pool_run_uses() {
+        let config = RenderConfig { threads: Some(1), ..RenderConfig::default() };
+        assert_eq!(config.run(rayon::current_num_threads), 1);
+        let config = RenderConfig { threads: Some(3), ..RenderConfig::default() };
+        assert_eq!(config.run(rayon::current_num_threads), 3);
+        // Sin límite corre en el pool global
+        assert_eq!(RenderConfig::default().run(rayon::current_num_threads), rayon::current_num_threads());
+    }
+
+    // Píxel en el que cae el punto de objeto p con las matrices de uniforms
+    fn project(uniforms: &Uniforms, model: &Mat4, p: Vec3) -> (usize, usize) {
+        let clip = uniforms.projection_matrix * uniforms.view_matrix * model * Vec4::new(p.x, p.y, p.z, 1.0);
+        let screen = uniforms.viewport_matrix * (clip / clip.w);
+        (screen.x as usize, screen.y as usize)
+    }
+
+    #[test]
+    fn rotating_the_model_carries_the_cloud_pattern_with_it() {
+        let (_, mut uniforms, vertices) = scene(96, 96);
+        // Nubes grandes para que un píxel de error no cambie el color
+        uniforms.params.detail = 0.02;
+        let albedo = |uniforms: &Uniforms| {
+            let (mut framebuffer, ..) = scene(96, 96);
+            framebuffer.set_deferred(true);
+            draw(&mut framebuffer, uniforms, &vertices, ShaderKind::Gaseoso);
+            framebuffer
+        };
+        let still = albedo(&uniforms);
+        uniforms.model_matrix = create_model_matrix(Vec3::zeros(), 1.0, Vec3::new(0.0, 0.4, 0.0));
+        let rotated = albedo(&uniforms);
+        let inverse = uniforms.model_matrix.try_inverse().unwrap();
+
+        // Diferencia media entre cada punto sin rotar y donde lo lleva la rotación (o la inversa)
+        let points: Vec<Vec3> = (0..9).map(|i| Vec3::new(i as f32 * 0.1 - 0.4, 0.2, 0.9).normalize()).collect();
+        let difference = |model: &Mat4| {
+            points.iter().map(|&p| {
+                let (x0, y0) = project(&uniforms, &Mat4::identity(), p);
+                let (x1, y1) = project(&uniforms, model, p);
+                let (a, b) = (channels(still.albedo_buffer[still.index(x0, y0).unwrap()]), channels(rotated.albedo_buffer[rotated.index(x1, y1).unwrap()]));
+                (a[0] - b[0]).abs()
+            }).sum::<i32>() as f32 / points.len() as f32
+        };
+
+        let with_model = difference(&uniforms.model_matrix);
+        let against_model = difference(&inverse);
+        assert!(with_model < 3.0 && with_model < against_model, "{} vs {}", with_model, against_model);
+    }
+
+    #[test]
+    fn the_debug_triangle_fills_a_large_contiguous_region() {
+        let (mut framebuffer, mut uniforms, _) = scene(64, 48);
+        let before = uniforms.model_matrix;
+        draw_debug_triangle(&mut framebuffer, &mut uniforms, ShaderKind::Rocoso, &RenderConfig::default()).unwrap();
+        assert_eq!(uniforms.model_matrix, before);
+
+        // El triángulo cubre 0.9 * 0.9 de la pantalla entre dos: ~40 % de los píxeles
+        let written = framebuffer.zbuffer.iter().filter(|depth| depth.is_finite()).count();
+        let expected = 64.0 * 48.0 * 0.405;
+        assert!((written as f32 - expected).abs() < expected * 0.05, "{}", written);
+
+        // Cada fila cubierta es un único tramo sin huecos, todo sombreado
+        for row in framebuffer.zbuffer.chunks(64) {
+            let covered: Vec<usize> = (0..64).filter(|&x| row[x].is_finite()).collect();
+            if let (Some(first), Some(last)) = (covered.first(), covered.last()) {
+                assert_eq!(covered.len(), last - first + 1);
+            }
+        }
+        assert_ne!(framebuffer.get(32, 24).unwrap(), Color::black());
+    }
+
+    #[test]
+    fn motion_blur_lands_between_the_shutter_endpoints() {
+        let (_, uniforms, vertices) = scene(32, 32);
+        let config = RenderConfig { motion_blur_samples: 4, shutter: 0.2, ..RenderConfig::default() };
+        let (mut blurred, ..) = scene(32, 32);
+        render(&mut blurred, &uniforms, &vertices, ShaderKind::Sol, &mut RenderCache::new(), &config, &DrawOptions::default()).unwrap();
+
+        // Las muestras van de time a time + 3/4 del obturador
+        let frame_at = |time: f32| {
+            let (mut framebuffer, ..) = scene(32, 32);
+            draw(&mut framebuffer, &uniforms.at_time(time), &vertices, ShaderKind::Sol);
+            framebuffer.buffer
+        };
+        let (open, close) = (frame_at(1.0), frame_at(1.15));
+        assert_ne!(blurred.buffer, open);
+
+        let index = blurred.index(16, 16).unwrap();
+        let (a, b, blur) = (channels(open[index]), channels(close[index]), channels(blurred.buffer[index]));
+        for channel in 0..3 {
+            let (low, high) = (a[channel].min(b[channel]), a[channel].max(b[channel]));
+            assert!(blur[channel] >= low - 1 && blur[channel] <= high + 1, "{:?} {:?} {:?}", a, blur, b);
+        }
+    }
+
+    #[test]
+    fn the_id_buffer_keeps_the_front_triangle_where_two_overlap() {
+        let (mut framebuffer, mut uniforms, _) = scene(32, 32);
+        framebuffer.set_id_buffer(true);
+        // Directo en coordenadas de dispositivo: z menor queda delante
+        uniforms.model_matrix = Mat4::identity();
+        uniforms.view_matrix = Mat4::identity();
+        uniforms.projection_matrix = Mat4::identity();
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let corner = |x: f32, y: f32, z: f32| Vertex::new(Vec3::new(x, y, z), normal, Vec2::zeros());
+        let vertices = [
+            // 1: delante, a la izquierda
+            corner(-0.9, -0.9, -0.5), corner(1.5, -0.9, -0.5), corner(-0.9, 1.5, -0.5),
+            // 2: detrás, a la derecha, dibujado después
+            corner(0.9, 0.9, 0.5), corner(-1.5, 0.9, 0.5), corner(0.9, -1.5, 0.5),
+        ];
+        draw(&mut framebuffer, &uniforms, &vertices, ShaderKind::Rocoso);
+
+        // Centro: lo cubren los dos
+        assert_eq!(framebuffer.id_at(16, 16), Some(1));
+        assert_eq!(framebuffer.id_at(28, 4), Some(2));
+        assert_eq!(framebuffer.id_at(4, 28), Some(1));
+        assert_eq!(framebuffer.id_at(0, 0), Some(0));
+    }
+
+    // Quad de lado 1 en el plano z = 0 con todas las normales iguales
+    fn quad(normal: Vec3) -> Vec<Vertex> {
+        let corner = |x: f32, y: f32| Vertex::new(Vec3::new(x, y, 0.0), normal, Vec2::zeros());
+        vec![
+            corner(-0.5, -0.5), corner(0.5, -0.5), corner(0.5, 0.5),
+            corner(-0.5, -0.5), corner(0.5, 0.5), corner(-0.5, 0.5),
+        ]
+    }
+
+    #[test]
+    fn two_sided_lighting_lights_a_back_face_like_its_front_mirror() {
+        let (_, uniforms, _) = scene(32, 32);
+        let draw_quad = |normal: Vec3, two_sided: bool| {
+            let (mut framebuffer, ..) = scene(32, 32);
+            let options = DrawOptions { two_sided, ..DrawOptions::default() };
+            render(&mut framebuffer, &uniforms, &quad(normal), ShaderKind::Azul, &mut RenderCache::new(), &RenderConfig::default(), &options).unwrap();
+            framebuffer.buffer
+        };
+
+        let front = draw_quad(Vec3::new(0.0, 0.0, 1.0), false);
+        let back = Vec3::new(0.0, 0.0, -1.0);
+        // La luz y la cámara están del lado +z: sin dos caras la trasera queda negra
+        assert!(draw_quad(back, false).iter().all(|&pixel| pixel == 0));
+        assert_eq!(draw_quad(back, true), front);
+        assert_ne!(front.iter().filter(|&&pixel| pixel != 0).count(), 0);
+    }
+
+    #[test]
+    fn a_pixel_aspect_of_two_halves_the_horizontal_screen_extent() {
+        let to_screen = |viewport: &Mat4, x: f32, y: f32| viewport * Vec4::new(x, y, 0.0, 1.0);
+        let square = create_viewport_matrix(64.0, 48.0, 1.0, Vec2::zeros());
+        let wide = create_viewport_matrix(64.0, 48.0, 2.0, Vec2::zeros());
+
+        // Píxeles el doble de anchos: la misma distancia ocupa la mitad de columnas
+        let (a, b) = (to_screen(&square, 0.5, 0.5), to_screen(&wide, 0.5, 0.5));
+        assert_eq!(a.x - 32.0, 16.0);
+        assert_eq!(b.x - 32.0, 8.0);
+        assert_eq!(a.y, b.y);
+        assert_eq!(to_screen(&wide, 0.0, 0.0).xy(), Vec2::new(32.0, 24.0));
+    }
+
+    #[cfg(feature = "debug-checks")]
+    #[test]
+    fn a_nan_normal_is_reported_and_painted_magenta() {
+        let (mut framebuffer, uniforms, mut vertices) = scene(32, 32);
+        for vertex in &mut vertices {
+            vertex.normal = Vec3::new(f32::NAN, 0.0, 1.0);
+        }
+        draw(&mut framebuffer, &uniforms, &vertices, ShaderKind::Rocoso);
+
+        // Todo el disco sale del color de error, el fondo no se toca
+        let center = framebuffer.index(16, 16).unwrap();
+        assert_eq!(framebuffer.buffer[center], checks::ERROR_COLOR.to_hex());
+        assert_ne!(framebuffer.buffer[0], checks::ERROR_COLOR.to_hex());
+
+        // check_fragment es quien lo reporta y lo descarta
+        let fragment = Fragment::new(16.0, 16.0, Color::black(), 0.5, Vec3::new(f32::NAN, 0.0, 1.0), 1.0, Vec3::zeros());
+        assert!(!checks::check_fragment(&fragment, ShaderKind::Rocoso));
+        let valid = Fragment::new(16.0, 16.0, Color::black(), 0.5, Vec3::new(0.0, 0.0, 1.0), 1.0, Vec3::zeros());
+        assert!(checks::check_fragment(&valid, ShaderKind::Rocoso));
+    }
+
+    #[test]
+    fn a_depth_bias_makes_a_coplanar_decal_win_everywhere() {
+        let (_, uniforms, _) = scene(32, 32);
+        let surface = quad(Vec3::new(0.0, 0.0, 1.0));
+        let draw_alone = |shader: ShaderKind| {
+            let (mut framebuffer, ..) = scene(32, 32);
+            draw(&mut framebuffer, &uniforms, &surface, shader);
+            framebuffer.buffer
+        };
+        let draw_decal = |depth_bias: f32| {
+            let (mut framebuffer, ..) = scene(32, 32);
+            draw(&mut framebuffer, &uniforms, &surface, ShaderKind::Azul);
+            let options = DrawOptions { depth_bias, ..DrawOptions::default() };
+            render(&mut framebuffer, &uniforms, &surface, ShaderKind::Neon, &mut RenderCache::new(), &RenderConfig::default(), &options).unwrap();
+            framebuffer.buffer
+        };
+
+        let base = draw_alone(ShaderKind::Azul);
+        let decal = draw_alone(ShaderKind::Neon);
+        assert_ne!(base, decal);
+        // Sin sesgo la calcomanía coplanar no gana en ningún píxel; con él, en todos
+        assert_eq!(draw_decal(0.0), base);
+        assert_eq!(draw_decal(1e-3), decal);
+    }
+
+    #[test]
+    fn exceeding_the_triangle_limit_fails_and_staying_under_renders() {
+        let (mut framebuffer, uniforms, vertices) = scene(32, 32);
+        let count = vertices.len() / 3;
+        let render_with = |framebuffer: &mut Framebuffer, limit: usize, options: &DrawOptions| {
+            let config = RenderConfig { max_triangles: Some(limit), ..RenderConfig::default() };
+            render(framebuffer, &uniforms, &vertices, ShaderKind::Azul, &mut RenderCache::new(), &config, options)
+        };
+
+        let error = render_with(&mut framebuffer, count - 1, &DrawOptions::default()).err();
+        assert_eq!(error, Some(RenderError::TooManyTriangles { count, limit: count - 1 }));
+        // Sin dibujar nada
+        assert!(framebuffer.buffer.iter().all(|&pixel| pixel == 0));
+        // El reflejo cuenta como otra copia de la malla
+        let mirrored = DrawOptions { mirror: Some(Vec3::new(1.0, 0.0, 0.0)), ..DrawOptions::default() };
+        let error = render_with(&mut framebuffer, count, &mirrored).err();
+        assert_eq!(error, Some(RenderError::TooManyTriangles { count: 2 * count, limit: count }));
+
+        assert!(render_with(&mut framebuffer, count, &DrawOptions::default()).is_ok());
+        let (mut unlimited, ..) = scene(32, 32);
+        draw(&mut unlimited, &uniforms, &vertices, ShaderKind::Azul);
+        assert_eq!(framebuffer.buffer, unlimited.buffer);
+    }
+
+    #[test]
+    fn a_half_transparent_cutout_covers_about_half_the_samples() {
+        let (_, mut uniforms, vertices) = scene(32, 32);
+        // Ruido justo en el umbral: alfa 0.5 en toda la superficie
+        uniforms.noise = Arc::new(ConstantNoise(0.0));
+        uniforms.params.cutout = Some(Cutout::default());
+        let coverage = |alpha_to_coverage: bool| {
+            let (mut framebuffer, ..) = scene(32, 32);
+            framebuffer.set_msaa(4);
+            let config = RenderConfig { alpha_to_coverage, ..RenderConfig::default() };
+            render(&mut framebuffer, &uniforms, &vertices, ShaderKind::Azul, &mut RenderCache::new(), &config, &DrawOptions::default()).unwrap();
+            framebuffer.resolve_msaa();
+            let alpha = framebuffer.alpha();
+            let interior: Vec<f32> = (12..20).flat_map(|y| (12..20).map(move |x| y * 32 + x)).map(|index| alpha[index] as f32 / 255.0).collect();
+            interior.iter().sum::<f32>() / interior.len() as f32
+        };
+
+        // Sin alfa a cobertura el recorte es todo o nada
+        assert_eq!(coverage(false), 1.0);
+        let mean = coverage(true);
+        assert!((mean - 0.5).abs() < 0.1, "{mean}");
+    }
+
+    #[test]
+    fn the_axis_gizmo_points_x_right_y_up_and_z_at_the_viewer() {
+        let (_, mut uniforms, _) = scene(100, 100);
+        uniforms.view_matrix = Mat4::identity();
+        let mut framebuffer = Framebuffer::new(100, 100);
+        framebuffer.clear_to(Color::black());
+        draw_axis_gizmo(&mut framebuffer, &uniforms, 40.0);
+
+        // El origen del gizmo queda en (50, 50): X hacia la derecha y Y hacia arriba
+        let red = Color::new(230, 60, 60);
+        let green = Color::new(60, 200, 60);
+        assert_eq!(framebuffer.get(70, 50), Some(red));
+        assert_eq!(framebuffer.get(90, 50), Some(red));
+        assert_eq!(framebuffer.get(30, 50), Some(Color::black()));
+        assert_eq!(framebuffer.get(50, 30), Some(green));
+        assert_eq!(framebuffer.get(50, 70), Some(Color::black()));
+
+        // Z apunta a la cámara: se ve de punta, solo en el origen y encima de los otros
+        assert_eq!(framebuffer.get(50, 50), Some(Color::new(70, 110, 240)));
+        assert_eq!(framebuffer.buffer.iter().filter(|&&pixel| pixel == 0x466EF0).count(), 1);
+    }
+
+    #[test]
+    fn a_red_vertex_tints_nearby_fragments_toward_red() {
+        let (_, mut uniforms, _) = scene(64, 64);
+        let mut surface = quad(Vec3::new(0.0, 0.0, 1.0));
+        for vertex in surface.iter_mut() {
+            vertex.color = if vertex.position.x < 0.0 && vertex.position.y < 0.0 { Color::new(255, 0, 0) } else { Color::new(255, 255, 255) };
+        }
+        let shade = |uniforms: &Uniforms| {
+            let (mut framebuffer, ..) = scene(64, 64);
+            draw(&mut framebuffer, uniforms, &surface, ShaderKind::Azul);
+            framebuffer
+        };
+
+        let plain = shade(&uniforms);
+        uniforms.params.tint_by_vertex_color = true;
+        let tinted = shade(&uniforms);
+
+        // Junto a la esquina roja pierde verde y azul; en la esquina blanca opuesta no cambia
+        let model = Mat4::identity();
+        let (red_x, red_y) = project(&uniforms, &model, Vec3::new(-0.45, -0.45, 0.0));
+        let (white_x, white_y) = project(&uniforms, &model, Vec3::new(0.45, 0.45, 0.0));
+        let pixel = |framebuffer: &Framebuffer, x: usize, y: usize| channels(framebuffer.buffer[framebuffer.index(x, y).unwrap()]);
+        let [r, g, b] = pixel(&tinted, red_x, red_y);
+        let [plain_r, plain_g, plain_b] = pixel(&plain, red_x, red_y);
+        assert!(r == plain_r && g < plain_g / 2 && b < plain_b / 2, "{:?} vs {:?}", [r, g, b], [plain_r, plain_g, plain_b]);
+        let far = pixel(&tinted, white_x, white_y);
+        let plain_far = pixel(&plain, white_x, white_y);
+        assert!(far.iter().zip(plain_far).all(|(a, b)| (a - b).abs() <= 8), "{:?} vs {:?}", far, plain_far);
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::raster::RasterPrecision;
+use crate::shaders::{ShaderKind, ShaderParams};
+
+// Todo lo que el usuario puede ajustar, para guardar una configuración y
+// volver a obtener exactamente el mismo render al cargarla
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderSetup {
+    pub shader: ShaderKind,
+    pub params: ShaderParams,
+    // Índice en la lista de paletas del planeta celular (ver main)
+    pub cellular_ramp: usize,
+    pub camera_eye: [f32; 3],
+    pub camera_center: [f32; 3],
+    pub camera_up: [f32; 3],
+    pub light_dir: [f32; 3],
+    pub seed: u64,
+    // Segundos de animación
+    pub time: f32,
+    // Lo que sigue se agregó después; con serde(default) las configuraciones
+    // guardadas antes se siguen cargando con los valores iniciales de la ventana
+    // Índice en la lista de mallas (esfera, toro) y nivel de teselado (ver main)
+    #[serde(default)]
+    pub mesh: usize,
+    // None: automático según el tamaño en pantalla
+    #[serde(default = "base_level")]
+    pub tessellation_level: Option<u32>,
+    #[serde(default)]
+    pub displaced: bool,
+    // Índices en las listas de gradaciones y balances de blancos (ver main)
+    #[serde(default)]
+    pub color_grade: usize,
+    #[serde(default)]
+    pub white_balance: usize,
+    #[serde(default)]
+    pub sepia: f32,
+    #[serde(default = "one")]
+    pub saturation: f32,
+    #[serde(default)]
+    pub hue_degrees: f32,
+    #[serde(default)]
+    pub smoothing: bool,
+    #[serde(default)]
+    pub auto_exposure: bool,
+    // MSAA 4x (M)
+    #[serde(default)]
+    pub msaa: bool,
+    // Índice de la vista de depuración (F1 a F4 y acento grave, ver main)
+    #[serde(default)]
+    pub debug_view: usize,
+    // Campos de RenderConfig; los hilos, el aspecto de píxel y el límite de
+    // triángulos vienen de variables de entorno y no se guardan
+    #[serde(default)]
+    pub depth_prepass: bool,
+    #[serde(default)]
+    pub feedback: f32,
+    #[serde(default = "single_sample")]
+    pub motion_blur_samples: u32,
+    #[serde(default)]
+    pub shutter: f32,
+    #[serde(default)]
+    pub alpha_to_coverage: bool,
+    #[serde(default)]
+    pub raster_precision: RasterPrecision,
+    #[serde(default)]
+    pub accumulate: bool,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn base_level() -> Option<u32> {
+    Some(0)
+}
+
+fn single_sample() -> u32 {
+    1
+}
+
+impl RenderSetup {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::Framebuffer;
+    use crate::render::{benchmark_scene, render, DrawOptions, RenderCache, RenderConfig};
+
+    fn sample_setup() -> RenderSetup {
+        RenderSetup {
+            shader: ShaderKind::Sol,
+            params: ShaderParams { limb_darkening: 0.6, spot_density: 20.0, ..ShaderParams::default() },
+            cellular_ramp: 2,
+            camera_eye: [0.0, 1.0, 3.0],
+            camera_center: [0.0, 0.0, 0.0],
+            camera_up: [0.0, 1.0, 0.0],
+            light_dir: [0.3, 0.5, 1.0],
+            seed: 7,
+            time: 2.5,
+            mesh: 1,
+            tessellation_level: Some(2),
+            displaced: true,
+            color_grade: 3,
+            white_balance: 1,
+            sepia: 0.5,
+            saturation: 1.5,
+            hue_degrees: 60.0,
+            smoothing: true,
+            auto_exposure: true,
+            msaa: true,
+            debug_view: 2,
+            depth_prepass: true,
+            feedback: 0.8,
+            motion_blur_samples: 4,
+            shutter: 0.25,
+            alpha_to_coverage: true,
+            raster_precision: RasterPrecision::FixedPoint,
+            accumulate: true,
+        }
+    }
+
+    fn render_setup(setup: &RenderSetup) -> Vec<u32> {
+        let (mut uniforms, vertices) = benchmark_scene(64, 48);
+        uniforms.params = setup.params;
+        uniforms.light_dir = setup.light_dir.into();
+        uniforms.seed = setup.seed;
+        uniforms.time = setup.time;
+        let mut framebuffer = Framebuffer::new(64, 48);
+        framebuffer.clear();
+        let config = RenderConfig { raster_precision: setup.raster_precision, ..RenderConfig::default() };
+        render(&mut framebuffer, &uniforms, &vertices, setup.shader, &mut RenderCache::new(), &config, &DrawOptions::default()).unwrap();
+        framebuffer.buffer
+    }
+
+    #[test]
+    fn save_then_load_gives_the_same_setup_and_render() {
+        let setup = sample_setup();
+        let path = std::env::temp_dir().join(format!("lab4_g_setup_{}.json", std::process::id()));
+        setup.save(&path).unwrap();
+        let loaded = RenderSetup::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(loaded == setup);
+        assert_eq!(render_setup(&loaded), render_setup(&setup));
+    }
+
+    #[test]
+    fn setups_saved_before_the_later_fields_load_with_defaults() {
+        let mut json = serde_json::to_value(sample_setup()).unwrap();
+        let object = json.as_object_mut().unwrap();
+        for field in ["mesh", "saturation", "msaa", "motion_blur_samples", "raster_precision", "accumulate"] {
+            object.remove(field);
+        }
+        let loaded: RenderSetup = serde_json::from_value(json).unwrap();
+
+        assert_eq!(loaded.mesh, 0);
+        assert_eq!(loaded.saturation, 1.0);
+        assert!(!loaded.msaa);
+        assert_eq!(loaded.motion_blur_samples, 1);
+        assert_eq!(loaded.raster_precision, RasterPrecision::Float);
+        assert!(!loaded.accumulate);
+        assert_eq!(loaded.seed, 7);
+    }
+}
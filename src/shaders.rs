@@ -1,13 +1,520 @@
 
-use nalgebra_glm::{Vec3, Vec4, Mat3, dot, mat4_to_mat3};
+use nalgebra_glm::{Vec3, Vec4, Mat3, Mat4, dot, mat4_to_mat3};
 use crate::vertex::Vertex;
 use crate::Uniforms;
 use crate::fragment::Fragment;
 use crate::color::Color;
+use crate::ramp::ColorRamp;
+use crate::light::SpotLight;
+use crate::shadow::ShadowSettings;
 use std::f32::consts::PI;
-use rand::Rng;
-use rand::SeedableRng;
-use rand::rngs::StdRng;
+
+// Parámetros ajustables de los shaders, compartidos por todos los planetas
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShaderParams {
+    // Oscurecimiento hacia el borde del disco solar (0 = disco plano)
+    pub limb_darkening: f32,
+    // Escala del ruido de las manchas: valores mayores dan manchas más pequeñas y numerosas
+    pub spot_density: f32,
+    // Umbral del ruido por debajo del cual hay mancha: controla su tamaño
+    pub spot_threshold: f32,
+    // Colores de la mancha y de la roca alrededor; spot_invert los intercambia
+    // (manchas claras sobre fondo oscuro en vez de oscuras sobre claro)
+    pub spot_color: Color,
+    pub spot_base_color: Color,
+    pub spot_invert: bool,
+    // Casquetes polares opcionales aplicados sobre cualquier planeta
+    pub ice_caps: Option<IceCaps>,
+    // Zonas climáticas: tiñe el albedo del ecuador a los polos
+    pub latitude_tint: Option<LatitudeTint>,
+    // Mezcla los degradados en luz lineal en lugar de espacio gamma
+    pub linear_blending: bool,
+    // Multiplica el albedo por el color de vértice interpolado (pintura por vértice)
+    pub tint_by_vertex_color: bool,
+    // Desfase (en las mismas unidades que t) de la pulsación del sol, el planeta
+    // rocoso y el de manchas, para que varios objetos no pulsen sincronizados
+    pub phase_offset: f32,
+    // Colores de las celdas del planeta celular y los umbrales de ruido que los
+    // separan. Sus paradas son estáticas, así que no se serializa (RenderSetup
+    // guarda cuál de las paletas conocidas está en uso)
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_cellular_ramp"))]
+    pub cellular_ramp: ColorRamp,
+    // Color del cielo que refleja el océano del planeta de arcilla hacia el
+    // borde del disco (según Fresnel); None desactiva el reflejo
+    pub reflection_color: Option<Color>,
+    // Recorte: huecos en la superficie donde el ruido cae bajo un umbral. Sin
+    // MSAA es una prueba de alfa (se descarta el fragmento); con MSAA y
+    // RenderConfig::alpha_to_coverage el alfa decide cuántas muestras cubre
+    pub cutout: Option<Cutout>,
+    // Nivel del mar, costa y hielo del planeta terrestre
+    pub earthlike: Earthlike,
+    // Contorno oscuro en la silueta (estilo caricatura)
+    pub outline: Option<Outline>,
+    // Tramado aplicado al valor de ruido antes de compararlo con los umbrales de
+    // las bandas (planetas rocoso y de arcilla); 0 deja los bordes nítidos
+    pub threshold_dither: f32,
+    // Brillo especular (Blinn-Phong) con la luz principal
+    pub specular: Option<Specular>,
+    // Espacio en el que quedan las normales (y en el que se ilumina)
+    pub normal_space: NormalSpace,
+    // Capa de nubes volumétrica sobre el planeta gaseoso (cara: se activa aparte)
+    pub volumetric_clouds: Option<VolumetricClouds>,
+    // Sombra de esa capa de nubes sobre la superficie
+    pub cloud_shadows: Option<CloudShadows>,
+    // Luz envolvente con translucidez para el planeta de arcilla
+    pub subsurface: Option<Subsurface>,
+    // Curvas de nivel sobre los shaders por bandas (rocoso, arcilla, celular)
+    pub contours: Option<Contours>,
+    // Aparición y desaparición del objeto según uniforms.time
+    pub envelope: Option<Envelope>,
+    // Cobertura de nubes del planeta gaseoso; None conserva su mezcla original
+    pub cloud_coverage: Option<CloudCoverage>,
+    // Foco adicional a la luz direccional (ver light.rs)
+    pub spot_light: Option<SpotLight>,
+    // Sombras proyectadas de la luz principal (mapa en uniforms.shadow_map)
+    pub shadows: Option<ShadowSettings>,
+    // Relieve: perturba la normal con el gradiente del ruido antes de iluminar
+    pub bump: Option<Bump>,
+    // Mezcla entre la normal geométrica (0) y la perturbada por el relieve (1);
+    // bajarla suaviza el autosombreado exagerado cerca de la silueta
+    pub normal_blend: f32,
+    // Exponente aplicado a fragment.intensity antes de iluminar (1 = lineal)
+    pub intensity_curve: f32,
+    // Nivel de detalle común a todos los shaders: 1 conserva el aspecto
+    // original de cada uno, 2 hace sus rasgos de la mitad de tamaño
+    pub detail: f32,
+    // Peso de la primera muestra de ruido del sol (1 = solo la primera, 0 = solo la segunda)
+    pub sol_noise_weight: f32,
+    // Desplazamiento de la segunda muestra de ruido del sol
+    pub sol_noise_offset: f32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VolumetricClouds {
+    // Pasos del ray marching: más pasos = menos bandas, más costo
+    pub steps: usize,
+    // Multiplicador de la densidad del ruido; 0 deja la capa transparente
+    pub density: f32,
+    // Grosor de la capa sobre la superficie, en radios del planeta
+    pub thickness: f32,
+    pub color: Color,
+}
+
+impl Default for VolumetricClouds {
+    fn default() -> Self {
+        VolumetricClouds {
+            steps: 8,
+            density: 6.0,
+            thickness: 0.15,
+            color: Color::new(245, 245, 250),
+        }
+    }
+}
+
+// strength es cuánto oscurece una nube opaca (1 = sombra negra); softness
+// es el radio del desenfoque de la sombra, en grosores de la capa
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CloudShadows {
+    pub strength: f32,
+    pub softness: f32,
+}
+
+impl Default for CloudShadows {
+    fn default() -> Self {
+        CloudShadows {
+            strength: 0.6,
+            softness: 0.5,
+        }
+    }
+}
+
+// En View las normales, la luz y la dirección de vista se pasan además por la
+// matriz de vista, así que una normal que mira a la cámara es siempre (0, 0, 1)
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NormalSpace {
+    #[default]
+    World,
+    View,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Specular {
+    pub strength: f32,
+    // Exponente del brillo: más alto = reflejo más pequeño y nítido
+    pub shininess: f32,
+    // Color del reflejo, independiente del albedo; None usa el albedo
+    pub color: Option<Color>,
+    // Reflejo estirado tipo metal cepillado en lugar del redondo de Blinn-Phong
+    pub anisotropic: Option<Anisotropic>,
+}
+
+impl Default for Specular {
+    fn default() -> Self {
+        Specular {
+            strength: 0.5,
+            shininess: 32.0,
+            color: None,
+            anisotropic: None,
+        }
+    }
+}
+
+// Rugosidad a lo largo de la tangente (los círculos alrededor del eje Y, como
+// las estrías de un anillo) y a través de ella. Con along > across el brillo
+// se alarga siguiendo la tangente; iguales dan un reflejo redondo
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Anisotropic {
+    pub roughness_along: f32,
+    pub roughness_across: f32,
+}
+
+impl Default for Anisotropic {
+    fn default() -> Self {
+        Anisotropic {
+            roughness_along: 0.5,
+            roughness_across: 0.08,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outline {
+    // Fresnel a partir del cual se dibuja el borde: más bajo = contorno más grueso
+    pub threshold: f32,
+    pub color: Color,
+}
+
+impl Default for Outline {
+    fn default() -> Self {
+        Outline {
+            threshold: 0.75,
+            color: Color::new(10, 10, 20),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bump {
+    // Cuánto se inclina la normal por unidad de gradiente del ruido
+    pub strength: f32,
+    // Escala del ruido del relieve: más alto = rugosidad más fina
+    pub scale: f32,
+}
+
+impl Default for Bump {
+    fn default() -> Self {
+        Bump {
+            strength: 0.15,
+            scale: 8.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cutout {
+    // Escala del ruido de los huecos
+    pub scale: f32,
+    // Ruido por debajo del cual la superficie desaparece
+    pub threshold: f32,
+    // Ancho de la transición de alfa alrededor del umbral
+    pub softness: f32,
+}
+
+impl Default for Cutout {
+    fn default() -> Self {
+        Cutout {
+            scale: 4.0,
+            threshold: 0.0,
+            softness: 0.15,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Earthlike {
+    // Altura (en el rango del ruido, ~[-1, 1]) por debajo de la cual hay océano
+    pub sea_level: f32,
+    // Ancho de la mezcla entre océano y tierra alrededor del nivel del mar
+    pub shoreline: f32,
+    // Latitud (en radianes) a partir de la cual empieza el hielo
+    pub ice_latitude: f32,
+}
+
+impl Default for Earthlike {
+    fn default() -> Self {
+        Earthlike {
+            sea_level: 0.0,
+            shoreline: 0.05,
+            ice_latitude: 1.1,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_cellular_ramp() -> ColorRamp {
+    CELULAR_RAMP
+}
+
+pub const CELULAR_RAMP: ColorRamp = ColorRamp::new(&[
+    (0.0, Color::new(85, 107, 47)),
+    (0.1, Color::new(124, 252, 0)),
+    (0.3, Color::new(34, 139, 34)),
+    (0.5, Color::new(173, 255, 47)),
+]);
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IceCaps {
+    pub color: Color,
+    // Latitud (en radianes) a partir de la cual empieza el hielo
+    pub start_lat: f32,
+    // Ancho (en radianes) de la transición hacia el color del hielo
+    pub softness: f32,
+}
+
+impl Default for IceCaps {
+    fn default() -> Self {
+        IceCaps {
+            color: Color::new(240, 248, 255),
+            start_lat: PI / 3.0,
+            softness: 0.15,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LatitudeTint {
+    pub equator: Color,
+    pub pole: Color,
+    // 0 = sin tinte, 1 = tinte completo
+    pub strength: f32,
+}
+
+impl Default for LatitudeTint {
+    fn default() -> Self {
+        LatitudeTint {
+            equator: Color::new(255, 200, 150),
+            pole: Color::new(150, 190, 255),
+            strength: 0.6,
+        }
+    }
+}
+
+// Aproximación barata de dispersión subsuperficial (materiales cerosos)
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subsurface {
+    // Cuánto pasa la luz más allá del terminador (0 = difusa normal)
+    pub wrap: f32,
+    // Color que se ve en la franja iluminada solo gracias al wrap
+    pub translucency: Color,
+}
+
+impl Default for Subsurface {
+    fn default() -> Self {
+        Subsurface {
+            wrap: 0.4,
+            translucency: Color::new(90, 160, 200),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Contours {
+    // Número de curvas en el rango completo del ruido
+    pub lines: f32,
+    pub color: Color,
+    // Medio ancho de cada curva, en fracción del espacio entre curvas
+    pub thickness: f32,
+}
+
+impl Default for Contours {
+    fn default() -> Self {
+        Contours {
+            lines: 12.0,
+            color: Color::new(30, 20, 10),
+            thickness: 0.06,
+        }
+    }
+}
+
+// Duraciones en segundos, contadas desde time = 0
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Envelope {
+    pub fade_in: f32,
+    pub hold: f32,
+    pub fade_out: f32,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope {
+            fade_in: 2.0,
+            hold: 4.0,
+            fade_out: 2.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CloudCoverage {
+    // Fracción del planeta cubierta por nubes, de 0 a 1
+    pub coverage: f32,
+    // Amplitud efectiva del ruido, para normalizarlo antes del umbral
+    pub noise_range: f32,
+    // Ancho de la transición en el borde de las nubes
+    pub softness: f32,
+}
+
+impl Default for CloudCoverage {
+    fn default() -> Self {
+        CloudCoverage {
+            coverage: 0.5,
+            noise_range: 0.5,
+            softness: 0.05,
+        }
+    }
+}
+
+impl Default for ShaderParams {
+    fn default() -> Self {
+        ShaderParams {
+            limb_darkening: 0.0,
+            spot_density: 15.0,
+            spot_threshold: 0.2,
+            spot_color: Color::new(139, 69, 19),
+            spot_base_color: Color::new(210, 105, 30),
+            spot_invert: false,
+            ice_caps: None,
+            latitude_tint: None,
+            linear_blending: false,
+            tint_by_vertex_color: false,
+            phase_offset: 0.0,
+            cellular_ramp: CELULAR_RAMP,
+            reflection_color: None,
+            cutout: None,
+            earthlike: Earthlike::default(),
+            outline: None,
+            threshold_dither: 0.0,
+            specular: None,
+            normal_space: NormalSpace::World,
+            volumetric_clouds: None,
+            cloud_shadows: None,
+            subsurface: None,
+            contours: None,
+            envelope: None,
+            cloud_coverage: None,
+            spot_light: None,
+            shadows: None,
+            bump: None,
+            normal_blend: 1.0,
+            intensity_curve: 1.0,
+            detail: 1.0,
+            sol_noise_weight: 0.5,
+            sol_noise_offset: 1000.0,
+        }
+    }
+}
+
+// Planetas disponibles, en el orden de las teclas 0-9 (Terrestre va con el 0
+// del teclado numérico)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShaderKind {
+    Neon,
+    Raro,
+    Saturno,
+    Azul,
+    Celular,
+    Mancha,
+    Sol,
+    Rocoso,
+    Gaseoso,
+    Arcilla,
+    Terrestre,
+}
+
+impl ShaderKind {
+    pub const ALL: [ShaderKind; 11] = [
+        ShaderKind::Neon,
+        ShaderKind::Raro,
+        ShaderKind::Saturno,
+        ShaderKind::Azul,
+        ShaderKind::Celular,
+        ShaderKind::Mancha,
+        ShaderKind::Sol,
+        ShaderKind::Rocoso,
+        ShaderKind::Gaseoso,
+        ShaderKind::Arcilla,
+        ShaderKind::Terrestre,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShaderKind::Neon => "Planeta neón",
+            ShaderKind::Raro => "Planeta raro",
+            ShaderKind::Saturno => "Planeta Saturno",
+            ShaderKind::Azul => "Planeta azul",
+            ShaderKind::Celular => "Planeta celular",
+            ShaderKind::Mancha => "Planeta con manchas",
+            ShaderKind::Sol => "Sol",
+            ShaderKind::Rocoso => "Planeta rocoso",
+            ShaderKind::Gaseoso => "Planeta gaseoso",
+            ShaderKind::Arcilla => "Planeta de arcilla",
+            ShaderKind::Terrestre => "Planeta terrestre",
+        }
+    }
+
+    // Distancia de la cámara al centro con la que se aprecia bien el detalle
+    // del shader. Los de ruido muy ampliado (zoom de cientos o miles) se ven
+    // como estática a la distancia por defecto (5), así que conviene acercarse;
+    // los de bandas amplias se ven mejor con el planeta completo
+    pub fn recommended_camera_distance(&self) -> f32 {
+        match self {
+            ShaderKind::Neon
+            | ShaderKind::Raro
+            | ShaderKind::Saturno
+            | ShaderKind::Azul
+            | ShaderKind::Mancha => 5.0,
+            ShaderKind::Gaseoso => 4.0,
+            ShaderKind::Celular | ShaderKind::Arcilla | ShaderKind::Terrestre => 3.5,
+            ShaderKind::Sol | ShaderKind::Rocoso => 3.0,
+        }
+    }
+
+    // Si el shader depende de uniforms.time. Hoy todos los planetas se animan
+    // (bandas, pulsaciones o ruido desplazado), pero un shader estático debe
+    // devolver false para que la interfaz ignore el tiempo
+    pub fn is_animated(&self) -> bool {
+        match self {
+            ShaderKind::Neon
+            | ShaderKind::Raro
+            | ShaderKind::Saturno
+            | ShaderKind::Azul
+            | ShaderKind::Celular
+            | ShaderKind::Mancha
+            | ShaderKind::Sol
+            | ShaderKind::Rocoso
+            | ShaderKind::Gaseoso
+            | ShaderKind::Arcilla
+            | ShaderKind::Terrestre => true,
+        }
+    }
+}
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     let position = Vec4::new(
@@ -29,10 +536,7 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
 
     let screen_position = uniforms.viewport_matrix * transformed_position;
 
-    let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
-    let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
-
-    let transformed_normal = normal_matrix * vertex.normal;
+    let transformed_normal = normal_matrix(uniforms) * vertex.normal;
 
     Vertex {
         position: vertex.position,
@@ -44,22 +548,534 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     }
 }
 
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, current_shader: u8) -> Color {
+// Inversa transpuesta del modelo; en NormalSpace::View incluye también la vista
+fn normal_matrix(uniforms: &Uniforms) -> Mat3 {
+    let model_mat3 = mat4_to_mat3(&uniforms.model_matrix);
+    let normal_matrix = model_mat3.transpose().try_inverse().unwrap_or(Mat3::identity());
+
+    match uniforms.params.normal_space {
+        NormalSpace::World => normal_matrix,
+        NormalSpace::View => mat4_to_mat3(&uniforms.view_matrix) * normal_matrix,
+    }
+}
+
+// Dirección de la luz en el mismo espacio que las normales
+pub fn shading_light_dir(uniforms: &Uniforms) -> Vec3 {
+    match uniforms.params.normal_space {
+        NormalSpace::World => uniforms.light_dir,
+        NormalSpace::View => mat4_to_mat3(&uniforms.view_matrix) * uniforms.light_dir,
+    }
+}
+
+// Posición del fragmento en espacio de objeto, para muestrear ruido. Mezclar
+// coordenadas de pantalla (como fragment.depth) hace que el patrón se deslice
+// sobre la esfera al mover la cámara y se corte en el borde del framebuffer;
+// en espacio de objeto el patrón queda pegado a la malla y gira con ella
+fn object_position(fragment: &Fragment) -> Vec3 {
+    fragment.vertex_position
+}
+
+fn world_position(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    let position = uniforms.model_matrix * Vec4::new(
+        fragment.vertex_position.x,
+        fragment.vertex_position.y,
+        fragment.vertex_position.z,
+        1.0
+    );
+    Vec3::new(position.x, position.y, position.z)
+}
+
+// Dirección del fragmento hacia la cámara
+fn view_dir(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    let world = world_position(fragment, uniforms);
+    match uniforms.params.normal_space {
+        NormalSpace::World => (uniforms.camera_pos - world).normalize(),
+        // En espacio de vista la cámara está en el origen
+        NormalSpace::View => {
+            let view = uniforms.view_matrix * Vec4::new(world.x, world.y, world.z, 1.0);
+            -Vec3::new(view.x, view.y, view.z).normalize()
+        }
+    }
+}
+
+// Normal vuelta hacia la cámara, para iluminar las dos caras de geometría
+// delgada (anillos): una cara trasera recibe la misma luz que su espejo frontal
+pub fn facing_normal(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    if dot(&fragment.normal, &view_dir(fragment, uniforms)) < 0.0 {
+        -fragment.normal
+    } else {
+        fragment.normal
+    }
+}
+
+// Desplazamiento del ruido derivado de la semilla (splitmix64); la semilla 0 no desplaza
+fn seed_offset(seed: u64) -> Vec3 {
+    if seed == 0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        (z >> 40) as f32 / (1u64 << 24) as f32 * 2000.0 - 1000.0
+    };
+
+    Vec3::new(next(), next(), next())
+}
+
+// Latitud en radianes [-PI/2, PI/2] de un punto sobre la esfera (mapeo esférico)
+pub fn latitude(position: &Vec3) -> f32 {
+    let radius = position.magnitude();
+    if radius == 0.0 {
+        return 0.0;
+    }
+    (position.y / radius).clamp(-1.0, 1.0).asin()
+}
+
+fn blend(a: &Color, b: &Color, t: f32, params: &ShaderParams) -> Color {
+    if params.linear_blending {
+        a.lerp_linear(b, t)
+    } else {
+        a.lerp(b, t)
+    }
+}
+
+const BAYER_4X4: [[f32; 4]; 4] = [
+    [ 0.0,  8.0,  2.0, 10.0],
+    [12.0,  4.0, 14.0,  6.0],
+    [ 3.0, 11.0,  1.0,  9.0],
+    [15.0,  7.0, 13.0,  5.0],
+];
+
+// Desplazamiento ordenado (Bayer 4x4) según la posición en pantalla, en
+// [-amount/2, amount/2). Sumado al ruido, convierte los bordes de banda en un punteado
+fn threshold_dither(fragment: &Fragment, amount: f32) -> f32 {
+    if amount == 0.0 {
+        return 0.0;
+    }
+    let x = fragment.position.x as usize % 4;
+    let y = fragment.position.y as usize % 4;
+    ((BAYER_4X4[y][x] + 0.5) / 16.0 - 0.5) * amount
+}
+
+// Escala de coordenadas de un shader. Cada shader conserva su escala base
+// (10, 500, 1000...), que es la que da su aspecto con detail = 1
+fn detail_zoom(base: f32, params: &ShaderParams) -> f32 {
+    base * params.detail
+}
+
+// Posición de muestreo escalada por detail, para los shaders que suman
+// desplazamientos (pulsación, una segunda capa lejana) antes del zoom: así
+// detail = 2 en p muestrea exactamente lo mismo que detail = 1 en 2p
+fn detail_position(position: Vec3, params: &ShaderParams) -> Vec3 {
+    position * params.detail
+}
+
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge1 <= edge0 {
+        return if x < edge0 { 0.0 } else { 1.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Multiplica base por un color que va de equator (latitud 0) a pole (±90°)
+pub fn latitude_tint(base: Color, latitude: f32, equator: Color, pole: Color, strength: f32) -> Color {
+    let t = (latitude.abs() / (PI / 2.0)).clamp(0.0, 1.0);
+    let zone = equator.lerp(&pole, t);
+    base.lerp(&base.blend_multiply(&zone), strength.clamp(0.0, 1.0))
+}
+
+// Curvas de nivel: pinta con contours.color los fragmentos cuyo ruido (en
+// [-1, 1]) queda cerca de uno de los niveles; el resto no cambia
+pub fn contour_overlay(base: Color, noise_value: f32, contours: &Contours) -> Color {
+  let level = (noise_value * 0.5 + 0.5) * contours.lines;
+  let fraction = level - level.floor();
+  let distance = fraction.min(1.0 - fraction);
+
+  if distance < contours.thickness {
+      contours.color
+  } else {
+      base
+  }
+}
+
+fn with_contours(base: Color, noise_value: f32, params: &ShaderParams) -> Color {
+  match params.contours {
+      Some(contours) => contour_overlay(base, noise_value, &contours),
+      None => base,
+  }
+}
+
+pub fn ice_cap_blend(base: Color, latitude: f32, cap_color: Color, start_lat: f32, softness: f32) -> Color {
+    let t = smoothstep(start_lat, start_lat + softness, latitude.abs());
+    base.lerp(&cap_color, t)
+}
+
+pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, current_shader: ShaderKind) -> Color {
+  let albedo = fragment_albedo(fragment, uniforms, current_shader);
+  apply_lighting(albedo, fragment, uniforms, current_shader)
+}
+
+// Ilumina el albedo y aplica los modificadores que van después de la luz
+pub fn apply_lighting(albedo: Color, fragment: &Fragment, uniforms: &Uniforms, current_shader: ShaderKind) -> Color {
+  let bumped;
+  let fragment = match uniforms.params.bump {
+      Some(bump) => {
+          bumped = bump_fragment(fragment, uniforms, &bump);
+          &bumped
+      }
+      None => fragment,
+  };
+  // Los términos de luz se suman en flotante y se recortan una sola vez
+  let diffuse = match (current_shader, uniforms.params.subsurface) {
+      (ShaderKind::Arcilla, Some(subsurface)) => subsurface_lighting(albedo, fragment, uniforms, &subsurface).scale_hdr(1.0),
+      _ => albedo.scale_hdr(fragment_lighting(fragment, uniforms, current_shader)),
+  };
+  let diffuse = match uniforms.params.shadows {
+      Some(settings) if !settings.shadow_color.is_black() => {
+          let shadowed = apply_intensity_curve(fragment.intensity, uniforms.params.intensity_curve) * (1.0 - shadow_visibility(fragment, uniforms));
+          diffuse + albedo.blend_multiply(&settings.shadow_color).scale_hdr(shadowed)
+      }
+      _ => diffuse,
+  };
+  let diffuse = match uniforms.params.spot_light {
+      Some(spot) => {
+          let light = spot_diffuse(&spot, fragment, uniforms);
+          let visibility = spot_shadow_visibility(&spot, fragment, uniforms);
+          diffuse + albedo.scale_hdr(light * visibility) + albedo.blend_multiply(&spot.shadow_color).scale_hdr(light * (1.0 - visibility))
+      }
+      None => diffuse,
+  };
+  // Las nubes de la capa también sombrean la superficie que tienen debajo
+  let diffuse = match (current_shader, uniforms.params.volumetric_clouds, uniforms.params.cloud_shadows) {
+      (ShaderKind::Gaseoso | ShaderKind::Terrestre, Some(clouds), Some(shadows)) => diffuse * cloud_shadow(fragment, uniforms, &clouds, &shadows),
+      _ => diffuse,
+  };
+  let lit = match uniforms.params.specular {
+      Some(specular) => diffuse + specular_term(albedo, fragment, uniforms, &specular).scale_hdr(1.0),
+      None => diffuse,
+  };
+  // Balance de blancos y exposición actúan sobre el valor lineal, antes del
+  // único recorte de from_hdr
+  let lit = Color::from_hdr(lit.component_mul(&(uniforms.white_balance * uniforms.exposure)));
+  let lit = match (current_shader, uniforms.params.reflection_color) {
+      (ShaderKind::Arcilla, Some(sky)) => ocean_reflection(lit, fragment, uniforms, sky),
+      _ => lit,
+  };
+  let lit = match (current_shader, uniforms.params.volumetric_clouds) {
+      (ShaderKind::Gaseoso | ShaderKind::Terrestre, Some(clouds)) => {
+          let (cloud, alpha) = volumetric_cloud_shader(fragment, uniforms, &clouds);
+          lit.lerp(&cloud, alpha)
+      }
+      _ => lit,
+  };
+
+  let lit = match uniforms.params.outline {
+      Some(outline) => toon_outline(lit, fragment, uniforms, &outline),
+      None => lit,
+  };
+
+  match uniforms.params.envelope {
+      Some(envelope) => lit * time_envelope(uniforms.time, envelope.fade_in, envelope.hold, envelope.fade_out),
+      None => lit,
+  }
+}
+
+// Opacidad del fragmento según params.cutout: 0 en los huecos, 1 en la
+// superficie y una transición suave entre ambos; 1 sin recorte
+pub fn cutout_alpha(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  match uniforms.params.cutout {
+      Some(cutout) => {
+          let p = object_position(fragment) * detail_zoom(cutout.scale, &uniforms.params);
+          let noise = uniforms.noise.get_noise_3d(p.x, p.y, p.z);
+          smoothstep(cutout.threshold - cutout.softness, cutout.threshold + cutout.softness, noise)
+      }
+      None => 1.0,
+  }
+}
+
+// Normal entre la geométrica (blend 0) y la perturbada (blend 1)
+pub fn blend_normal(geometric: &Vec3, perturbed: &Vec3, blend: f32) -> Vec3 {
+  let mixed = geometric.lerp(perturbed, blend.clamp(0.0, 1.0));
+  if mixed.magnitude() > 0.0 { mixed.normalize() } else { *geometric }
+}
+
+// Copia del fragmento con la normal inclinada según el gradiente del ruido
+// (diferencias centrales en espacio de objeto) y la difusa recalculada
+fn bump_fragment(fragment: &Fragment, uniforms: &Uniforms, bump: &Bump) -> Fragment {
+  let zoom = detail_zoom(bump.scale, &uniforms.params);
+  let height = |p: Vec3| uniforms.noise.get_noise_3d(p.x * zoom, p.y * zoom, p.z * zoom);
+  let epsilon = 0.5 / zoom.max(f32::EPSILON);
+  let p = object_position(fragment);
+  let gradient = Vec3::new(
+      height(p + Vec3::new(epsilon, 0.0, 0.0)) - height(p - Vec3::new(epsilon, 0.0, 0.0)),
+      height(p + Vec3::new(0.0, epsilon, 0.0)) - height(p - Vec3::new(0.0, epsilon, 0.0)),
+      height(p + Vec3::new(0.0, 0.0, epsilon)) - height(p - Vec3::new(0.0, 0.0, epsilon)),
+  ) / (2.0 * epsilon);
+
+  // Solo cuenta la parte del gradiente tangente a la superficie
+  let normal = fragment.normal;
+  let gradient = normal_matrix(uniforms) * gradient;
+  let tangent_gradient = gradient - normal * dot(&gradient, &normal);
+  let perturbed = (normal - tangent_gradient * bump.strength).normalize();
+
+  let mut bumped = fragment.clone();
+  bumped.normal = blend_normal(&normal, &perturbed, uniforms.params.normal_blend);
+  bumped.intensity = dot(&bumped.normal, &shading_light_dir(uniforms).normalize()).clamp(0.0, 1.0);
+  bumped
+}
+
+// Multiplicador en [0, 1]: sube de 0 a 1 durante fade_in, se mantiene en 1
+// durante hold y baja a 0 durante fade_out; 0 antes de empezar y al terminar
+pub fn time_envelope(time: f32, fade_in: f32, hold: f32, fade_out: f32) -> f32 {
+  if time < 0.0 {
+      return 0.0;
+  }
+  let fade_out_start = fade_in + hold;
+  if time < fade_in {
+      smoothstep(0.0, fade_in, time)
+  } else if time <= fade_out_start {
+      1.0
+  } else {
+      1.0 - smoothstep(fade_out_start, fade_out_start + fade_out, time)
+  }
+}
+
+// Aporte del foco, calculado en el mismo espacio que las normales
+fn spot_diffuse(spot: &SpotLight, fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  let world = world_position(fragment, uniforms);
+  match uniforms.params.normal_space {
+      NormalSpace::World => spot.diffuse(&world, &fragment.normal),
+      NormalSpace::View => {
+          let to_view = |point: Vec3| {
+              let view = uniforms.view_matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+              Vec3::new(view.x, view.y, view.z)
+          };
+          let direction = mat4_to_mat3(&uniforms.view_matrix) * spot.direction;
+          spot.transformed(to_view(spot.position), direction).diffuse(&to_view(world), &fragment.normal)
+      }
+  }
+}
+
+// Luz de la fuente principal que llega al fragmento según el mapa de sombras;
+// 1 si las sombras están desactivadas
+fn shadow_visibility(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  match (&uniforms.shadow_map, uniforms.params.shadows) {
+      (Some(map), Some(settings)) => map.visibility(&world_position(fragment, uniforms), &settings),
+      _ => 1.0,
+  }
+}
+
+// Igual para el foco: 1 si no proyecta sombras o si están desactivadas
+fn spot_shadow_visibility(spot: &SpotLight, fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  match (&uniforms.spot_shadow_map, uniforms.params.shadows) {
+      (Some(map), Some(settings)) if spot.casts_shadow => map.visibility(&world_position(fragment, uniforms), &settings),
+      _ => 1.0,
+  }
+}
+
+// Difusa envolvente: (n·l + wrap) / (1 + wrap). Con wrap 0 es la difusa
+// normal; con wrap > 0 la luz llega un poco más allá de los 90°
+pub fn wrap_diffuse(normal: &Vec3, light_dir: &Vec3, wrap: f32) -> f32 {
+  let wrap = wrap.max(0.0);
+  ((dot(normal, light_dir) + wrap) / (1.0 + wrap)).clamp(0.0, 1.0)
+}
+
+fn subsurface_lighting(albedo: Color, fragment: &Fragment, uniforms: &Uniforms, subsurface: &Subsurface) -> Color {
+  let light_dir = shading_light_dir(uniforms).normalize();
+  let wrapped = wrap_diffuse(&fragment.normal, &light_dir, subsurface.wrap);
+  let direct = dot(&fragment.normal, &light_dir).max(0.0);
+  albedo * wrapped + subsurface.translucency * (wrapped - direct).max(0.0)
+}
+
+// Reflejo de Blinn-Phong; se suma a la difusa y usa su propio color
+fn specular_term(albedo: Color, fragment: &Fragment, uniforms: &Uniforms, specular: &Specular) -> Color {
+  let light_dir = shading_light_dir(uniforms).normalize();
+  if dot(&fragment.normal, &light_dir) <= 0.0 {
+      return Color::black();
+  }
+
+  let half = (light_dir + view_dir(fragment, uniforms)).normalize();
+  let highlight = match specular.anisotropic {
+      Some(anisotropic) => anisotropic_highlight(fragment, uniforms, &half, &anisotropic),
+      None => dot(&fragment.normal, &half).max(0.0).powf(specular.shininess),
+  } * shadow_visibility(fragment, uniforms);
+  specular.color.unwrap_or(albedo) * (specular.strength * highlight)
+}
+
+// Lóbulo de Ward: exp(-((h·t / αt)² + (h·b / αb)²) / (h·n)²). La tangente no
+// viene en los vértices; sale del círculo alrededor del eje Y que pasa por el
+// punto, en espacio de objeto, llevado al espacio de la normal
+fn anisotropic_highlight(fragment: &Fragment, uniforms: &Uniforms, half: &Vec3, anisotropic: &Anisotropic) -> f32 {
+  let normal = fragment.normal;
+  let n_dot_h = dot(&normal, half);
+  if n_dot_h <= 0.0 {
+      return 0.0;
+  }
+
+  let p = object_position(fragment);
+  let tangent = normal_matrix(uniforms) * Vec3::new(p.z, 0.0, -p.x);
+  let tangent = tangent - normal * dot(&tangent, &normal);
+  if tangent.magnitude() <= f32::EPSILON {
+      // En el eje no hay dirección de estría: reflejo redondo
+      return n_dot_h.powf(2.0 / anisotropic.roughness_across.max(0.01).powi(2));
+  }
+  let tangent = tangent.normalize();
+  let bitangent = normal.cross(&tangent);
+
+  let along = dot(half, &tangent) / anisotropic.roughness_along.max(0.01);
+  let across = dot(half, &bitangent) / anisotropic.roughness_across.max(0.01);
+  (-(along * along + across * across) / (n_dot_h * n_dot_h)).exp()
+}
+
+// Recorre el rayo de vista desde la superficie hasta salir de la capa de
+// nubes, acumulando opacidad (Beer-Lambert) y la luz de cada muestra.
+// Devuelve el color de la nube y su opacidad en [0, 1]
+pub fn volumetric_cloud_shader(fragment: &Fragment, uniforms: &Uniforms, clouds: &VolumetricClouds) -> (Color, f32) {
+  let inverse_model = uniforms.model_matrix.try_inverse().unwrap_or(Mat4::identity());
+  let camera = inverse_model * Vec4::new(uniforms.camera_pos.x, uniforms.camera_pos.y, uniforms.camera_pos.z, 1.0);
+  let origin = fragment.vertex_position;
+  let direction = (Vec3::new(camera.x, camera.y, camera.z) - origin).normalize();
+
+  // Distancia hasta la esfera exterior de la capa
+  let outer_radius = origin.magnitude() * (1.0 + clouds.thickness);
+  let b = dot(&origin, &direction);
+  let c = origin.magnitude_squared() - outer_radius * outer_radius;
+  let path = -b + (b * b - c).max(0.0).sqrt();
+
+  let steps = clouds.steps.max(1);
+  let step_length = path / steps as f32;
+  let light_dir = uniforms.light_dir.normalize();
+
+  let mut transmittance = 1.0;
+  let mut light = 0.0;
+  for step in 0..steps {
+      let sample = origin + direction * (step_length * (step as f32 + 0.5));
+      let density = cloud_density(&sample, uniforms, clouds);
+
+      let absorbed = 1.0 - (-density * step_length / clouds.thickness.max(f32::EPSILON)).exp();
+      let lighting = 0.3 + 0.7 * dot(&sample.normalize(), &light_dir).max(0.0);
+      light += transmittance * absorbed * lighting;
+      transmittance *= 1.0 - absorbed;
+  }
+
+  let alpha = 1.0 - transmittance;
+  if alpha <= 0.0 {
+      return (Color::black(), 0.0);
+  }
+  (clouds.color * (light / alpha), alpha)
+}
+
+// Densidad de la capa de nubes en un punto de espacio de objeto
+fn cloud_density(point: &Vec3, uniforms: &Uniforms, clouds: &VolumetricClouds) -> f32 {
+  let zoom = detail_zoom(150.0, &uniforms.params);
+  let p = animate_coord(point * zoom, Vec3::new(0.3, 0.0, 0.0), uniforms.time);
+  let noise = uniforms.noise.get_noise_3d(p.x, p.y, p.z);
+  (noise * 0.5 + 0.5 - 0.4).max(0.0) * clouds.density
+}
+
+// Luz que dejan pasar las nubes hasta la superficie: se mide la densidad en
+// mitad de la capa, en el punto que cruza el rayo hacia la luz, promediando
+// cinco muestras repartidas en un radio de softness para suavizar el borde.
+// 1 bajo cielo despejado, 1 - strength bajo una nube opaca
+fn cloud_shadow(fragment: &Fragment, uniforms: &Uniforms, clouds: &VolumetricClouds, shadows: &CloudShadows) -> f32 {
+  let surface = object_position(fragment);
+  let light_dir = uniforms.light_dir.normalize();
+  let layer = clouds.thickness * surface.magnitude();
+  let center = surface + light_dir * (layer * 0.5);
+
+  // Dos ejes perpendiculares a la luz para repartir las muestras
+  let side = if light_dir.y.abs() > 0.99 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+  let u = light_dir.cross(&side).normalize() * (layer * shadows.softness);
+  let v = light_dir.cross(&u);
+  let taps = [center, center + u, center - u, center + v, center - v];
+  let density = taps.iter().map(|tap| cloud_density(tap, uniforms, clouds)).sum::<f32>() / taps.len() as f32;
+
+  let opacity = 1.0 - (-density).exp();
+  1.0 - shadows.strength.clamp(0.0, 1.0) * opacity
+}
+
+// Término de Fresnel simple: 0 mirando de frente a la cámara, 1 en la silueta
+fn fresnel(fragment: &Fragment, uniforms: &Uniforms) -> f32 {
+  1.0 - dot(&fragment.normal, &view_dir(fragment, uniforms)).clamp(0.0, 1.0)
+}
+
+// Reflejo barato del entorno: mezcla hacia el color del cielo con Fresnel²,
+// nada de frente a la cámara y casi todo en la silueta
+fn ocean_reflection(color: Color, fragment: &Fragment, uniforms: &Uniforms, sky: Color) -> Color {
+  let fresnel = fresnel(fragment, uniforms);
+  color.lerp(&sky, fresnel * fresnel)
+}
+
+// Contorno tipo cel shading: los fragmentos cercanos a la silueta toman el color del borde
+fn toon_outline(color: Color, fragment: &Fragment, uniforms: &Uniforms, outline: &Outline) -> Color {
+  if fresnel(fragment, uniforms) > outline.threshold {
+      outline.color
+  } else {
+      color
+  }
+}
+
+// Color base del shader antes de aplicar la iluminación (albedo del G-buffer)
+pub fn fragment_albedo(fragment: &Fragment, uniforms: &Uniforms, current_shader: ShaderKind) -> Color {
+  let albedo = base_albedo(fragment, uniforms, current_shader);
+  let albedo = if uniforms.params.tint_by_vertex_color { albedo.blend_multiply(&fragment.vertex_color) } else { albedo };
+  let albedo = match &uniforms.texture {
+      Some(texture) => albedo.blend_multiply(&texture.sample_trilinear(fragment.tex_coords.x, fragment.tex_coords.y, fragment.texture_lod)),
+      None => albedo,
+  };
+  let latitude = latitude(&fragment.vertex_position);
+
+  let albedo = match uniforms.params.latitude_tint {
+      Some(tint) => latitude_tint(albedo, latitude, tint.equator, tint.pole, tint.strength),
+      None => albedo,
+  };
+
+  match uniforms.params.ice_caps {
+      Some(caps) => ice_cap_blend(albedo, latitude, caps.color, caps.start_lat, caps.softness),
+      None => albedo,
+  }
+}
+
+fn base_albedo(fragment: &Fragment, uniforms: &Uniforms, current_shader: ShaderKind) -> Color {
   match current_shader {
-      0 => planeta_neon(fragment, uniforms),
-      1 => planeta_raro(fragment, uniforms),
-      2 => planeta_saturno(fragment, uniforms),
-      3 => planeta_azul(fragment, uniforms),
-      4 => planeta_celular(fragment, uniforms),
-      5 => planeta_mancha(fragment, uniforms),
-      6 => sol(fragment, uniforms),
-      7 => planeta_rocoso(fragment, uniforms),
-      8 => planeta_gaseoso(fragment, uniforms),
-      9 => planeta_arcilla(fragment, uniforms),
-      _ => planeta_mancha(fragment, uniforms),
+      ShaderKind::Neon => planeta_neon(fragment, uniforms),
+      ShaderKind::Raro => planeta_raro(fragment, uniforms),
+      ShaderKind::Saturno => planeta_saturno(fragment, uniforms),
+      ShaderKind::Azul => planeta_azul(fragment, uniforms),
+      ShaderKind::Celular => planeta_celular(fragment, uniforms),
+      ShaderKind::Mancha => planeta_mancha(fragment, uniforms),
+      ShaderKind::Sol => sol(fragment, uniforms),
+      ShaderKind::Rocoso => planeta_rocoso(fragment, uniforms),
+      ShaderKind::Gaseoso => planeta_gaseoso(fragment, uniforms),
+      ShaderKind::Arcilla => planeta_arcilla(fragment, uniforms),
+      ShaderKind::Terrestre => earthlike_shader(fragment, uniforms),
   }
 }
 
+fn fragment_lighting(fragment: &Fragment, uniforms: &Uniforms, current_shader: ShaderKind) -> f32 {
+  let intensity = apply_intensity_curve(fragment.intensity, uniforms.params.intensity_curve) * shadow_visibility(fragment, uniforms);
+  match current_shader {
+      ShaderKind::Rocoso => {
+          let light_dir = Vec3::new(1.0, 1.0, 0.5).normalize(); 
+          let diffuse_intensity = dot(&light_dir, &fragment.normal).max(0.0);
+          (0.6 + 0.4 * diffuse_intensity) * intensity
+      }
+      _ => intensity,
+  }
+}
+
+// intensity^exponent: > 1 hace más brusca la caída hacia la sombra, < 1 la
+// suaviza; 0 y 1 no cambian con ningún exponente
+pub fn apply_intensity_curve(intensity: f32, exponent: f32) -> f32 {
+  intensity.clamp(0.0, 1.0).powf(exponent.max(f32::EPSILON))
+}
+
 
 
 fn planeta_raro(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -71,15 +1087,12 @@ fn planeta_raro(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
     let position = fragment.vertex_position;
 
-    let t = uniforms.time as f32 * 0.04; 
+    let t = uniforms.time * 2.4; 
     let swirl = (position.x * 10.0 + position.y * 10.0 + t).sin(); 
 
-    let noise_zoom = 7.0;
-    let noise_value = uniforms.noise.get_noise_3d(
-        position.x * noise_zoom,
-        position.y * noise_zoom,
-        position.z * noise_zoom + t,
-    ).abs(); 
+    let noise_zoom = detail_zoom(7.0, &uniforms.params);
+    let p = animate_coord(position * noise_zoom, Vec3::new(0.0, 0.0, 2.4), uniforms.time);
+    let noise_value = uniforms.noise.get_noise_3d(p.x, p.y, p.z).abs(); 
 
     let wave_value = (position.y * 12.0 + swirl * 5.0).sin();
 
@@ -88,7 +1101,7 @@ fn planeta_raro(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let threshold_3 = 0.2;
     let threshold_4 = 0.6;
 
-    let base_color = if wave_value < threshold_1 {
+    if wave_value < threshold_1 {
         color_1.lerp(&color_2, noise_value)
     } else if wave_value < threshold_2 {
         color_2.lerp(&color_3, noise_value)
@@ -98,9 +1111,7 @@ fn planeta_raro(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         color_4.lerp(&color_5, noise_value)
     } else {
         color_5.lerp(&color_1, noise_value)
-    };
-
-    base_color * fragment.intensity
+    }
 }
   
 fn planeta_saturno(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -112,10 +1123,10 @@ fn planeta_saturno(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
   let position = fragment.vertex_position;
 
-  let t = uniforms.time as f32 * 0.02; 
+  let t = uniforms.time * 1.2; 
   let pulsate = (t * 0.5).sin() * 0.5; 
 
-  let zoom = 10.0; 
+  let zoom = detail_zoom(10.0, &uniforms.params); 
   let bands_value = ((position.y * zoom) + pulsate).sin(); 
 
   let threshold_1 = -0.8;
@@ -123,7 +1134,7 @@ fn planeta_saturno(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let threshold_3 = 0.0;
   let threshold_4 = 0.4;
 
-  let base_color = if bands_value < threshold_1 {
+  if bands_value < threshold_1 {
       color_1
   } else if bands_value < threshold_2 {
       color_2
@@ -133,9 +1144,7 @@ fn planeta_saturno(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       color_4
   } else {
       color_5
-  };
-
-  base_color * fragment.intensity
+  }
 }
   
 fn planeta_azul(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -150,10 +1159,10 @@ fn planeta_azul(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
     let position = fragment.vertex_position;
 
-    let t = uniforms.time as f32 * 0.02;
+    let t = uniforms.time * 1.2;
     let pulsate = (t * 0.5).sin() * 0.5; 
 
-    let zoom = 15.0; 
+    let zoom = detail_zoom(15.0, &uniforms.params); 
     let bands_value = ((position.y * zoom) + pulsate).sin(); 
 
     let threshold_1 = -0.8;
@@ -166,7 +1175,7 @@ fn planeta_azul(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let threshold_8 = 0.6;
 
     // Asignar colores basados en el valor de las bandas
-    let base_color = if bands_value < threshold_1 {
+    if bands_value < threshold_1 {
         color_1
     } else if bands_value < threshold_2 {
         color_2
@@ -182,77 +1191,59 @@ fn planeta_azul(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         color_7
     } else {
         color_8
-    };
-
-    base_color * fragment.intensity
+    }
 }
   
 fn planeta_celular(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-  let ring_color_1 = Color::new(85, 107, 47);   
-  let ring_color_2 = Color::new(124, 252, 0);  
-  let ring_color_3 = Color::new(34, 139, 34);   
-  let ring_color_4 = Color::new(173, 255, 47);  
-
   let position = fragment.vertex_position;
 
-  let t = uniforms.time as f32 * 0.03; 
+  let t = uniforms.time * 1.8; 
   let pulsate = (t * 0.5).sin() * 0.2; 
 
-  let zoom = 600.0; 
-  let noise_value = uniforms.noise.get_noise_2d(
-      (position.x + pulsate) * zoom, 
-      position.z * zoom + t,         
-  ).abs();
-
-  let ring_threshold_1 = 0.1;
-  let ring_threshold_2 = 0.3;
-  let ring_threshold_3 = 0.5;
-  let ring_threshold_4 = 0.7;
-
-  let ring_color = if noise_value < ring_threshold_1 {
-      ring_color_1
-  } else if noise_value < ring_threshold_2 {
-      ring_color_2
-  } else if noise_value < ring_threshold_3 {
-      ring_color_3
-  } else {
-      ring_color_4
-  };
+  let zoom = detail_zoom(600.0, &uniforms.params); 
+  // Ruido 2D sobre (x, z): la z de p es la segunda coordenada
+  let p = animate_coord(Vec3::new((position.x + pulsate) * zoom, 0.0, position.z * zoom), Vec3::new(0.0, 0.0, 1.8), uniforms.time);
+  let noise_value = uniforms.noise.get_noise_2d(p.x, p.z).abs();
 
-  ring_color * fragment.intensity
+  with_contours(uniforms.params.cellular_ramp.step(noise_value), noise_value, &uniforms.params)
 }
 
   
 fn planeta_mancha(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let spot_color = Color::new(139, 69, 19);  
-    let rock_base_color = Color::new(210, 105, 30); 
+    let (spot_color, rock_base_color) = if uniforms.params.spot_invert {
+        (uniforms.params.spot_base_color, uniforms.params.spot_color)
+    } else {
+        (uniforms.params.spot_color, uniforms.params.spot_base_color)
+    };
     let highlight_color = Color::new(255, 140, 0); 
     let dot_color = Color::new(255, 222, 173); 
 
     let position = fragment.vertex_position;
 
-    let t = uniforms.time as f32 * 0.03;
+    let t = uniforms.time * 1.8 + uniforms.params.phase_offset;
     let pulsate = (t * 0.6).sin() * 0.5 + 0.5; 
 
-    let rock_zoom = 15.0; 
+    let rock_zoom = detail_zoom(15.0, &uniforms.params); 
     let rock_noise_value = uniforms.noise.get_noise_3d(
         position.x * rock_zoom,
         position.y * rock_zoom,
         position.z * rock_zoom,
     ).abs();
 
-    let spot_zoom = 15.0; 
+    let offset = seed_offset(uniforms.seed);
+
+    let spot_zoom = detail_zoom(uniforms.params.spot_density, &uniforms.params); 
     let spot_noise_value = uniforms.noise.get_noise_2d(
-        position.x * spot_zoom,
-        position.y * spot_zoom,
+        position.x * spot_zoom + offset.x,
+        position.y * spot_zoom + offset.y,
     ).abs(); 
 
-    let spot_threshold = 0.2 * pulsate; 
+    let spot_threshold = uniforms.params.spot_threshold * pulsate; 
 
-    let dots_zoom = 50.0;
+    let dots_zoom = detail_zoom(50.0, &uniforms.params);
     let dots_noise_value = uniforms.noise.get_noise_2d(
-        position.x * dots_zoom,
-        position.y * dots_zoom,
+        position.x * dots_zoom + offset.z,
+        position.y * dots_zoom + offset.x,
     ).abs(); 
 
     let dots_threshold = 0.05; 
@@ -263,13 +1254,11 @@ fn planeta_mancha(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         rock_base_color.lerp(&highlight_color, rock_noise_value)
     };
  
-    let final_color = if dots_noise_value < dots_threshold {
+    if dots_noise_value < dots_threshold {
         dot_color  
     } else {
         base_color  
-    };
- 
-    final_color * fragment.intensity
+    }
 }
 
 
@@ -286,28 +1275,34 @@ fn sol(fragment: &Fragment, uniforms: &Uniforms) -> Color {
  
   let base_frequency = 0.5;  
   let pulsate_amplitude = 0.6;  
-  let t = uniforms.time as f32 * 0.02;  
+  let t = uniforms.time * 1.2 + uniforms.params.phase_offset;
  
   let pulsate = (t * base_frequency).sin() * pulsate_amplitude;
 
-  let zoom = 1000.0;  
+  let zoom = detail_zoom(1000.0, &uniforms.params);  
   let noise_value1 = uniforms.noise.get_noise_3d(
       position.x * zoom,
       position.y * zoom,
       (position.z + pulsate) * zoom,
   );
+  let offset = uniforms.params.sol_noise_offset;
   let noise_value2 = uniforms.noise.get_noise_3d(
-      (position.x + 1000.0) * zoom,
-      (position.y + 1000.0) * zoom,
-      (position.z + 1000.0 + pulsate) * zoom,
+      (position.x + offset) * zoom,
+      (position.y + offset) * zoom,
+      (position.z + offset + pulsate) * zoom,
   );
-  let noise_value = (noise_value1 + noise_value2) * 0.5;  
+  let weight = uniforms.params.sol_noise_weight.clamp(0.0, 1.0);
+  let noise_value = noise_value1 * weight + noise_value2 * (1.0 - weight);
  
   let blended_color = core_color
       .lerp(&mid_color, noise_value.abs())
       .lerp(&corona_color, (noise_value * 0.5 + 0.5).clamp(0.0, 1.0));
+
+  // Oscurecimiento de limbo: I = 1 - u * (1 - cos θ), θ entre normal y vista
+  let cos_theta = dot(&fragment.normal, &view_dir(fragment, uniforms)).clamp(0.0, 1.0);
+  let limb = 1.0 - uniforms.params.limb_darkening * (1.0 - cos_theta);
  
-  blended_color * fragment.intensity
+  blended_color * limb
 }
 
 fn planeta_rocoso(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -319,27 +1314,24 @@ fn planeta_rocoso(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let color_6 = Color::new(139, 69, 19);   
   let color_7 = Color::new(160, 82, 45);   
  
-  let position = Vec3::new(
+  let position = detail_position(Vec3::new(
       fragment.vertex_position.x,
       fragment.vertex_position.y,
       fragment.depth,
-  );
+  ), &uniforms.params);
  
-  let t = uniforms.time as f32 * 0.01; 
+  let t = uniforms.time * 0.6 + uniforms.params.phase_offset;
   let pulsate = (t * 0.5).sin() * 0.1;  
  
   let zoom = 1000.0;  
-  let noise_value1 = uniforms.noise.get_noise_3d(
-      (position.x + pulsate) * zoom,
-      (position.y + pulsate) * zoom,
-      position.z * zoom + t,  
-  );
-  let noise_value2 = uniforms.noise.get_noise_3d(
-      (position.x + 1000.0 + pulsate) * zoom,
-      (position.y + 1000.0 + pulsate) * zoom,
-      position.z * zoom + t, 
-  );
-  let noise_value = (noise_value1 + noise_value2) * 0.5; 
+  let scroll = Vec3::new(0.0, 0.0, 0.6);
+  let z = position.z * zoom + uniforms.params.phase_offset;
+  let p1 = animate_coord(Vec3::new((position.x + pulsate) * zoom, (position.y + pulsate) * zoom, z), scroll, uniforms.time);
+  let p2 = animate_coord(Vec3::new((position.x + 1000.0 + pulsate) * zoom, (position.y + 1000.0 + pulsate) * zoom, z), scroll, uniforms.time);
+  let noise_value1 = uniforms.noise.get_noise_3d(p1.x, p1.y, p1.z);
+  let noise_value2 = uniforms.noise.get_noise_3d(p2.x, p2.y, p2.z);
+  let noise_value = (noise_value1 + noise_value2) * 0.5;
+  let noise_value = noise_value + threshold_dither(fragment, uniforms.params.threshold_dither);
 
   let stone_threshold_1 = -0.4;
   let stone_threshold_2 = -0.2;
@@ -364,12 +1356,7 @@ fn planeta_rocoso(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       color_7
   };
  
-  let light_dir = Vec3::new(1.0, 1.0, 0.5).normalize(); 
-  let diffuse_intensity = dot(&light_dir, &fragment.normal).max(0.0);
- 
-  let final_color = base_color * (0.6 + 0.4 * diffuse_intensity);
-
-  final_color * fragment.intensity
+  with_contours(base_color, noise_value, &uniforms.params)
 }
 
 
@@ -377,37 +1364,79 @@ fn planeta_gaseoso(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let cloud_color = Color::new(255, 255, 255);  
   let fog_color = Color::new(120, 120, 120);   
 
-  let position = Vec3::new(
-      fragment.vertex_position.x,
-      fragment.vertex_position.y,
-      fragment.depth,
-  );
+  let position = object_position(fragment);
 
-  let t = uniforms.time as f32 * 0.01; 
+  let t = uniforms.time * 0.6; 
   let pulsate = (t * 0.3).sin() * 0.5; 
 
   let zoom = 200.0; 
-  let noise_value1 = uniforms.noise.get_noise_3d(
-      (position.x + pulsate) * zoom,
-      (position.y + pulsate) * zoom,
-      position.z * zoom + t, 
-  );
-  let noise_value2 = uniforms.noise.get_noise_3d(
-      (position.x - pulsate) * zoom,
-      (position.y - pulsate) * zoom,
-      position.z * zoom - t, 
-  );
+  let sample = detail_position(position, &uniforms.params);
+  // Las dos capas se desplazan en sentidos opuestos
+  let scroll = Vec3::new(0.0, 0.0, 0.6);
+  let p1 = animate_coord(Vec3::new((sample.x + pulsate) * zoom, (sample.y + pulsate) * zoom, sample.z * zoom), scroll, uniforms.time);
+  let p2 = animate_coord(Vec3::new((sample.x - pulsate) * zoom, (sample.y - pulsate) * zoom, sample.z * zoom), -scroll, uniforms.time);
+  let noise_value1 = uniforms.noise.get_noise_3d(p1.x, p1.y, p1.z);
+  let noise_value2 = uniforms.noise.get_noise_3d(p2.x, p2.y, p2.z);
   let noise_value = (noise_value1 + noise_value2) * 0.5; 
 
   let gradient = (1.0 - position.y.abs()).clamp(0.0, 1.0); 
 
-  let final_color = cloud_color
-      .lerp(&fog_color, noise_value.abs())
-      .lerp(&fog_color, 1.0 - gradient);
+  let clouds = match uniforms.params.cloud_coverage {
+      Some(coverage) => {
+          let normalized = remap_noise(noise_value, coverage.noise_range);
+          fog_color.lerp(&cloud_color, cloud_mask(normalized, coverage.coverage, coverage.softness))
+      }
+      None => cloud_color.lerp(&fog_color, noise_value.abs()),
+  };
+
+  clouds.lerp(&fog_color, 1.0 - gradient)
+}
+
+// Desplaza una coordenada de ruido a velocidad constante: base + velocity * time.
+// Es la convención común para animar el ruido de los shaders (el patrón se
+// mueve `velocity` unidades de ruido por segundo de uniforms.time)
+pub fn animate_coord(base: Vec3, velocity: Vec3, time: f32) -> Vec3 {
+  base + velocity * time
+}
 
-  final_color * fragment.intensity
+// Lleva el ruido de [-range, range] (su amplitud real, que suele ser menor que
+// 1) a [0, 1], para que los umbrales no dependan del tipo de ruido
+pub fn remap_noise(noise_value: f32, range: f32) -> f32 {
+  (noise_value / range.max(f32::EPSILON) * 0.5 + 0.5).clamp(0.0, 1.0)
 }
 
+// Cuánto de nube hay para un ruido normalizado en [0, 1]: coverage es la
+// fracción aproximada del cielo cubierta (0 = despejado, 1 = cubierto)
+pub fn cloud_mask(normalized_noise: f32, coverage: f32, softness: f32) -> f32 {
+  if coverage <= 0.0 {
+      return 0.0;
+  }
+  if coverage >= 1.0 {
+      return 1.0;
+  }
+  let threshold = 1.0 - coverage;
+  smoothstep(threshold - softness, threshold + softness, normalized_noise)
+}
+
+
+// Océano (el planeta de arcilla) bajo el nivel del mar, tierra (el rocoso)
+// sobre él y casquetes de hielo hacia los polos, todo a partir de una misma
+// altura de ruido en espacio de objeto
+pub fn earthlike_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+  let earth = uniforms.params.earthlike;
+  let position = object_position(fragment);
+  let zoom = 2.0;
+  let sample = detail_position(position, &uniforms.params);
+  let height = uniforms.noise.get_noise_3d(sample.x * zoom, sample.y * zoom, sample.z * zoom) * 0.7
+      + uniforms.noise.get_noise_3d(sample.x * zoom * 4.0, sample.y * zoom * 4.0, sample.z * zoom * 4.0) * 0.3;
+
+  let half_shore = earth.shoreline.max(0.0) * 0.5;
+  let land = smoothstep(earth.sea_level - half_shore, earth.sea_level + half_shore, height);
+  let surface = planeta_arcilla(fragment, uniforms).lerp(&planeta_rocoso(fragment, uniforms), land);
+
+  let caps = IceCaps::default();
+  ice_cap_blend(surface, latitude(&position), caps.color, earth.ice_latitude, caps.softness)
+}
 
 fn planeta_arcilla(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let color_1 = Color::new(173, 216, 230); 
@@ -422,21 +1451,18 @@ fn planeta_arcilla(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       fragment.depth,
   );
 
-  let t = uniforms.time as f32 * 0.02; 
+  let t = uniforms.time * 1.2; 
   let pulsate = (t * 0.3).sin() * 0.3; 
 
   let zoom = 500.0; 
-  let noise_value1 = uniforms.noise.get_noise_3d(
-      (position.x + pulsate) * zoom,
-      (position.y + pulsate) * zoom,
-      position.z * zoom + t, 
-  );
-  let noise_value2 = uniforms.noise.get_noise_3d(
-      (position.x - pulsate) * zoom,
-      (position.y - pulsate) * zoom,
-      position.z * zoom - t, 
-  );
-  let noise_value = (noise_value1 + noise_value2) * 0.5; 
+  let sample = detail_position(position, &uniforms.params);
+  let scroll = Vec3::new(0.0, 0.0, 1.2);
+  let p1 = animate_coord(Vec3::new((sample.x + pulsate) * zoom, (sample.y + pulsate) * zoom, sample.z * zoom), scroll, uniforms.time);
+  let p2 = animate_coord(Vec3::new((sample.x - pulsate) * zoom, (sample.y - pulsate) * zoom, sample.z * zoom), -scroll, uniforms.time);
+  let noise_value1 = uniforms.noise.get_noise_3d(p1.x, p1.y, p1.z);
+  let noise_value2 = uniforms.noise.get_noise_3d(p2.x, p2.y, p2.z);
+  let noise_value = (noise_value1 + noise_value2) * 0.5;
+  let noise_value = noise_value + threshold_dither(fragment, uniforms.params.threshold_dither);
 
   let gradient = (1.0 - position.y.abs()).clamp(0.0, 1.0); 
 
@@ -457,11 +1483,9 @@ fn planeta_arcilla(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       color_5
   };
 
-  let final_color = base_color
-      .lerp(&color_5, 1.0 - gradient) 
-      * fragment.intensity;
+  let final_color = blend(&base_color, &color_5, 1.0 - gradient, &uniforms.params);
 
-  final_color
+  with_contours(final_color, noise_value, &uniforms.params)
 }
 
 fn planeta_neon(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -473,10 +1497,10 @@ fn planeta_neon(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
   let position = fragment.vertex_position;
 
-  let t = uniforms.time as f32 * 0.04; 
+  let t = uniforms.time * 2.4; 
   let wave_movement = (position.x * 10.0 + position.y * 10.0 + t).sin(); 
 
-  let zoom = 10.0; 
+  let zoom = detail_zoom(10.0, &uniforms.params); 
   let wave_value = ((position.x * zoom) + wave_movement).sin(); 
 
   let threshold_1 = -0.8;
@@ -484,7 +1508,7 @@ fn planeta_neon(fragment: &Fragment, uniforms: &Uniforms) -> Color {
   let threshold_3 = 0.0;
   let threshold_4 = 0.4;
 
-  let base_color = if wave_value < threshold_1 {
+  if wave_value < threshold_1 {
       color_1
   } else if wave_value < threshold_2 {
       color_2
@@ -494,7 +1518,801 @@ fn planeta_neon(fragment: &Fragment, uniforms: &Uniforms) -> Color {
       color_4
   } else {
       color_5
-  };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use nalgebra_glm::Vec2;
+    use crate::noise::{ConstantNoise, LinearNoise, NoiseSource};
+    use crate::render::{benchmark_scene, create_model_matrix, create_view_matrix};
+    use crate::camera::Camera;
+    use crate::light::SpotLight;
+    use crate::shadow::ShadowMap;
+    use crate::vertex::Vertex;
+
+    // Uniforms de la escena fija (cámara en (0, 0, 3), luz hacia +z) con otro ruido
+    fn uniforms_with(noise: impl NoiseSource + 'static) -> Uniforms {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        uniforms.noise = Arc::new(noise);
+        uniforms
+    }
+
+    // Fragmento de la esfera unitaria en position (espacio de objeto) con esa normal
+    fn fragment_at(position: Vec3, normal: Vec3) -> Fragment {
+        Fragment::new(32.0, 32.0, Color::black(), 0.5, normal, 1.0, position)
+    }
+
+    fn channels(color: Color) -> [i32; 3] {
+        let hex = color.to_hex();
+        [(hex >> 16 & 0xFF) as i32, (hex >> 8 & 0xFF) as i32, (hex & 0xFF) as i32]
+    }
+
+    fn assert_close(a: Color, b: Color, tolerance: i32) {
+        let (a, b) = (channels(a), channels(b));
+        assert!(a.iter().zip(&b).all(|(x, y)| (x - y).abs() <= tolerance), "{:?} vs {:?}", a, b);
+    }
+
+    #[test]
+    fn limb_darkening_dims_the_edge_of_the_sun_by_its_coefficient() {
+        let mut uniforms = uniforms_with(ConstantNoise(0.0));
+        uniforms.params.limb_darkening = 0.6;
+        let center = fragment_albedo(&fragment_at(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0)), &uniforms, ShaderKind::Sol);
+        // En la silueta la normal es perpendicular a la vista: cos θ = 0
+        let limb = fragment_albedo(&fragment_at(Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)), &uniforms, ShaderKind::Sol);
+
+        assert!(center.luminance() > limb.luminance());
+        assert_close(limb, center * 0.4, 1);
+
+        // Con el coeficiente en 0 (el predeterminado) el disco es plano
+        uniforms.params.limb_darkening = 0.0;
+        let flat = fragment_albedo(&fragment_at(Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)), &uniforms, ShaderKind::Sol);
+        assert_eq!(flat, center);
+    }
+
+    // Fracción de una rejilla del hemisferio frontal que cae dentro de una mancha;
+    // las manchas van del marrón al naranja de la roca, así que su rojo queda bajo 210
+    fn spot_coverage(uniforms: &Uniforms) -> f32 {
+        let mut spots = 0;
+        let mut total = 0;
+        for i in 0..40 {
+            for j in 0..40 {
+                let x = i as f32 / 40.0 * 1.4 - 0.7;
+                let y = j as f32 / 40.0 * 1.4 - 0.7;
+                let position = Vec3::new(x, y, (1.0 - x * x - y * y).sqrt());
+                let color = fragment_albedo(&fragment_at(position, position), uniforms, ShaderKind::Mancha);
+                if channels(color)[0] < 210 {
+                    spots += 1;
+                }
+                total += 1;
+            }
+        }
+        spots as f32 / total as f32
+    }
+
+    #[test]
+    fn spot_coverage_depends_on_the_seed_and_repeats_for_the_same_one() {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        let first = spot_coverage(&uniforms);
+        assert!(first > 0.0);
+        assert_eq!(spot_coverage(&uniforms), first);
+
+        uniforms.seed = 7;
+        let other = spot_coverage(&uniforms);
+        assert!((first - other).abs() > 0.01, "{} vs {}", first, other);
+        assert_eq!(spot_coverage(&uniforms), other);
+    }
+
+    #[test]
+    fn ice_caps_leave_the_equator_alone_and_cover_the_pole() {
+        let mut uniforms = uniforms_with(ConstantNoise(0.3));
+        let equator = fragment_at(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+        let pole = fragment_at(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let bare = fragment_albedo(&equator, &uniforms, ShaderKind::Azul);
+
+        let caps = IceCaps::default();
+        uniforms.params.ice_caps = Some(caps);
+        assert_eq!(fragment_albedo(&equator, &uniforms, ShaderKind::Azul), bare);
+        assert_eq!(fragment_albedo(&pole, &uniforms, ShaderKind::Azul), caps.color);
+    }
+
+    #[test]
+    fn phase_offsets_shift_the_pulsation_at_the_same_time() {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        let fragments: Vec<Fragment> = (0..16)
+            .map(|i| {
+                let position = Vec3::new(i as f32 * 0.05 - 0.4, 0.1, 0.9).normalize();
+                fragment_at(position, position)
+            })
+            .collect();
+        let shade = |uniforms: &Uniforms| -> Vec<Color> {
+            fragments.iter().map(|fragment| fragment_albedo(fragment, uniforms, ShaderKind::Sol)).collect()
+        };
+
+        let in_phase = shade(&uniforms);
+        uniforms.params.phase_offset = 2.0;
+        assert_ne!(shade(&uniforms), in_phase);
+
+        // El desfase equivale a adelantar el reloj del sol (t = time * 1.2 + phase_offset)
+        uniforms.time = 0.0;
+        uniforms.params.phase_offset = 1.2;
+        assert_eq!(shade(&uniforms), in_phase);
+    }
+
+    // Albedo de una diagonal de fragmentos del hemisferio frontal
+    fn shade_diagonal(uniforms: &Uniforms, shader: ShaderKind) -> Vec<Color> {
+        (0..16)
+            .map(|i| {
+                let offset = i as f32 * 0.05 - 0.4;
+                let position = Vec3::new(offset, offset, 0.9).normalize();
+                fragment_albedo(&fragment_at(position, position), uniforms, shader)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shaders_marked_animated_change_over_time() {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        for shader in ShaderKind::ALL {
+            uniforms.time = 1.0;
+            let before = shade_diagonal(&uniforms, shader);
+            uniforms.time = 2.5;
+            let after = shade_diagonal(&uniforms, shader);
+            assert_eq!(before != after, shader.is_animated(), "{}", shader.name());
+        }
+    }
+
+    #[test]
+    fn the_default_cellular_ramp_keeps_the_old_bands_and_a_custom_one_recolors() {
+        let fragment = fragment_at(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+        // Umbrales y colores de las bandas que el shader tenía escritos a mano
+        let bands = [
+            (0.05, Color::new(85, 107, 47)),
+            (0.2, Color::new(124, 252, 0)),
+            (0.4, Color::new(34, 139, 34)),
+            (0.8, Color::new(173, 255, 47)),
+        ];
+        for (value, expected) in bands {
+            let uniforms = uniforms_with(ConstantNoise(value));
+            assert_eq!(fragment_albedo(&fragment, &uniforms, ShaderKind::Celular), expected, "{}", value);
+        }
+
+        const GRAY: ColorRamp = ColorRamp::new(&[(0.0, Color::new(10, 10, 10)), (0.5, Color::new(200, 200, 200))]);
+        let mut uniforms = uniforms_with(ConstantNoise(0.2));
+        uniforms.params.cellular_ramp = GRAY;
+        assert_eq!(fragment_albedo(&fragment, &uniforms, ShaderKind::Celular), Color::new(10, 10, 10));
+    }
+
+    #[test]
+    fn the_outline_colors_the_silhouette_but_not_the_front() {
+        let mut uniforms = uniforms_with(ConstantNoise(0.3));
+        let outline = Outline::default();
+        uniforms.params.outline = Some(outline);
+        let front = fragment_at(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+        let silhouette = fragment_at(Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(fragment_shader(&silhouette, &uniforms, ShaderKind::Azul), outline.color);
+        let outlined_front = fragment_shader(&front, &uniforms, ShaderKind::Azul);
+        assert_ne!(outlined_front, outline.color);
+
+        uniforms.params.outline = None;
+        assert_eq!(fragment_shader(&front, &uniforms, ShaderKind::Azul), outlined_front);
+    }
+
+    // Fracción de un bloque de 4x4 píxeles que el planeta rocoso pinta por
+    // encima del umbral 0 (color 4) con el ruido fijo en value
+    fn fraction_above_zero(value: f32, dither: f32) -> f32 {
+        let mut uniforms = uniforms_with(ConstantNoise(value));
+        uniforms.params.threshold_dither = dither;
+        let mut above = 0;
+        for y in 0..4 {
+            for x in 0..4 {
+                let mut fragment = fragment_at(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+                fragment.position = Vec2::new(x as f32, y as f32);
+                if fragment_albedo(&fragment, &uniforms, ShaderKind::Rocoso) == Color::new(188, 143, 143) {
+                    above += 1;
+                }
+            }
+        }
+        above as f32 / 16.0
+    }
+
+    #[test]
+    fn threshold_dither_spreads_the_band_edge_across_pixels() {
+        let values = [-0.15, -0.05, 0.05, 0.15];
+
+        // Sin tramado el bloque cambia de banda de golpe al cruzar el umbral
+        let hard: Vec<f32> = values.iter().map(|&value| fraction_above_zero(value, 0.0)).collect();
+        assert_eq!(hard, vec![0.0, 0.0, 1.0, 1.0]);
+
+        // Con tramado el cambio es gradual y cerca del umbral conviven las dos bandas
+        let dithered: Vec<f32> = values.iter().map(|&value| fraction_above_zero(value, 0.4)).collect();
+        assert!(dithered.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", dithered);
+        assert!(dithered[1] > 0.0 && dithered[2] < 1.0, "{:?}", dithered);
+    }
+
+    #[test]
+    fn a_constant_noise_makes_the_shader_output_predictable() {
+        // El planeta rocoso elige su banda solo por el ruido: con un valor fijo
+        // cualquier punto de la esfera sale del mismo color
+        let positions = [Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.6, 0.0, 0.8), Vec3::new(0.0, -0.8, 0.6)];
+        for (value, expected) in [(0.7, Color::new(245, 222, 179)), (-0.3, Color::new(139, 69, 19)), (-0.9, Color::new(160, 82, 45))] {
+            let uniforms = uniforms_with(ConstantNoise(value));
+            for position in positions {
+                assert_eq!(fragment_albedo(&fragment_at(position, position), &uniforms, ShaderKind::Rocoso), expected);
+            }
+        }
+    }
+
+    // Albedo de shader en position (normal radial) con el ruido de prueba dado
+    fn albedo_with(noise: impl NoiseSource + 'static, shader: ShaderKind, position: Vec3) -> Color {
+        fragment_albedo(&fragment_at(position, position.normalize()), &uniforms_with(noise), shader)
+    }
+
+    const FRONT: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    #[test]
+    fn the_neon_planet_ignores_the_noise_and_is_pinned_by_time() {
+        // t = 2.4: sin(0 + sin(2.4)) ≈ 0.63 cae en la última banda
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Neon, FRONT), Color::new(75, 0, 130));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Neon, FRONT), Color::new(75, 0, 130));
+    }
+
+    #[test]
+    fn the_odd_planet_is_pinned_under_a_linear_noise() {
+        // wave = sin(5 sin(2.4)) ≈ -0.23: mezcla del cian al verde según |ruido|;
+        // el ruido se muestrea en (0, 0, 7 + 2.4 t) = (0, 0, 9.4) -> 0.094
+        let noise = LinearNoise { scale: 0.01, offset: 0.0 };
+        assert_eq!(albedo_with(noise, ShaderKind::Raro, FRONT), Color::new(0, 255, 243));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Raro, FRONT), Color::new(0, 255, 191));
+    }
+
+    #[test]
+    fn the_saturn_planet_ignores_the_noise_and_is_pinned_by_time() {
+        // En el ecuador las bandas valen sin(0.5 sin(0.6)) ≈ 0.28: cuarta banda
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Saturno, FRONT), Color::new(153, 76, 0));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Saturno, FRONT), Color::new(153, 76, 0));
+    }
+
+    #[test]
+    fn the_blue_planet_ignores_the_noise_and_is_pinned_by_time() {
+        // Mismo valor de banda que Saturno (≈ 0.28), séptimo color de la escala
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Azul, FRONT), Color::new(0, 105, 148));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Azul, FRONT), Color::new(0, 105, 148));
+    }
+
+    #[test]
+    fn the_cellular_planet_is_pinned_under_a_constant_noise() {
+        // Usa |ruido| en la rampa: 0.25 y 0.5 caen en la segunda y la cuarta parada
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Celular, FRONT), Color::new(124, 252, 0));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Celular, FRONT), Color::new(173, 255, 47));
+    }
+
+    #[test]
+    fn the_spotted_planet_is_pinned_under_a_constant_noise() {
+        // El umbral de mancha pulsa a 0.2 * 0.94 < 0.25: roca con un cuarto del resalte
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Mancha, FRONT), Color::new(221, 114, 23));
+        // Con ruido 0.02 todo queda bajo el umbral de los puntos claros
+        assert_eq!(albedo_with(ConstantNoise(0.02), ShaderKind::Mancha, FRONT), Color::new(255, 222, 173));
+    }
+
+    #[test]
+    fn the_sun_is_pinned_under_a_constant_noise() {
+        // Núcleo -> medio con 0.25 y luego -> corona con 0.625
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Sol, FRONT), Color::new(255, 180, 56));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Sol, FRONT), Color::new(255, 214, 75));
+    }
+
+    #[test]
+    fn the_rocky_planet_is_pinned_under_a_constant_noise() {
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Rocoso, FRONT), Color::new(210, 180, 140));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Rocoso, FRONT), Color::new(160, 82, 45));
+    }
+
+    #[test]
+    fn the_gas_planet_is_pinned_under_a_linear_noise() {
+        // Las dos capas pulsan y se desplazan en sentidos opuestos, así que con un
+        // ruido lineal su promedio es 0.001 * 200 * (x + y + z)
+        let noise = || LinearNoise { scale: 0.001, offset: 0.0 };
+        assert_eq!(albedo_with(noise(), ShaderKind::Gaseoso, FRONT), Color::new(228, 228, 228));
+        assert_eq!(albedo_with(noise(), ShaderKind::Gaseoso, Vec3::new(0.6, 0.0, 0.8)), Color::new(217, 217, 217));
+        // Lejos del ecuador se funde con la niebla: 221 -> 120 en un 60 %
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Gaseoso, Vec3::new(0.0, 0.6, 0.8)), Color::new(160, 160, 160));
+    }
+
+    #[test]
+    fn the_clay_planet_is_pinned_under_a_constant_noise() {
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Arcilla, FRONT), Color::new(135, 206, 250));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Arcilla, FRONT), Color::new(0, 105, 148));
+    }
+
+    #[test]
+    fn the_earthlike_planet_is_pinned_under_a_constant_noise() {
+        // Sobre el nivel del mar es el rocoso, debajo el océano de arcilla y en el polo hielo
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Terrestre, FRONT), Color::new(210, 180, 140));
+        assert_eq!(albedo_with(ConstantNoise(-0.5), ShaderKind::Terrestre, FRONT), Color::new(0, 105, 148));
+        assert_eq!(albedo_with(ConstantNoise(0.25), ShaderKind::Terrestre, Vec3::new(0.0, 1.0, 0.0)), IceCaps::default().color);
+    }
+
+    #[test]
+    fn out_of_range_intensities_are_clamped_before_lighting() {
+        let uniforms = uniforms_with(ConstantNoise(0.25));
+        let base = albedo_with(ConstantNoise(0.25), ShaderKind::Azul, FRONT);
+        let lit = |intensity: f32| {
+            let mut fragment = fragment_at(FRONT, FRONT);
+            fragment.intensity = intensity;
+            fragment_shader(&fragment, &uniforms, ShaderKind::Azul)
+        };
+
+        assert_eq!(lit(1.5), base);
+        assert_eq!(lit(-0.2), Color::black());
+        // El constructor ya recorta la intensidad
+        assert_eq!(Fragment::new(0.0, 0.0, base, 0.5, FRONT, 1.5, FRONT).intensity, 1.0);
+    }
+
+    #[test]
+    fn the_highlight_uses_the_specular_color_and_the_diffuse_keeps_the_albedo() {
+        // De frente, con la luz y la cámara en +z, el vector medio es la normal: brillo 1
+        let mut uniforms = uniforms_with(ConstantNoise(0.25));
+        let fragment = fragment_at(FRONT, FRONT);
+        let albedo = fragment_albedo(&fragment, &uniforms, ShaderKind::Azul);
+        assert_eq!(fragment_shader(&fragment, &uniforms, ShaderKind::Azul), albedo);
+
+        uniforms.params.specular = Some(Specular { strength: 0.5, shininess: 32.0, color: Some(Color::new(0, 200, 0)), anisotropic: None });
+        assert_eq!(fragment_shader(&fragment, &uniforms, ShaderKind::Azul), Color::new(0, 205, 148));
+
+        uniforms.params.specular = Some(Specular { strength: 0.5, shininess: 32.0, color: Some(Color::new(200, 0, 0)), anisotropic: None });
+        assert_eq!(fragment_shader(&fragment, &uniforms, ShaderKind::Azul), Color::new(100, 105, 148));
+
+        // Sin color propio el reflejo toma el del albedo
+        uniforms.params.specular = Some(Specular { strength: 0.5, shininess: 32.0, color: None, anisotropic: None });
+        assert_eq!(fragment_shader(&fragment, &uniforms, ShaderKind::Azul), Color::new(0, 157, 222));
+    }
+
+    #[test]
+    fn in_view_space_a_normal_facing_the_camera_is_always_plus_z() {
+        let mut uniforms = uniforms_with(ConstantNoise(0.0));
+        uniforms.params.normal_space = NormalSpace::View;
+
+        for (eye, rotation) in [(Vec3::new(0.0, 0.0, 3.0), Vec3::zeros()), (Vec3::new(3.0, 1.0, 0.0), Vec3::new(0.4, 1.2, 0.0)), (Vec3::new(-2.0, 0.0, -2.0), Vec3::new(0.0, 2.5, 0.7))] {
+            uniforms.view_matrix = create_view_matrix(eye, Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+            uniforms.model_matrix = create_model_matrix(Vec3::zeros(), 1.0, rotation);
+
+            // Normal de objeto que, tras la rotación del modelo, mira hacia la cámara
+            let model = mat4_to_mat3(&uniforms.model_matrix);
+            let normal = model.try_inverse().unwrap() * eye.normalize();
+            let vertex = Vertex::new(normal, normal, Vec2::zeros());
+
+            let view_normal = vertex_shader(&vertex, &uniforms).transformed_normal.normalize();
+            assert!((view_normal - FRONT).magnitude() < 1e-4, "{:?}", view_normal);
+        }
+    }
 
-  base_color * fragment.intensity
+    #[test]
+    fn denser_clouds_are_more_opaque_and_zero_density_is_transparent() {
+        let uniforms = uniforms_with(ConstantNoise(0.6));
+        let fragment = fragment_at(FRONT, FRONT);
+        let alpha = |density: f32| {
+            let clouds = VolumetricClouds { density, ..VolumetricClouds::default() };
+            volumetric_cloud_shader(&fragment, &uniforms, &clouds).1
+        };
+
+        assert_eq!(alpha(0.0), 0.0);
+        let (thin, thick) = (alpha(1.0), alpha(6.0));
+        assert!(thin > 0.0 && thick > thin && thick < 1.0, "{} {}", thin, thick);
+    }
+
+    #[test]
+    fn the_sun_weight_selects_one_noise_sample_or_the_other() {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        let shade = |uniforms: &Uniforms, weight: f32, offset: f32| {
+            let mut uniforms = uniforms.clone();
+            uniforms.params.sol_noise_weight = weight;
+            uniforms.params.sol_noise_offset = offset;
+            shade_diagonal(&uniforms, ShaderKind::Sol)
+        };
+
+        // Con peso 1 solo cuenta la primera muestra: el desplazamiento de la segunda no importa
+        assert_eq!(shade(&uniforms, 1.0, 1000.0), shade(&uniforms, 1.0, 20.0));
+        // Con peso 0 solo cuenta la segunda; sin desplazamiento coincide con la primera
+        assert_ne!(shade(&uniforms, 0.0, 1000.0), shade(&uniforms, 0.0, 20.0));
+        assert_eq!(shade(&uniforms, 0.0, 0.0), shade(&uniforms, 1.0, 1000.0));
+
+        uniforms.time = 3.0;
+        assert_eq!(shade(&uniforms, 0.0, 0.0), shade(&uniforms, 1.0, 0.0));
+    }
+
+    #[test]
+    fn latitude_tint_uses_the_equator_color_at_zero_and_the_pole_color_at_ninety() {
+        let white = Color::new(255, 255, 255);
+        let equator = Color::new(255, 200, 120);
+        let pole = Color::new(120, 160, 255);
+
+        assert_eq!(latitude_tint(white, 0.0, equator, pole, 1.0), equator);
+        assert_eq!(latitude_tint(white, PI / 2.0, equator, pole, 1.0), pole);
+        assert_eq!(latitude_tint(white, -PI / 2.0, equator, pole, 1.0), pole);
+        assert_eq!(latitude_tint(white, PI / 4.0, equator, pole, 0.0), white);
+
+        // latitude da ±90° en los polos de la esfera
+        assert!((latitude(&Vec3::new(0.0, -2.0, 0.0)) + PI / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn wrapped_diffuse_reaches_slightly_past_ninety_degrees() {
+        let light_dir = Vec3::new(0.0, 0.0, 1.0);
+        // 100° desde la luz: n·l ≈ -0.17
+        let angle = 100.0f32.to_radians();
+        let normal = Vec3::new(angle.sin(), 0.0, angle.cos());
+
+        assert_eq!(wrap_diffuse(&normal, &light_dir, 0.0), 0.0);
+        assert!(wrap_diffuse(&normal, &light_dir, 0.5) > 0.0);
+        assert_eq!(wrap_diffuse(&light_dir, &light_dir, 0.5), 1.0);
+        assert_eq!(wrap_diffuse(&-light_dir, &light_dir, 0.5), 0.0);
+    }
+
+    // Cambios de color a lo largo del meridiano frontal (de polo a polo)
+    fn color_changes_along_meridian(uniforms: &Uniforms, shader: ShaderKind) -> usize {
+        let colors: Vec<Color> = (0..=400)
+            .map(|i| {
+                let angle = (i as f32 / 400.0 - 0.5) * PI;
+                let position = Vec3::new(0.0, angle.sin(), angle.cos());
+                fragment_albedo(&fragment_at(position, position), uniforms, shader)
+            })
+            .collect();
+        colors.windows(2).filter(|pair| pair[0] != pair[1]).count()
+    }
+
+    #[test]
+    fn detail_one_is_the_original_scale_and_two_halves_the_features() {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        assert_eq!(uniforms.params.detail, 1.0);
+        // Con el valor predeterminado cada shader usa su escala base, la de
+        // las salidas fijadas en las pruebas de cada shader de arriba
+        assert_eq!(detail_zoom(15.0, &uniforms.params), 15.0);
+
+        let original = color_changes_along_meridian(&uniforms, ShaderKind::Saturno);
+        uniforms.params.detail = 2.0;
+        let doubled = color_changes_along_meridian(&uniforms, ShaderKind::Saturno);
+        let ratio = doubled as f32 / original as f32;
+        assert!((1.7..=2.3).contains(&ratio), "{} -> {}", original, doubled);
+    }
+
+    #[test]
+    fn detail_two_at_p_samples_the_same_noise_as_detail_one_at_twice_p() {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        uniforms.time = 1.3;
+        // El rocoso y el océano toman la z de la profundidad, que también se dobla;
+        // en el ecuador el degradado por latitud es el mismo para p y 2p
+        let albedo = |uniforms: &Uniforms, position: Vec3, depth: f32, shader: ShaderKind| {
+            let fragment = Fragment::new(32.0, 32.0, Color::black(), depth, position.normalize(), 1.0, position);
+            fragment_albedo(&fragment, uniforms, shader)
+        };
+        for shader in [ShaderKind::Rocoso, ShaderKind::Gaseoso, ShaderKind::Terrestre] {
+            for position in [Vec3::new(0.31, 0.0, 0.95), Vec3::new(-0.6, 0.0, 0.8), Vec3::new(0.0013, 0.0, -0.0009)] {
+                uniforms.params.detail = 2.0;
+                let finer = albedo(&uniforms, position, 0.5, shader);
+                uniforms.params.detail = 1.0;
+                assert_eq!(finer, albedo(&uniforms, position * 2.0, 1.0, shader), "{:?} {:?}", shader, position);
+            }
+        }
+    }
+
+    #[test]
+    fn detail_one_reproduces_the_output_from_before_the_parameter() {
+        // Albedos con el ruido real tomados antes de que existiera detail (el
+        // terrestre, de cuando se agregó)
+        let positions = [Vec3::new(0.31, 0.42, 0.85), Vec3::new(-0.6, 0.1, 0.79), Vec3::new(0.28, -0.55, 0.79), Vec3::new(0.0007, -0.0011, 0.0013)];
+        let cases = [
+            (0.0, [[0xCD853F, 0x979797, 0x2978A7], [0xCD853F, 0xF0F0F0, 0x1B8CF4], [0xBC8F8F, 0xB2B2B2, 0x3D96C2], [0xDEB887, 0xFCFCFC, 0xC5C8B8]]),
+            (1.3, [[0x8B4513, 0x999999, 0x4EA4CF], [0x8B4513, 0xF1F1F1, 0x9CCDDE], [0xBC8F8F, 0xABABAB, 0x0E7BC4], [0x8B4513, 0xFCFCFC, 0x536B8C]]),
+        ];
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        for (time, expected) in cases {
+            uniforms.time = time;
+            for (position, expected) in positions.iter().zip(expected) {
+                let fragment = fragment_at(*position, position.normalize());
+                let albedos = [ShaderKind::Rocoso, ShaderKind::Gaseoso, ShaderKind::Terrestre]
+                    .map(|shader| fragment_albedo(&fragment, &uniforms, shader).to_hex());
+                assert_eq!(albedos, expected, "t = {} {:?}", time, position);
+            }
+        }
+    }
+
+    #[test]
+    fn contours_draw_thin_lines_at_their_levels_and_leave_the_rest() {
+        let base = Color::new(200, 200, 200);
+        let contours = Contours { lines: 4.0, color: Color::new(30, 20, 10), thickness: 0.05 };
+        // Barrido del ruido de -1 a 1: las curvas caen en -0.5, 0 y 0.5 (y los extremos)
+        let on_line: Vec<bool> = (0..=400)
+            .map(|i| contour_overlay(base, i as f32 / 200.0 - 1.0, &contours) == contours.color)
+            .collect();
+
+        for level in [-0.5f32, 0.0, 0.5] {
+            let index = ((level + 1.0) * 200.0).round() as usize;
+            assert!(on_line[index], "{}", level);
+        }
+        // Entre curvas el color base no cambia y cada curva es delgada
+        assert_eq!(contour_overlay(base, -0.25, &contours), base);
+        assert_eq!(contour_overlay(base, 0.25, &contours), base);
+        let drawn = on_line.iter().filter(|&&line| line).count();
+        assert!(drawn > 0 && drawn < on_line.len() / 5, "{}", drawn);
+    }
+
+    #[test]
+    fn the_envelope_fades_in_holds_and_fades_out() {
+        let envelope = |time: f32| time_envelope(time, 1.0, 2.0, 1.0);
+        assert_eq!(envelope(-0.5), 0.0);
+        assert_eq!(envelope(0.0), 0.0);
+        assert_eq!(envelope(0.5), 0.5);
+        assert_eq!(envelope(1.5), 1.0);
+        assert_eq!(envelope(3.0), 1.0);
+        assert_eq!(envelope(3.5), 0.5);
+        assert_eq!(envelope(4.5), 0.0);
+
+        // Transiciones suaves: sin saltos entre pasos pequeños
+        let samples: Vec<f32> = (0..=500).map(|i| envelope(i as f32 / 100.0)).collect();
+        assert!(samples.windows(2).all(|pair| (pair[0] - pair[1]).abs() < 0.02));
+    }
+
+    #[test]
+    fn an_exponent_of_two_darkens_mid_intensities_but_not_the_endpoints() {
+        assert_eq!(apply_intensity_curve(0.5, 2.0), 0.25);
+        assert!(apply_intensity_curve(0.5, 2.0) < apply_intensity_curve(0.5, 1.0));
+        for exponent in [0.5, 1.0, 2.0] {
+            assert_eq!(apply_intensity_curve(0.0, exponent), 0.0);
+            assert_eq!(apply_intensity_curve(1.0, exponent), 1.0);
+        }
+
+        // Dentro del shader: el mismo fragmento a media luz sale más oscuro
+        let mut uniforms = uniforms_with(ConstantNoise(0.25));
+        let mut fragment = fragment_at(FRONT, FRONT);
+        fragment.intensity = 0.5;
+        let linear = fragment_shader(&fragment, &uniforms, ShaderKind::Azul);
+        uniforms.params.intensity_curve = 2.0;
+        assert!(fragment_shader(&fragment, &uniforms, ShaderKind::Azul).luminance() < linear.luminance());
+    }
+
+    // Albedo del planeta gaseoso a lo largo del ecuador, donde no hay niebla de latitud
+    fn shade_equator(uniforms: &Uniforms) -> Vec<Color> {
+        (0..24)
+            .map(|i| {
+                let angle = i as f32 / 24.0 * PI - PI / 2.0;
+                let position = Vec3::new(angle.sin(), 0.0, angle.cos());
+                fragment_albedo(&fragment_at(position, position), uniforms, ShaderKind::Gaseoso)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_coverage_is_all_sky_and_full_coverage_is_all_cloud() {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        uniforms.params.cloud_coverage = Some(CloudCoverage { coverage: 0.0, ..CloudCoverage::default() });
+        assert!(shade_equator(&uniforms).iter().all(|&color| color == Color::new(120, 120, 120)));
+
+        uniforms.params.cloud_coverage = Some(CloudCoverage { coverage: 1.0, ..CloudCoverage::default() });
+        assert!(shade_equator(&uniforms).iter().all(|&color| color == Color::new(255, 255, 255)));
+
+        // En el medio hay de las dos
+        uniforms.params.cloud_coverage = Some(CloudCoverage::default());
+        let half = shade_equator(&uniforms);
+        assert!(half.iter().any(|&color| color.luminance() > 0.8) && half.iter().any(|&color| color.luminance() < 0.6));
+    }
+
+    #[test]
+    fn normal_blend_zero_keeps_the_geometric_normal_and_one_the_perturbed() {
+        let geometric = Vec3::new(0.0, 0.0, 1.0);
+        let perturbed = Vec3::new(0.6, 0.0, 0.8);
+        assert_eq!(blend_normal(&geometric, &perturbed, 0.0), geometric);
+        assert!((blend_normal(&geometric, &perturbed, 1.0) - perturbed).magnitude() < 1e-6);
+
+        // En medio queda normalizada y entre ambas
+        let half = blend_normal(&geometric, &perturbed, 0.5);
+        assert!((half.magnitude() - 1.0).abs() < 1e-6);
+        assert!(half.x > 0.0 && half.x < perturbed.x);
+        // Fuera de [0, 1] se recorta
+        assert_eq!(blend_normal(&geometric, &perturbed, -1.0), geometric);
+    }
+
+    #[test]
+    fn scrolling_through_animate_coord_keeps_the_shaders_pixel_exact() {
+        // Albedos con el ruido real en t = 1.3, tomados antes de pasar el
+        // desplazamiento de cada shader a animate_coord
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        uniforms.time = 1.3;
+        let cases = [
+            (Vec3::new(0.31, 0.42, 0.85), [0x999999, 0x556B2F, 0x4EA4CF, 0xFF7D3E]),
+            (Vec3::new(-0.6, 0.1, 0.79), [0xF1F1F1, 0x7CFC00, 0x9CCDDE, 0xFF7C7B]),
+            (Vec3::new(0.0007, -0.0011, 0.0013), [0xFCFCFC, 0x7CFC00, 0x1E90FF, 0x22EB86]),
+        ];
+        for (position, expected) in cases {
+            let fragment = fragment_at(position, position.normalize());
+            let albedos = [ShaderKind::Gaseoso, ShaderKind::Celular, ShaderKind::Arcilla, ShaderKind::Raro]
+                .map(|shader| fragment_albedo(&fragment, &uniforms, shader).to_hex());
+            assert_eq!(albedos, expected, "{:?}", position);
+        }
+    }
+
+    #[test]
+    fn the_ocean_reflects_the_sky_at_the_silhouette_but_not_in_front() {
+        let mut uniforms = uniforms_with(ConstantNoise(0.3));
+        let front = fragment_at(FRONT, FRONT);
+        let silhouette = fragment_at(Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let plain_front = fragment_shader(&front, &uniforms, ShaderKind::Arcilla);
+        let plain_silhouette = fragment_shader(&silhouette, &uniforms, ShaderKind::Arcilla);
+
+        let sky = Color::new(255, 0, 255);
+        uniforms.params.reflection_color = Some(sky);
+        assert_eq!(fragment_shader(&front, &uniforms, ShaderKind::Arcilla), plain_front);
+        assert_ne!(plain_silhouette, sky);
+        assert_eq!(fragment_shader(&silhouette, &uniforms, ShaderKind::Arcilla), sky);
+
+        // Solo el océano refleja
+        assert_ne!(fragment_shader(&silhouette, &uniforms, ShaderKind::Azul), sky);
+    }
+
+    #[test]
+    fn every_shader_recommends_a_positive_finite_distance() {
+        for shader in ShaderKind::ALL {
+            let distance = shader.recommended_camera_distance();
+            // Fuera del planeta de radio 1 para no meter la cámara dentro
+            assert!(distance.is_finite() && distance > 1.0, "{shader:?}: {distance}");
+
+            // Aplicada a la cámara, también con el ojo sobre el centro, queda a esa distancia
+            for eye in [Vec3::new(1.0, 2.0, 5.0), Vec3::zeros()] {
+                let mut camera = Camera::new(eye, Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0));
+                camera.set_distance(distance);
+                assert!((camera.eye.magnitude() - distance).abs() < 1e-4, "{shader:?}: {:?}", camera.eye);
+            }
+        }
+    }
+
+    #[test]
+    fn the_earthlike_planet_is_ocean_below_sea_level_land_above_and_mixed_at_the_shore() {
+        // Con ruido constante la altura es el propio valor del ruido
+        let shade = |value: f32| {
+            let uniforms = uniforms_with(ConstantNoise(value));
+            let fragment = fragment_at(FRONT, FRONT);
+            (earthlike_shader(&fragment, &uniforms), planeta_arcilla(&fragment, &uniforms), planeta_rocoso(&fragment, &uniforms))
+        };
+
+        let (deep, ocean, _) = shade(-0.5);
+        assert_eq!(deep, ocean);
+        let (high, _, land) = shade(0.5);
+        assert_eq!(high, land);
+
+        // Justo en el nivel del mar, mitad y mitad
+        let (shore, ocean, land) = shade(0.0);
+        assert_ne!(ocean, land);
+        assert_eq!(shore, ocean.lerp(&land, 0.5));
+    }
+
+    #[test]
+    fn spot_invert_swaps_the_spot_and_base_colors() {
+        // Ruido fijo en 0.2: por encima del umbral de los puntos, así no los pinta
+        let mut uniforms = uniforms_with(ConstantNoise(0.2));
+        let fragment = fragment_at(FRONT, FRONT);
+        let normal = fragment_albedo(&fragment, &uniforms, ShaderKind::Mancha);
+
+        uniforms.params.spot_invert = true;
+        let inverted = fragment_albedo(&fragment, &uniforms, ShaderKind::Mancha);
+        assert_ne!(inverted, normal);
+
+        // Igual que intercambiar los dos colores a mano
+        uniforms.params.spot_invert = false;
+        let (spot, base) = (uniforms.params.spot_color, uniforms.params.spot_base_color);
+        uniforms.params.spot_color = base;
+        uniforms.params.spot_base_color = spot;
+        assert_eq!(fragment_albedo(&fragment, &uniforms, ShaderKind::Mancha), inverted);
+    }
+
+    #[test]
+    fn the_anisotropic_highlight_stretches_along_the_tangent() {
+        let uniforms = uniforms_with(ConstantNoise(0.0));
+        // En (0, 0, 1) la tangente del círculo alrededor de Y es +x
+        let fragment = fragment_at(FRONT, FRONT);
+        let tilted = |x: f32, y: f32| Vec3::new(x, y, 1.0).normalize();
+        let highlight = |anisotropic: &Anisotropic, half: Vec3| anisotropic_highlight(&fragment, &uniforms, &half, anisotropic);
+
+        let brushed = Anisotropic::default();
+        let along = highlight(&brushed, tilted(0.2, 0.0));
+        let across = highlight(&brushed, tilted(0.0, 0.2));
+        assert!((highlight(&brushed, FRONT) - 1.0).abs() < 1e-6);
+        assert!(along > 0.5 && across < 0.01, "{along} {across}");
+
+        // Con la misma rugosidad en ambas direcciones el reflejo es redondo
+        let round = Anisotropic { roughness_along: 0.2, roughness_across: 0.2 };
+        assert!((highlight(&round, tilted(0.2, 0.0)) - highlight(&round, tilted(0.0, 0.2))).abs() < 1e-6);
+    }
+
+    #[test]
+    fn a_spot_that_casts_no_shadow_lights_an_occluded_fragment() {
+        let mut uniforms = uniforms_with(ConstantNoise(0.3));
+        // Un cuadrado en z = 2 tapa el punto (0, 0, 1) visto desde el foco en (0, 0, 3)
+        let corner = |x: f32, y: f32| Vertex::new_with_color(Vec3::new(x, y, 2.0), Color::black());
+        let occluder = [
+            corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0),
+            corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0),
+        ];
+        let mut map = ShadowMap::new(FRONT, 3.0, 64);
+        map.rasterize(&occluder, &Mat4::identity());
+        uniforms.spot_shadow_map = Some(Arc::new(map));
+        uniforms.params.shadows = Some(ShadowSettings::default());
+
+        let fragment = fragment_at(FRONT, FRONT);
+        let lit_by = |uniforms: &mut Uniforms, casts_shadow: bool| {
+            let spot = SpotLight { position: Vec3::new(0.0, 0.0, 3.0), direction: -FRONT, casts_shadow, ..SpotLight::default() };
+            uniforms.params.spot_light = Some(spot);
+            channels(fragment_shader(&fragment, uniforms, ShaderKind::Azul))
+        };
+
+        uniforms.params.spot_light = None;
+        let without_spot = channels(fragment_shader(&fragment, &uniforms, ShaderKind::Azul));
+        let shadowed = lit_by(&mut uniforms, true);
+        let unshadowed = lit_by(&mut uniforms, false);
+
+        // Con sombra el foco no llega; sin ella ilumina como si no hubiera nada delante
+        assert_eq!(shadowed, without_spot);
+        assert!(unshadowed.iter().sum::<i32>() > shadowed.iter().sum::<i32>(), "{:?} {:?}", unshadowed, shadowed);
+    }
+
+    #[test]
+    fn a_fragment_under_dense_cloud_is_darker_than_under_clear_sky() {
+        let clouds = VolumetricClouds::default();
+        let shadows = CloudShadows::default();
+        let fragment = fragment_at(FRONT, FRONT);
+        // El ruido fija la densidad de la capa: -0.5 la deja vacía y 0.8 muy densa
+        let light_through = |value: f32| cloud_shadow(&fragment, &uniforms_with(ConstantNoise(value)), &clouds, &shadows);
+
+        assert_eq!(light_through(-0.5), 1.0);
+        let dense = light_through(0.8);
+        assert!(dense < 0.5 && dense >= 1.0 - shadows.strength, "{dense}");
+
+        // En el shader completo la sombra solo oscurece la superficie bajo la nube
+        let shade = |value: f32, cloud_shadows: Option<CloudShadows>| {
+            let mut uniforms = uniforms_with(ConstantNoise(value));
+            uniforms.params.volumetric_clouds = Some(clouds);
+            uniforms.params.cloud_shadows = cloud_shadows;
+            channels(fragment_shader(&fragment, &uniforms, ShaderKind::Gaseoso)).iter().sum::<i32>()
+        };
+        assert_eq!(shade(-0.5, Some(shadows)), shade(-0.5, None));
+        assert!(shade(0.8, Some(shadows)) < shade(0.8, None));
+    }
+
+    #[test]
+    fn a_warm_white_balance_shifts_an_unclipped_fragment_toward_red() {
+        let fragment = fragment_at(FRONT, FRONT);
+        let shade = |white_balance: Vec3| {
+            let mut uniforms = uniforms_with(ConstantNoise(0.0));
+            uniforms.white_balance = white_balance;
+            channels(fragment_shader(&fragment, &uniforms, ShaderKind::Rocoso))
+        };
+        let neutral = shade(Vec3::new(1.0, 1.0, 1.0));
+        let warm = shade(Vec3::new(1.1, 1.0, 0.88));
+
+        // Ningún canal está recortado, así que la ganancia se ve entera
+        assert!(neutral.iter().all(|&channel| channel > 20 && channel < 200), "{:?}", neutral);
+        assert!(warm[0] > neutral[0] && warm[2] < neutral[2], "{:?} vs {:?}", warm, neutral);
+        assert_eq!(warm[1], neutral[1]);
+    }
+
+    #[test]
+    fn a_lower_exposure_recovers_highlights_that_clip_at_full_exposure() {
+        let fragment = fragment_at(FRONT, FRONT);
+        let shade = |gray: u8, exposure: f32| {
+            let mut uniforms = uniforms_with(ConstantNoise(0.0));
+            uniforms.params.specular = Some(Specular { strength: 1.0, shininess: 1.0, color: None, anisotropic: None });
+            uniforms.exposure = exposure;
+            channels(apply_lighting(Color::new(gray, gray, gray), &fragment, &uniforms, ShaderKind::Rocoso))
+        };
+
+        // Luz difusa más brillo: los dos grises se recortan al mismo blanco
+        assert_eq!(shade(200, 1.0), [255, 255, 255]);
+        assert_eq!(shade(240, 1.0), [255, 255, 255]);
+        // La exposición actúa antes del recorte, así que vuelven a distinguirse;
+        // aplicada sobre el frame ya cuantizado ambos darían el mismo gris
+        let (dim, bright) = (shade(200, 0.5), shade(240, 0.5));
+        assert!(bright[0] < 255 && dim[0] < bright[0], "{:?} {:?}", dim, bright);
+    }
 }
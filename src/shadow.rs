@@ -0,0 +1,181 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4, look_at, ortho};
+use crate::color::Color;
+use crate::vertex::Vertex;
+use crate::raster::{barycentric, covers, is_top_left, triangle_area_2d};
+
+// Suavizado de los bordes de sombra por filtrado de porcentaje cercano (PCF):
+// se comparan taps x taps muestras repartidas en un cuadrado de ±radius texels.
+// Más taps dan una penumbra más suave y más cara; radius fija su ancho
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShadowSettings {
+    // Lado del mapa de sombras en texels
+    pub resolution: usize,
+    // Muestras por eje del kernel PCF; 1 deja la sombra dura
+    pub taps: usize,
+    pub radius: f32,
+    // Margen de profundidad para que la superficie no se sombree a sí misma
+    pub bias: f32,
+    // Luz que queda en la sombra de la luz principal (negro = sombra normal);
+    // multiplica al albedo, así un azul da sombras frías estilizadas
+    pub shadow_color: Color,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            resolution: 512,
+            taps: 3,
+            radius: 1.5,
+            bias: 0.01,
+            shadow_color: Color::black(),
+        }
+    }
+}
+
+// Profundidad vista desde la luz direccional, con proyección ortográfica que
+// cubre una esfera de `extent` alrededor del origen
+pub struct ShadowMap {
+    size: usize,
+    depth: Vec<f32>,
+    light_view_projection: Mat4,
+}
+
+impl ShadowMap {
+    pub fn new(light_dir: Vec3, extent: f32, size: usize) -> Self {
+        let light_dir = light_dir.normalize();
+        let up = if light_dir.y.abs() > 0.99 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+        let view = look_at(&(light_dir * extent * 2.0), &Vec3::new(0.0, 0.0, 0.0), &up);
+        let projection = ortho(-extent, extent, -extent, extent, extent, extent * 3.0);
+
+        ShadowMap {
+            size: size.max(1),
+            depth: vec![f32::INFINITY; size.max(1) * size.max(1)],
+            light_view_projection: projection * view,
+        }
+    }
+
+    // Punto del mundo en coordenadas del mapa: texels en x, y y profundidad NDC en z
+    fn to_map(&self, world: &Vec3) -> Vec3 {
+        let clip = self.light_view_projection * Vec4::new(world.x, world.y, world.z, 1.0);
+        Vec3::new(
+            (clip.x * 0.5 + 0.5) * self.size as f32,
+            (0.5 - clip.y * 0.5) * self.size as f32,
+            clip.z,
+        )
+    }
+
+    // Agrega una malla (lista de triángulos) como proyectora de sombra
+    pub fn rasterize(&mut self, vertex_array: &[Vertex], model_matrix: &Mat4) {
+        for tri in vertex_array.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| {
+                let world = model_matrix * Vec4::new(tri[i].position.x, tri[i].position.y, tri[i].position.z, 1.0);
+                self.to_map(&Vec3::new(world.x, world.y, world.z))
+            });
+            self.rasterize_triangle(&a, &b, &c);
+        }
+    }
+
+    fn rasterize_triangle(&mut self, a: &Vec3, b: &Vec3, c: &Vec3) {
+        let area = triangle_area_2d(a, b, c);
+        if area == 0.0 {
+            return;
+        }
+
+        let last = self.size as f32 - 1.0;
+        let min_x = a.x.min(b.x).min(c.x).floor().clamp(0.0, last) as usize;
+        let min_y = a.y.min(b.y).min(c.y).floor().clamp(0.0, last) as usize;
+        let max_x = a.x.max(b.x).max(c.x).ceil().clamp(0.0, last) as usize;
+        let max_y = a.y.max(b.y).max(c.y).ceil().clamp(0.0, last) as usize;
+        let top_left = [is_top_left(b, c, area), is_top_left(c, a, area), is_top_left(a, b, area)];
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+                let weights = barycentric(&point, a, b, c);
+                if covers(weights.x, top_left[0]) && covers(weights.y, top_left[1]) && covers(weights.z, top_left[2]) {
+                    let depth = a.z * weights.x + b.z * weights.y + c.z * weights.z;
+                    let texel = &mut self.depth[y * self.size + x];
+                    *texel = texel.min(depth);
+                }
+            }
+        }
+    }
+
+    // Fracción de luz que llega a un punto del mundo: 1 iluminado, 0 en sombra
+    // y valores intermedios en la penumbra. Fuera del mapa no hay sombra
+    pub fn visibility(&self, world: &Vec3, settings: &ShadowSettings) -> f32 {
+        let point = self.to_map(world);
+        let taps = settings.taps.max(1);
+        let step = if taps > 1 { 2.0 * settings.radius / (taps - 1) as f32 } else { 0.0 };
+        let start = if taps > 1 { -settings.radius } else { 0.0 };
+
+        let mut lit = 0;
+        for j in 0..taps {
+            for i in 0..taps {
+                let x = (point.x + start + step * i as f32).floor();
+                let y = (point.y + start + step * j as f32).floor();
+                let occluder = if x < 0.0 || y < 0.0 || x >= self.size as f32 || y >= self.size as f32 {
+                    f32::INFINITY
+                } else {
+                    self.depth[y as usize * self.size + x as usize]
+                };
+                if point.z - settings.bias <= occluder {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f32 / (taps * taps) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::identity;
+
+    // Luz en una fila de puntos que cruza el borde de la sombra
+    fn edge_profile(taps: usize, radius: f32) -> Vec<f32> {
+        // Un cuadrado en z = 1 que tapa la mitad x < 0 del plano z = 0
+        let corner = |x: f32, y: f32| Vertex::new_with_color(Vec3::new(x, y, 1.0), Color::black());
+        let occluder = [
+            corner(-2.0, -2.0), corner(0.0, -2.0), corner(0.0, 2.0),
+            corner(-2.0, -2.0), corner(0.0, 2.0), corner(-2.0, 2.0),
+        ];
+        let mut map = ShadowMap::new(Vec3::new(0.0, 0.0, 1.0), 2.0, 64);
+        map.rasterize(&occluder, &identity());
+
+        let settings = ShadowSettings { taps, radius, ..ShadowSettings::default() };
+        (0..200).map(|i| map.visibility(&Vec3::new(-0.5 + i as f32 / 200.0, 0.3, 0.0), &settings)).collect()
+    }
+
+    // Puntos en penumbra y cuántos niveles de luz distintos hay entre ellos
+    fn penumbra(taps: usize, radius: f32) -> (usize, usize) {
+        let mut partial: Vec<f32> = edge_profile(taps, radius).into_iter().filter(|&light| light > 0.0 && light < 1.0).collect();
+        let width = partial.len();
+        partial.sort_by(f32::total_cmp);
+        partial.dedup();
+        (width, partial.len())
+    }
+
+    #[test]
+    fn more_pcf_taps_soften_the_shadow_edge() {
+        let profile = edge_profile(1, 3.0);
+        assert_eq!(profile.first(), Some(&0.0));
+        assert_eq!(profile.last(), Some(&1.0));
+
+        // Con un solo tap el borde es un escalón; con más aparece una penumbra
+        // cada vez más gradual
+        let (hard_width, _) = penumbra(1, 3.0);
+        let (soft_width, soft_levels) = penumbra(3, 3.0);
+        let (softer_width, softer_levels) = penumbra(5, 3.0);
+        assert_eq!(hard_width, 0);
+        assert!(soft_width > 0, "{soft_width}");
+        assert!(softer_levels > soft_levels, "{soft_levels} {softer_levels}");
+
+        // Los taps extremos caen en ±radius: un núcleo más grande (más taps
+        // sobre un radio mayor) ensancha la penumbra a ambos lados del borde
+        let (wider_width, _) = penumbra(7, 6.0);
+        assert!(wider_width > softer_width, "{softer_width} {wider_width}");
+    }
+}
@@ -0,0 +1,208 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::shaders::{fragment_shader, ShaderKind};
+use crate::Uniforms;
+
+// Muestra plana de un shader, sin la distorsión de la esfera: un quad que
+// llena la pantalla, mirando a la cámara, con posiciones planas en [-1, 1]
+pub fn render_shader_swatch(width: usize, height: usize, shader: ShaderKind, uniforms: &Uniforms) -> Vec<Color> {
+    let mut pixels = Vec::with_capacity(width * height);
+    let normal = Vec3::new(0.0, 0.0, 1.0);
+
+    for y in 0..height {
+        for x in 0..width {
+            let u = (x as f32 + 0.5) / width as f32;
+            let v = (y as f32 + 0.5) / height as f32;
+            let position = Vec3::new(u * 2.0 - 1.0, 1.0 - v * 2.0, 0.0);
+
+            let fragment = Fragment::new(
+                x as f32,
+                y as f32,
+                Color::black(),
+                0.0,
+                normal,
+                1.0,
+                position,
+            );
+            pixels.push(fragment_shader(&fragment, uniforms, shader));
+        }
+    }
+
+    pixels
+}
+
+// Columnas y filas de la hoja de contacto: cuadrícula casi cuadrada para todos los shaders
+fn contact_sheet_grid() -> (usize, usize) {
+    let count = ShaderKind::ALL.len();
+    let columns = (count as f32).sqrt().ceil() as usize;
+    (columns, count.div_ceil(columns))
+}
+
+// Tamaño en píxeles de la imagen que devuelve render_contact_sheet
+pub fn contact_sheet_size(cell_width: usize, cell_height: usize) -> (usize, usize) {
+    let (columns, rows) = contact_sheet_grid();
+    (columns * cell_width, rows * cell_height)
+}
+
+// Todos los shaders lado a lado, una celda por shader en el orden de
+// ShaderKind::ALL, cada una rotulada con la tecla que lo selecciona
+pub fn render_contact_sheet(cell_width: usize, cell_height: usize, uniforms: &Uniforms) -> Vec<Color> {
+    let (columns, _) = contact_sheet_grid();
+    let (width, height) = contact_sheet_size(cell_width, cell_height);
+    let mut sheet = vec![Color::black(); width * height];
+
+    for (index, shader) in ShaderKind::ALL.iter().enumerate() {
+        let origin_x = (index % columns) * cell_width;
+        let origin_y = (index / columns) * cell_height;
+        let cell = render_shader_swatch(cell_width, cell_height, *shader, uniforms);
+
+        for y in 0..cell_height {
+            let row = (origin_y + y) * width + origin_x;
+            sheet[row..row + cell_width].copy_from_slice(&cell[y * cell_width..(y + 1) * cell_width]);
+        }
+        // Dos dígitos (decenas y unidades): con más de 10 shaders un solo
+        // dígito repetiría rótulos
+        draw_digit(&mut sheet, width, origin_x + 4, origin_y + 4, index / 10 % 10);
+        draw_digit(&mut sheet, width, origin_x + 4 + LABEL_WIDTH, origin_y + 4, index % 10);
+    }
+
+    sheet
+}
+
+// Dígitos de 3x5 píxeles, una fila por byte (los 3 bits bajos, el más alto a la izquierda)
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+// Ancho en píxeles de un dígito con su margen (5 columnas a escala 2)
+const LABEL_WIDTH: usize = 10;
+
+// Rótulo blanco sobre fondo negro, a escala 2 para que se lea
+fn draw_digit(image: &mut [Color], width: usize, x: usize, y: usize, digit: usize) {
+    const SCALE: usize = 2;
+    for row in 0..7 {
+        for column in 0..5 {
+            // Margen de un píxel (en la escala del dígito) alrededor del glifo
+            let lit = (1..6).contains(&row)
+                && (1..4).contains(&column)
+                && DIGITS[digit][row - 1] & (0b100 >> (column - 1)) != 0;
+            let color = if lit { Color::new(255, 255, 255) } else { Color::black() };
+
+            for dy in 0..SCALE {
+                for dx in 0..SCALE {
+                    let (px, py) = (x + column * SCALE + dx, y + row * SCALE + dy);
+                    if px < width && py * width + px < image.len() {
+                        image[py * width + px] = color;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::noise::NoiseSource;
+    use crate::render::benchmark_scene;
+
+    // Ruido de la dálmata: la capa de roca (3D) plana para que cada mancha sea
+    // de un solo color, y las manchas y puntos (2D) del ruido real o fijos
+    struct SpotNoise(Option<Arc<dyn NoiseSource>>);
+
+    impl NoiseSource for SpotNoise {
+        fn get_noise_2d(&self, x: f32, y: f32) -> f32 {
+            self.0.as_ref().map_or(0.5, |noise| noise.get_noise_2d(x, y))
+        }
+
+        fn get_noise_3d(&self, _x: f32, _y: f32, _z: f32) -> f32 {
+            0.0
+        }
+    }
+
+    fn near(color: Color, target: [i32; 3]) -> bool {
+        let hex = color.to_hex() as i32;
+        let channels = [hex >> 16 & 0xFF, hex >> 8 & 0xFF, hex & 0xFF];
+        channels.iter().zip(target).all(|(channel, target)| (channel - target).abs() <= 8)
+    }
+
+    #[test]
+    fn the_dalmatian_swatch_is_black_inside_the_spots_and_white_outside() {
+        let (mut uniforms, _) = benchmark_scene(64, 64);
+        let real_noise = uniforms.noise.clone();
+        uniforms.params.spot_color = Color::black();
+        uniforms.params.spot_base_color = Color::new(255, 255, 255);
+        let swatch = |uniforms: &Uniforms| render_shader_swatch(64, 64, ShaderKind::Mancha, uniforms);
+
+        // Sin manchas ni puntos (ruido 2D fijo sobre los umbrales) es blanco liso
+        uniforms.noise = Arc::new(SpotNoise(None));
+        let plain = swatch(&uniforms);
+        assert!(plain.iter().all(|color| *color == plain[0]));
+        assert!(near(plain[0], [255, 255, 255]), "{:06X}", plain[0].to_hex());
+
+        // Con el ruido real, las manchas son los píxeles que desaparecen con
+        // spot_threshold = 0; el resto de la muestra no cambia
+        uniforms.noise = Arc::new(SpotNoise(Some(real_noise)));
+        let spotted = swatch(&uniforms);
+        uniforms.params.spot_threshold = 0.0;
+        let unspotted = swatch(&uniforms);
+        let (inside, outside): (Vec<_>, Vec<_>) = spotted.iter().zip(&unspotted).partition(|(spotted, unspotted)| spotted != unspotted);
+        assert!(!inside.is_empty() && !outside.is_empty(), "{} {}", inside.len(), outside.len());
+        for (spot, background) in inside {
+            assert!(near(*spot, [0, 0, 0]) && near(*background, [255, 255, 255]), "{:06X} {:06X}", spot.to_hex(), background.to_hex());
+        }
+        // Fuera de las manchas solo hay fondo blanco y los puntos claros
+        let dot = Color::new(255, 222, 173);
+        assert!(outside.iter().all(|(color, _)| near(**color, [255, 255, 255]) || **color == dot));
+        assert!(outside.iter().any(|(color, _)| near(**color, [255, 255, 255])));
+    }
+
+    #[test]
+    fn the_contact_sheet_has_one_varied_cell_per_shader() {
+        let (uniforms, _) = benchmark_scene(64, 64);
+        let (cell_width, cell_height) = (40, 30);
+        let sheet = render_contact_sheet(cell_width, cell_height, &uniforms);
+
+        // 11 shaders en una cuadrícula de 4 x 3
+        assert_eq!(contact_sheet_size(cell_width, cell_height), (160, 90));
+        assert_eq!(sheet.len(), 160 * 90);
+
+        for index in 0..ShaderKind::ALL.len() {
+            let (origin_x, origin_y) = ((index % 4) * cell_width, (index / 4) * cell_height);
+            // Debajo del rótulo (14 px de alto), la celda tiene más de un color
+            let first = sheet[(origin_y + 16) * 160 + origin_x];
+            let varied = (16..cell_height).any(|y| (0..cell_width).any(|x| sheet[(origin_y + y) * 160 + origin_x + x] != first));
+            assert!(varied, "{}", ShaderKind::ALL[index].name());
+        }
+    }
+
+    #[test]
+    fn tiles_zero_and_ten_get_different_labels() {
+        let (uniforms, _) = benchmark_scene(64, 64);
+        let (cell_width, cell_height) = (40, 30);
+        let sheet = render_contact_sheet(cell_width, cell_height, &uniforms);
+        // Los dos dígitos del rótulo ocupan 20 x 14 px desde (4, 4) de la celda
+        let label = |index: usize| -> Vec<Color> {
+            let (origin_x, origin_y) = ((index % 4) * cell_width, (index / 4) * cell_height);
+            (4..18).flat_map(|y| (4..24).map(move |x| (y, x)))
+                .map(|(y, x)| sheet[(origin_y + y) * 160 + origin_x + x])
+                .collect()
+        };
+
+        assert_ne!(label(0), label(10));
+        // Las unidades coinciden ("00" y "10"): solo cambian las decenas
+        let units = |label: &[Color]| -> Vec<Color> { label.chunks(20).flat_map(|row| row[10..].to_vec()).collect() };
+        assert_eq!(units(&label(0)), units(&label(10)));
+    }
+}
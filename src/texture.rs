@@ -0,0 +1,214 @@
+use nalgebra_glm::{Vec2, Vec3};
+use crate::color::Color;
+use crate::raster::triangle_area_2d;
+
+// Qué pasa con una coordenada fuera de [0, 1]: Repeat la envuelve, Clamp la
+// fija al texel del borde y Mirror la refleja en cada repetición
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapMode {
+    // Texel entero para el índice i (puede ser negativo o >= size)
+    fn texel_index(self, i: i64, size: usize) -> usize {
+        let size = size as i64;
+        let index = match self {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::Clamp => i.clamp(0, size - 1),
+            WrapMode::Mirror => {
+                let period = i.rem_euclid(2 * size);
+                if period < size { period } else { 2 * size - 1 - period }
+            }
+        };
+        index as usize
+    }
+}
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    data: Vec<Color>,
+}
+
+// Textura con su cadena de mipmaps; el nivel 0 es la imagen original
+pub struct Texture {
+    levels: Vec<MipLevel>,
+    // Modo de borde por eje (u, v), usado por sample y sample_trilinear
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, data: Vec<Color>) -> Self {
+        assert_eq!(data.len(), width * height, "texture data does not match its size");
+        Texture {
+            levels: vec![MipLevel { width, height, data }],
+            wrap_u: WrapMode::Repeat,
+            wrap_v: WrapMode::Repeat,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    // Genera los niveles reduciendo a la mitad (promedio de 2x2) hasta llegar a 1x1
+    pub fn build_mips(&mut self) {
+        self.levels.truncate(1);
+
+        while {
+            let last = self.levels.last().unwrap();
+            last.width > 1 || last.height > 1
+        } {
+            let previous = self.levels.last().unwrap();
+            let width = (previous.width / 2).max(1);
+            let height = (previous.height / 2).max(1);
+            let mut data = Vec::with_capacity(width * height);
+
+            for y in 0..height {
+                for x in 0..width {
+                    let x0 = (x * 2).min(previous.width - 1);
+                    let x1 = (x * 2 + 1).min(previous.width - 1);
+                    let y0 = (y * 2).min(previous.height - 1);
+                    let y1 = (y * 2 + 1).min(previous.height - 1);
+
+                    let top = previous.texel(x0, y0).lerp(&previous.texel(x1, y0), 0.5);
+                    let bottom = previous.texel(x0, y1).lerp(&previous.texel(x1, y1), 0.5);
+                    data.push(top.lerp(&bottom, 0.5));
+                }
+            }
+
+            self.levels.push(MipLevel { width, height, data });
+        }
+    }
+
+    pub fn set_wrap(&mut self, wrap_u: WrapMode, wrap_v: WrapMode) {
+        self.wrap_u = wrap_u;
+        self.wrap_v = wrap_v;
+    }
+
+    pub fn mip_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    // Muestreo bilineal del nivel 0
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        self.levels[0].bilinear(u, v, self.wrap_u, self.wrap_v)
+    }
+
+    // Muestreo trilineal: interpola entre los dos niveles más cercanos a lod.
+    // Sin mipmaps construidos equivale a sample
+    pub fn sample_trilinear(&self, u: f32, v: f32, lod: f32) -> Color {
+        let max_level = (self.levels.len() - 1) as f32;
+        let lod = lod.clamp(0.0, max_level);
+        let lower = lod.floor() as usize;
+        let upper = (lower + 1).min(self.levels.len() - 1);
+
+        let a = self.levels[lower].bilinear(u, v, self.wrap_u, self.wrap_v);
+        let b = self.levels[upper].bilinear(u, v, self.wrap_u, self.wrap_v);
+        a.lerp(&b, lod - lower as f32)
+    }
+}
+
+impl MipLevel {
+    fn texel(&self, x: usize, y: usize) -> Color {
+        self.data[y * self.width + x]
+    }
+
+    fn bilinear(&self, u: f32, v: f32, wrap_u: WrapMode, wrap_v: WrapMode) -> Color {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let fx = x - x.floor();
+        let fy = y - y.floor();
+
+        let (xi, yi) = (x.floor() as i64, y.floor() as i64);
+        let x0 = wrap_u.texel_index(xi, self.width);
+        let x1 = wrap_u.texel_index(xi + 1, self.width);
+        let y0 = wrap_v.texel_index(yi, self.height);
+        let y1 = wrap_v.texel_index(yi + 1, self.height);
+
+        let top = self.texel(x0, y0).lerp(&self.texel(x1, y0), fx);
+        let bottom = self.texel(x0, y1).lerp(&self.texel(x1, y1), fx);
+        top.lerp(&bottom, fy)
+    }
+}
+
+// Nivel de detalle para todo un triángulo. Sin derivadas por fragmento, se
+// compara el área que el triángulo ocupa en texels con la que ocupa en
+// píxeles: si cada píxel cubre k texels, lod = log2(sqrt(k)). Es exacto para
+// triángulos con mapeo afín y una buena aproximación cuando la perspectiva
+// varía poco dentro del triángulo
+pub fn triangle_lod(screen: [Vec3; 3], uvs: [Vec2; 3], texture: &Texture) -> f32 {
+    let texel = |uv: &Vec2| Vec3::new(uv.x * texture.width() as f32, uv.y * texture.height() as f32, 0.0);
+    let pixel_area = triangle_area_2d(&screen[0], &screen[1], &screen[2]).abs();
+    let texel_area = triangle_area_2d(&texel(&uvs[0]), &texel(&uvs[1]), &texel(&uvs[2])).abs();
+
+    if pixel_area <= f32::EPSILON || texel_area <= f32::EPSILON {
+        return 0.0;
+    }
+
+    (0.5 * (texel_area / pixel_area).log2()).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tablero de ajedrez blanco y negro de size x size texels
+    fn checkerboard(size: usize) -> Texture {
+        let data = (0..size * size)
+            .map(|i| if (i % size + i / size).is_multiple_of(2) { Color::new(255, 255, 255) } else { Color::black() })
+            .collect();
+        Texture::new(size, size, data)
+    }
+
+    #[test]
+    fn a_far_surface_samples_closer_to_the_average_than_the_base_level() {
+        let mut texture = checkerboard(16);
+        texture.build_mips();
+        assert_eq!(texture.mip_count(), 5);
+
+        // La textura completa dentro de un triángulo de 4 píxeles de lado
+        let uvs = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
+        let far = [Vec3::new(0.0, 0.0, 0.5), Vec3::new(4.0, 0.0, 0.5), Vec3::new(0.0, 4.0, 0.5)];
+        let near = [Vec3::new(0.0, 0.0, 0.5), Vec3::new(16.0, 0.0, 0.5), Vec3::new(0.0, 16.0, 0.5)];
+        let far_lod = triangle_lod(far, uvs, &texture);
+        assert!((far_lod - 2.0).abs() < 1e-4, "{}", far_lod);
+        assert_eq!(triangle_lod(near, uvs, &texture), 0.0);
+
+        // Centro del primer texel (blanco)
+        let (u, v) = (0.5 / 16.0, 0.5 / 16.0);
+        let average = 127.5;
+        let base = texture.sample(u, v).luminance() * 255.0;
+        let filtered = texture.sample_trilinear(u, v, far_lod).luminance() * 255.0;
+        assert!((filtered - average).abs() < (base - average).abs(), "{} vs {}", filtered, base);
+        assert!((filtered - average).abs() < 2.0, "{}", filtered);
+    }
+
+    #[test]
+    fn u_repeats_while_v_clamps_under_a_mixed_wrap() {
+        // Rampa de 4x4: el rojo sube con la columna y el verde con la fila
+        let data = (0..16).map(|i| Color::new((i % 4 * 80) as u8, (i / 4 * 80) as u8, 0)).collect();
+        let mut texture = Texture::new(4, 4, data);
+        texture.set_wrap(WrapMode::Repeat, WrapMode::Clamp);
+
+        // Centros de texel: una vuelta más en u cae en la misma columna
+        assert_eq!(texture.sample(1.125, 0.375), texture.sample(0.125, 0.375));
+        assert_eq!(texture.sample(2.625, 0.625), texture.sample(0.625, 0.625));
+        // Pasado el borde en v se queda en la última fila
+        assert_eq!(texture.sample(0.125, 1.5), texture.sample(0.125, 0.875));
+        assert_eq!(texture.sample(0.125, 1.5), Color::new(0, 240, 0));
+
+        // Repitiendo también en v, 1.5 volvería a la mitad de la textura
+        texture.set_wrap(WrapMode::Repeat, WrapMode::Repeat);
+        assert_ne!(texture.sample(0.125, 1.5), Color::new(0, 240, 0));
+    }
+}
@@ -2,31 +2,74 @@ use nalgebra_glm::{Vec3, dot};
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
 use crate::color::Color;
+use crate::raster::{barycentric, covers, edge_function_fixed, is_top_left, sample_pattern, snap_to_subpixel, to_fixed, triangle_area_2d, RasterPrecision};
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+// Con samples > 1 (MSAA) la cobertura se evalúa en varios puntos por píxel y se
+// guarda como máscara en el fragmento, pero el fragmento se genera (y se
+// sombrea) una sola vez, con los atributos interpolados en el centro del píxel
+pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, light_dir: &Vec3, samples: usize, precision: RasterPrecision) -> Vec<Fragment> {
   let mut fragments = Vec::new();
-  let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+  let (a, b, c) = match precision {
+    RasterPrecision::Float => (v1.transformed_position, v2.transformed_position, v3.transformed_position),
+    RasterPrecision::FixedPoint => (
+      snap_to_subpixel(&v1.transformed_position),
+      snap_to_subpixel(&v2.transformed_position),
+      snap_to_subpixel(&v3.transformed_position),
+    ),
+  };
 
   let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
 
-  let light_dir = Vec3::new(0.0, 0.0, 1.0);
+  let light_dir = light_dir.normalize();
 
-  let triangle_area = edge_function(&a, &b, &c);
+  let triangle_area = triangle_area_2d(&a, &b, &c);
+  if triangle_area == 0.0 {
+    return fragments;
+  }
+
+  let top_left_1 = is_top_left(&b, &c, triangle_area);
+  let top_left_2 = is_top_left(&c, &a, triangle_area);
+  let top_left_3 = is_top_left(&a, &b, triangle_area);
+  let pattern = sample_pattern(samples);
+  let fixed = |v: &Vec3| (to_fixed(v.x), to_fixed(v.y));
+  let (fa, fb, fc) = (fixed(&a), fixed(&b), fixed(&c));
+  let area_sign = if triangle_area > 0.0 { 1 } else { -1 };
+  let covers_fixed = |edge: i64, top_left: bool| {
+    let weight = edge * area_sign;
+    weight > 0 || (weight == 0 && top_left)
+  };
 
   for y in min_y..=max_y {
     for x in min_x..=max_x {
-      let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
-
-      let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
+      let mut coverage = 0u32;
+      for (i, (offset_x, offset_y)) in pattern.iter().enumerate() {
+        let sample = Vec3::new(x as f32 + offset_x, y as f32 + offset_y, 0.0);
+        let covered = match precision {
+          RasterPrecision::Float => {
+            let weights = barycentric(&sample, &a, &b, &c);
+            covers(weights.x, top_left_1) && covers(weights.y, top_left_2) && covers(weights.z, top_left_3)
+          }
+          RasterPrecision::FixedPoint => {
+            let p = fixed(&sample);
+            covers_fixed(edge_function_fixed(fb, fc, p), top_left_1)
+              && covers_fixed(edge_function_fixed(fc, fa, p), top_left_2)
+              && covers_fixed(edge_function_fixed(fa, fb, p), top_left_3)
+          }
+        };
+        if covered {
+          coverage |= 1 << i;
+        }
+      }
 
-      if w1 >= 0.0 && w1 <= 1.0 && 
-         w2 >= 0.0 && w2 <= 1.0 &&
-         w3 >= 0.0 && w3 <= 1.0 {
+      if coverage != 0 {
+        let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+        let weights = barycentric(&point, &a, &b, &c);
+        let (w1, w2, w3) = (weights.x, weights.y, weights.z);
 
         let normal = v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3;
         let normal = normal.normalize();
 
-        let intensity = dot(&normal, &light_dir).max(0.0);
+        let intensity = dot(&normal, &light_dir).clamp(0.0, 1.0);
 
         let base_color = Color::new(100, 100, 100);
         let lit_color = base_color * intensity;
@@ -35,17 +78,19 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
 
         let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
 
-        fragments.push(
-            Fragment::new(
-                x as f32,
-                y as f32,
-                lit_color,
-                depth,
-                normal,
-                intensity,
-                vertex_position,
-            )
+        let mut fragment = Fragment::new(
+            x as f32,
+            y as f32,
+            lit_color,
+            depth,
+            normal,
+            intensity,
+            vertex_position,
         );
+        fragment.coverage = coverage;
+        fragment.vertex_color = Color::from_hdr(v1.color.scale_hdr(w1) + v2.color.scale_hdr(w2) + v3.color.scale_hdr(w3));
+        fragment.tex_coords = v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3;
+        fragments.push(fragment);
       }
     }
   }
@@ -62,14 +107,68 @@ fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i3
     (min_x, min_y, max_x, max_y)
 }
 
-fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) -> (f32, f32, f32) {
-    let w1 = edge_function(b, c, p) / area;
-    let w2 = edge_function(c, a, p) / area;
-    let w3 = edge_function(a, b, p) / area;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+    use std::collections::HashMap;
+
+    // Vértice ya en pantalla (transformed_position) con la normal hacia la cámara
+    fn screen_vertex(x: f32, y: f32) -> Vertex {
+        let mut vertex = Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::zeros());
+        vertex.transformed_position = Vec3::new(x, y, 0.5);
+        vertex.transformed_normal = vertex.normal;
+        vertex
+    }
 
-    (w1, w2, w3)
-}
+    // Cuántas veces se generó cada píxel al rasterizar los triángulos
+    fn pixel_counts(triangles: &[[Vertex; 3]], precision: RasterPrecision) -> HashMap<(i32, i32), u32> {
+        let mut counts = HashMap::new();
+        for [a, b, c] in triangles {
+            for fragment in triangle(a, b, c, &Vec3::new(0.0, 0.0, 1.0), 1, precision) {
+                *counts.entry((fragment.position.x as i32, fragment.position.y as i32)).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn pixels_on_a_shared_edge_are_drawn_exactly_once() {
+        // Cuadrado de 16x16 partido por la diagonal: los centros de los píxeles
+        // (x + 0.5, x + 0.5) caen justo sobre la arista compartida
+        let corners = [screen_vertex(0.0, 0.0), screen_vertex(16.0, 0.0), screen_vertex(16.0, 16.0), screen_vertex(0.0, 16.0)];
+        let triangles = [
+            [corners[0].clone(), corners[1].clone(), corners[2].clone()],
+            [corners[0].clone(), corners[2].clone(), corners[3].clone()],
+        ];
+        let counts = pixel_counts(&triangles, RasterPrecision::Float);
+
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(counts.get(&(x, y)), Some(&1), "pixel ({}, {})", x, y);
+            }
+        }
+        assert_eq!(counts.len(), 16 * 16);
+    }
 
-fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
-    (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
-}
\ No newline at end of file
+    #[test]
+    fn fixed_point_leaves_no_gaps_along_a_shared_edge() {
+        // Cuadrado corrido una fracción de píxel, partido por cada una de sus diagonales
+        let corners = [(0.37, 0.37), (16.37, 0.37), (16.37, 16.37), (0.37, 16.37)].map(|(x, y)| screen_vertex(x, y));
+        let splits = [[0, 1, 2, 0, 2, 3], [0, 1, 3, 1, 2, 3]];
+
+        for split in splits {
+            let triangles = [
+                [corners[split[0]].clone(), corners[split[1]].clone(), corners[split[2]].clone()],
+                [corners[split[3]].clone(), corners[split[4]].clone(), corners[split[5]].clone()],
+            ];
+            let counts = pixel_counts(&triangles, RasterPrecision::FixedPoint);
+            for y in 0..16 {
+                for x in 0..16 {
+                    assert_eq!(counts.get(&(x, y)), Some(&1), "pixel ({}, {}) with split {:?}", x, y, split);
+                }
+            }
+            assert_eq!(counts.len(), 16 * 16);
+        }
+    }
+}
@@ -17,7 +17,8 @@ impl Vertex {
       position,
       normal,
       tex_coords,
-      color: Color::black(),
+      // Blanco: multiplicar por el color de vértice no cambia una malla sin pintar
+      color: Color::new(255, 255, 255),
       transformed_position: position,
       transformed_normal: normal,
     }
@@ -46,7 +47,7 @@ impl Default for Vertex {
       position: Vec3::new(0.0, 0.0, 0.0),
       normal: Vec3::new(0.0, 1.0, 0.0),
       tex_coords: Vec2::new(0.0, 0.0),
-      color: Color::black(),
+      color: Color::new(255, 255, 255),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
     }